@@ -0,0 +1,328 @@
+//! Per-repository agent configuration.
+//!
+//! Repositories can commit a `.claude/agent.toml` to tune the agent:
+//!
+//! ```toml
+//! [review]
+//! ignore = ["docs/**", "*.snap"]
+//! focus = ["security", "error handling"]
+//! severity_threshold = "warning"
+//! instructions = "We use anyhow in binaries and thiserror in libraries."
+//! max_diff_size = 500000
+//! max_changed_lines = 5000
+//! auto_approve = false
+//! suggestion_check = "php -l {file}"
+//! auto_describe = true
+//!
+//! [commands]
+//! allowed = ["make test"]
+//! denied = ["npm install"]
+//! allow_pipes = true
+//! setup = ["composer install --no-interaction"]
+//!
+//! [commands.fix]
+//! allowed = ["make fmt"]
+//!
+//! [[analyzers]]
+//! name = "phpstan"
+//! command = "vendor/bin/phpstan analyse --error-format=json --no-progress {files}"
+//! format = "phpstan"
+//! files = ["*.php"]
+//! ```
+
+use std::path::Path;
+
+use glob::Pattern;
+use serde::Deserialize;
+
+use claude_agent_core::IssueSeverity;
+
+use crate::mr_reviewer::AnalyzerSettings;
+use crate::policy::CommandRule;
+
+/// Location of the config file, relative to the repository root.
+pub const CONFIG_PATH: &str = ".claude/agent.toml";
+
+/// Error loading the repository config.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {CONFIG_PATH}: {0}")]
+    Read(#[from] std::io::Error),
+
+    #[error("invalid {CONFIG_PATH}: {0}")]
+    Parse(#[from] toml::de::Error),
+
+    #[error("invalid {CONFIG_PATH}: {0}")]
+    Invalid(String),
+}
+
+/// Contents of `.claude/agent.toml`. Every field is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub review: ReviewSettings,
+    pub commands: CommandSettings,
+    /// Static analyzers whose findings on changed lines are given to the
+    /// reviewer.
+    pub analyzers: Vec<AnalyzerSettings>,
+}
+
+/// `[review]` section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReviewSettings {
+    /// Glob patterns of files left out of the review.
+    pub ignore: Vec<String>,
+    /// Areas the reviewer should pay particular attention to.
+    pub focus: Vec<String>,
+    /// Issues below this severity are not posted.
+    pub severity_threshold: IssueSeverity,
+    /// Extra instructions for the reviewer.
+    pub instructions: Option<String>,
+    /// Diffs larger than this many bytes are not reviewed.
+    pub max_diff_size: Option<usize>,
+    /// Diffs adding and removing more than this many lines are not reviewed.
+    pub max_changed_lines: Option<usize>,
+    /// Whether the agent may approve PRs.
+    pub auto_approve: bool,
+    /// Syntax check or linter that suggested changes must pass, run in the
+    /// checkout with `{file}` replaced by the changed file.
+    pub suggestion_check: Option<String>,
+    /// Write the PR description before the first review when the author
+    /// left it empty.
+    pub auto_describe: bool,
+}
+
+impl Default for ReviewSettings {
+    fn default() -> Self {
+        Self {
+            ignore: Vec::new(),
+            focus: Vec::new(),
+            severity_threshold: IssueSeverity::Info,
+            instructions: None,
+            max_diff_size: None,
+            max_changed_lines: None,
+            auto_approve: true,
+            suggestion_check: None,
+            auto_describe: false,
+        }
+    }
+}
+
+/// `[commands]` section.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandSettings {
+    /// Commands the agent may run in addition to the built-in allowlist,
+    /// written as command prefixes (`"make test"`).
+    pub allowed: Vec<String>,
+    /// Commands the agent may never run, even if built in.
+    pub denied: Vec<String>,
+    /// Allow piping between allowed commands.
+    pub allow_pipes: bool,
+    /// Overrides for review jobs (`[commands.review]`).
+    pub review: JobCommandSettings,
+    /// Overrides for jobs that change code (`[commands.fix]`).
+    pub fix: JobCommandSettings,
+    /// Commands run after clone, before the agent starts.
+    pub setup: Vec<String>,
+}
+
+/// `[commands.review]` and `[commands.fix]` sections.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobCommandSettings {
+    pub allowed: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+impl RepoConfig {
+    /// Load the config from a checkout. A missing file gives the defaults.
+    pub fn load(repo_path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(repo_path.join(CONFIG_PATH)) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parse and validate config file contents.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        for pattern in &self.review.ignore {
+            Pattern::new(pattern).map_err(|e| {
+                ConfigError::Invalid(format!("review.ignore: bad pattern {pattern:?}: {e}"))
+            })?;
+        }
+        if self.review.max_diff_size == Some(0) {
+            return Err(ConfigError::Invalid(
+                "review.max_diff_size must be greater than 0".into(),
+            ));
+        }
+        if self.review.max_changed_lines == Some(0) {
+            return Err(ConfigError::Invalid(
+                "review.max_changed_lines must be greater than 0".into(),
+            ));
+        }
+        if self
+            .review
+            .suggestion_check
+            .as_ref()
+            .is_some_and(|cmd| cmd.trim().is_empty())
+        {
+            return Err(ConfigError::Invalid(
+                "review.suggestion_check must not be empty".into(),
+            ));
+        }
+        if self.commands.setup.iter().any(|cmd| cmd.trim().is_empty()) {
+            return Err(ConfigError::Invalid(
+                "commands.setup must not contain empty commands".into(),
+            ));
+        }
+        for analyzer in &self.analyzers {
+            if analyzer.name.trim().is_empty() || analyzer.command.trim().is_empty() {
+                return Err(ConfigError::Invalid(
+                    "analyzers: name and command must not be empty".into(),
+                ));
+            }
+            for pattern in &analyzer.files {
+                Pattern::new(pattern).map_err(|e| {
+                    ConfigError::Invalid(format!(
+                        "analyzers.{}.files: bad pattern {pattern:?}: {e}",
+                        analyzer.name
+                    ))
+                })?;
+            }
+        }
+        let commands = &self.commands;
+        let rules = [
+            ("commands.allowed", &commands.allowed),
+            ("commands.denied", &commands.denied),
+            ("commands.review.allowed", &commands.review.allowed),
+            ("commands.review.denied", &commands.review.denied),
+            ("commands.fix.allowed", &commands.fix.allowed),
+            ("commands.fix.denied", &commands.fix.denied),
+        ];
+        for (field, list) in rules {
+            for rule in list {
+                CommandRule::parse(rule).map_err(|e| {
+                    ConfigError::Invalid(format!("{field}: bad command {rule:?}: {e}"))
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `path` matches one of the `review.ignore` patterns.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        self.review
+            .ignore
+            .iter()
+            .filter_map(|p| Pattern::new(p).ok())
+            .any(|p| p.matches(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mr_reviewer::AnalyzerFormat;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = RepoConfig::parse(
+            r#"
+[review]
+ignore = ["docs/**", "*.snap"]
+focus = ["security"]
+severity_threshold = "warning"
+instructions = "Prefer early returns."
+max_diff_size = 1000
+max_changed_lines = 200
+auto_approve = false
+suggestion_check = "cargo check"
+auto_describe = true
+
+[commands]
+allowed = ["make test"]
+setup = ["npm ci"]
+
+[[analyzers]]
+name = "semgrep"
+command = "semgrep scan --sarif --quiet {files}"
+format = "sarif"
+files = ["*.php", "*.js"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.review.focus, vec!["security"]);
+        assert_eq!(config.review.severity_threshold, IssueSeverity::Warning);
+        assert_eq!(config.review.max_diff_size, Some(1000));
+        assert_eq!(config.review.max_changed_lines, Some(200));
+        assert!(!config.review.auto_approve);
+        assert_eq!(
+            config.review.suggestion_check.as_deref(),
+            Some("cargo check")
+        );
+        assert!(config.review.auto_describe);
+        assert_eq!(config.commands.setup, vec!["npm ci"]);
+        assert_eq!(config.analyzers.len(), 1);
+        assert_eq!(config.analyzers[0].format, AnalyzerFormat::Sarif);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = RepoConfig::parse("").unwrap();
+        assert!(config.review.auto_approve);
+        assert!(!config.review.auto_describe);
+        assert_eq!(config.review.severity_threshold, IssueSeverity::Info);
+        assert!(config.review.max_diff_size.is_none());
+
+        let missing = RepoConfig::load(Path::new("/nonexistent")).unwrap();
+        assert!(missing.commands.allowed.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let unknown = RepoConfig::parse("[review]\nignored = []\n").unwrap_err();
+        assert!(unknown.to_string().contains("unknown field `ignored`"));
+
+        let severity = RepoConfig::parse("[review]\nseverity_threshold = \"high\"\n").unwrap_err();
+        assert!(
+            severity
+                .to_string()
+                .starts_with("invalid .claude/agent.toml")
+        );
+
+        let pattern = RepoConfig::parse("[review]\nignore = [\"a/***\"]\n").unwrap_err();
+        assert!(pattern.to_string().contains("review.ignore"));
+
+        let size = RepoConfig::parse("[review]\nmax_diff_size = 0\n").unwrap_err();
+        assert!(size.to_string().contains("max_diff_size"));
+
+        let lines = RepoConfig::parse("[review]\nmax_changed_lines = 0\n").unwrap_err();
+        assert!(lines.to_string().contains("max_changed_lines"));
+
+        let setup = RepoConfig::parse("[commands]\nsetup = [\" \"]\n").unwrap_err();
+        assert!(setup.to_string().contains("commands.setup"));
+
+        let allowed = RepoConfig::parse("[commands]\nallowed = [\"make; rm\"]\n").unwrap_err();
+        assert!(allowed.to_string().contains("commands.allowed"));
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let config = RepoConfig::parse("[review]\nignore = [\"docs/**\", \"*.snap\"]\n").unwrap();
+
+        assert!(config.is_ignored("docs/guide.md"));
+        assert!(config.is_ignored("tests/snapshots/a.snap"));
+        assert!(!config.is_ignored("src/lib.rs"));
+        assert!(!config.is_ignored("src/docs.rs"));
+    }
+}
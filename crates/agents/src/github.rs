@@ -0,0 +1,707 @@
+//! Minimal GitHub client for posting PR comments and reviews and managing
+//! review threads.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::{Method, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use claude_agent_core::ReviewDecision;
+
+pub const GITHUB_API: &str = "https://api.github.com";
+
+/// Longest we wait for a rate limit to reset before giving up.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// How many times a rate-limited request is retried.
+const MAX_RATE_LIMIT_RETRIES: u32 = 2;
+
+/// Errors from the GitHub API.
+#[derive(Debug, thiserror::Error)]
+pub enum GithubError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("GitHub API {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("GitHub rate limit exceeded, retry after {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("GitHub GraphQL error: {0}")]
+    Graphql(String),
+
+    #[error("no GitHub client configured")]
+    NotConfigured,
+}
+
+/// GitHub review event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewEvent {
+    Approve,
+    RequestChanges,
+    Comment,
+}
+
+impl From<ReviewDecision> for ReviewEvent {
+    fn from(decision: ReviewDecision) -> Self {
+        match decision {
+            ReviewDecision::Approved => ReviewEvent::Approve,
+            ReviewDecision::ChangesRequested => ReviewEvent::RequestChanges,
+            ReviewDecision::Comment => ReviewEvent::Comment,
+        }
+    }
+}
+
+/// A page of review threads of a pull request, with their first comments.
+const REVIEW_THREADS_QUERY: &str = r#"query($owner: String!, $name: String!, $number: Int!, $after: String) {
+  repository(owner: $owner, name: $name) {
+    pullRequest(number: $number) {
+      reviewThreads(first: 100, after: $after) {
+        pageInfo { hasNextPage endCursor }
+        nodes {
+          id
+          isResolved
+          isOutdated
+          path
+          line
+          comments(first: 20) {
+            nodes { databaseId body viewerDidAuthor author { login } }
+          }
+        }
+      }
+    }
+  }
+}"#;
+
+const RESOLVE_THREAD_MUTATION: &str = r#"mutation($id: ID!) {
+  resolveReviewThread(input: { threadId: $id }) { thread { id } }
+}"#;
+
+/// An inline review thread on a pull request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewThread {
+    /// GraphQL node ID, used to resolve the thread.
+    pub id: String,
+    pub path: String,
+    pub line: Option<u32>,
+    pub is_resolved: bool,
+    /// The code the thread points at has changed since it was started.
+    pub is_outdated: bool,
+    pub comments: Vec<ThreadComment>,
+}
+
+impl ReviewThread {
+    /// ID of the comment that started the thread.
+    pub fn comment_id(&self) -> Option<u64> {
+        self.comments.first().map(|c| c.id)
+    }
+
+    /// Whether the thread was started by the authenticated user (this bot).
+    pub fn is_own(&self) -> bool {
+        self.comments.first().is_some_and(|c| c.by_viewer)
+    }
+}
+
+/// A comment in a review thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadComment {
+    pub id: u64,
+    pub author: String,
+    pub body: String,
+    /// Written by the authenticated user.
+    pub by_viewer: bool,
+}
+
+#[derive(Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+/// A page of a GraphQL connection.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Page<T> {
+    page_info: PageInfo,
+    nodes: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawThread {
+    id: String,
+    is_resolved: bool,
+    is_outdated: bool,
+    path: String,
+    line: Option<u32>,
+    comments: Nodes<RawComment>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawComment {
+    database_id: Option<u64>,
+    body: String,
+    viewer_did_author: bool,
+    author: Option<RawAuthor>,
+}
+
+#[derive(Deserialize)]
+struct RawAuthor {
+    login: String,
+}
+
+impl From<RawThread> for ReviewThread {
+    fn from(raw: RawThread) -> Self {
+        Self {
+            id: raw.id,
+            path: raw.path,
+            line: raw.line,
+            is_resolved: raw.is_resolved,
+            is_outdated: raw.is_outdated,
+            comments: raw
+                .comments
+                .nodes
+                .into_iter()
+                .map(|c| ThreadComment {
+                    id: c.database_id.unwrap_or_default(),
+                    author: c.author.map(|a| a.login).unwrap_or_else(|| "ghost".into()),
+                    body: c.body,
+                    by_viewer: c.viewer_did_author,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// GitHub REST API client authenticated with a token.
+#[derive(Debug, Clone)]
+pub struct GithubClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl GithubClient {
+    pub fn new(token: &str) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent("claude-agent")
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            http,
+            base_url: GITHUB_API.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    /// Use a different API root (GitHub Enterprise, tests).
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Post a comment on a PR's conversation, returning the comment ID.
+    pub async fn post_issue_comment(
+        &self,
+        repo: &str,
+        number: &str,
+        body: &str,
+    ) -> Result<u64, GithubError> {
+        let path = format!("/repos/{repo}/issues/{number}/comments");
+        let response = self
+            .post(&path, &serde_json::json!({ "body": body }))
+            .await?;
+        Ok(id_of(&response))
+    }
+
+    /// Submit a review without inline comments, returning the review ID.
+    pub async fn submit_review(
+        &self,
+        repo: &str,
+        number: &str,
+        event: ReviewEvent,
+        body: &str,
+    ) -> Result<u64, GithubError> {
+        let payload = serde_json::json!({ "event": event, "body": body });
+        self.create_review(repo, number, &payload).await
+    }
+
+    /// Create a review from any serializable review payload (e.g. one with
+    /// inline comments), returning the review ID.
+    pub async fn create_review(
+        &self,
+        repo: &str,
+        number: &str,
+        review: &impl Serialize,
+    ) -> Result<u64, GithubError> {
+        let path = format!("/repos/{repo}/pulls/{number}/reviews");
+        let response = self.post(&path, review).await?;
+        Ok(id_of(&response))
+    }
+
+    /// Replace the body of a pull request.
+    pub async fn update_pull_request_body(
+        &self,
+        repo: &str,
+        number: &str,
+        body: &str,
+    ) -> Result<(), GithubError> {
+        let path = format!("/repos/{repo}/pulls/{number}");
+        self.send(Method::PATCH, &path, &serde_json::json!({ "body": body }))
+            .await?;
+        Ok(())
+    }
+
+    /// Fetch all inline review threads of a PR.
+    pub async fn review_threads(
+        &self,
+        repo: &str,
+        number: &str,
+    ) -> Result<Vec<ReviewThread>, GithubError> {
+        let (owner, name) = repo
+            .split_once('/')
+            .ok_or_else(|| GithubError::Graphql(format!("invalid repository {repo:?}")))?;
+        let number: u64 = number
+            .parse()
+            .map_err(|_| GithubError::Graphql(format!("invalid PR number {number:?}")))?;
+
+        let mut threads = Vec::new();
+        let mut after: Option<String> = None;
+        loop {
+            let variables = serde_json::json!({
+                "owner": owner,
+                "name": name,
+                "number": number,
+                "after": after,
+            });
+            let mut data = self.graphql(REVIEW_THREADS_QUERY, variables).await?;
+            let page = data["repository"]["pullRequest"]["reviewThreads"].take();
+            let page: Page<RawThread> = serde_json::from_value(page)
+                .map_err(|e| GithubError::Graphql(format!("unexpected review threads: {e}")))?;
+            threads.extend(page.nodes.into_iter().map(ReviewThread::from));
+            match page.page_info.end_cursor {
+                Some(cursor) if page.page_info.has_next_page => after = Some(cursor),
+                _ => return Ok(threads),
+            }
+        }
+    }
+
+    /// Reply to an inline review comment, returning the reply's ID.
+    pub async fn reply_to_review_comment(
+        &self,
+        repo: &str,
+        number: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<u64, GithubError> {
+        let path = format!("/repos/{repo}/pulls/{number}/comments/{comment_id}/replies");
+        let response = self
+            .post(&path, &serde_json::json!({ "body": body }))
+            .await?;
+        Ok(id_of(&response))
+    }
+
+    /// Mark a review thread as resolved.
+    pub async fn resolve_review_thread(&self, thread_id: &str) -> Result<(), GithubError> {
+        self.graphql(
+            RESOLVE_THREAD_MUTATION,
+            serde_json::json!({ "id": thread_id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Run a GraphQL query, returning its `data`.
+    async fn graphql(&self, query: &str, variables: Value) -> Result<Value, GithubError> {
+        let body = serde_json::json!({ "query": query, "variables": variables });
+        let mut response = self
+            .send_to(Method::POST, &self.graphql_url(), &body)
+            .await?;
+        if let Some(errors) = response["errors"].as_array()
+            && !errors.is_empty()
+        {
+            let messages: Vec<&str> = errors
+                .iter()
+                .filter_map(|e| e["message"].as_str())
+                .collect();
+            return Err(GithubError::Graphql(messages.join("; ")));
+        }
+        Ok(response["data"].take())
+    }
+
+    /// The GraphQL endpoint: `/graphql` on github.com, but `/api/graphql`
+    /// next to the `/api/v3` REST root of GitHub Enterprise Server.
+    fn graphql_url(&self) -> String {
+        match self.base_url.strip_suffix("/api/v3") {
+            Some(host) => format!("{host}/api/graphql"),
+            None => format!("{}/graphql", self.base_url),
+        }
+    }
+
+    async fn post(&self, path: &str, body: &impl Serialize) -> Result<Value, GithubError> {
+        self.send(Method::POST, path, body).await
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: &impl Serialize,
+    ) -> Result<Value, GithubError> {
+        self.send_to(method, &format!("{}{}", self.base_url, path), body)
+            .await
+    }
+
+    /// Send a JSON body, retrying while rate limited.
+    async fn send_to(
+        &self,
+        method: Method,
+        url: &str,
+        body: &impl Serialize,
+    ) -> Result<Value, GithubError> {
+        let mut retries = 0;
+        loop {
+            let response = self
+                .http
+                .request(method.clone(), url)
+                .bearer_auth(&self.token)
+                .header("Accept", "application/vnd.github+json")
+                .header("X-GitHub-Api-Version", "2022-11-28")
+                .json(body)
+                .send()
+                .await?;
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response.json().await?);
+            }
+            if is_rate_limited(&response) {
+                let retry_after = rate_limit_wait(&response);
+                let can_wait = retry_after.is_some_and(|wait| wait <= MAX_RATE_LIMIT_WAIT);
+                if retries < MAX_RATE_LIMIT_RETRIES && can_wait {
+                    let wait = retry_after.unwrap_or_default();
+                    info!(url = %url, wait_secs = wait.as_secs(), "GitHub rate limited, waiting");
+                    tokio::time::sleep(wait).await;
+                    retries += 1;
+                    continue;
+                }
+                warn!(url = %url, "GitHub rate limit exceeded");
+                return Err(GithubError::RateLimited { retry_after });
+            }
+
+            let message = response.text().await.unwrap_or_default();
+            warn!(url = %url, status = %status, "GitHub API request failed");
+            return Err(GithubError::Api {
+                status: status.as_u16(),
+                message,
+            });
+        }
+    }
+}
+
+fn id_of(response: &Value) -> u64 {
+    response["id"].as_u64().unwrap_or_default()
+}
+
+/// Primary rate limits answer 403/429 with no remaining requests; secondary
+/// limits send `retry-after`.
+fn is_rate_limited(response: &Response) -> bool {
+    let headers = response.headers();
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::FORBIDDEN => {
+            headers.contains_key("retry-after")
+                || headers
+                    .get("x-ratelimit-remaining")
+                    .is_some_and(|v| v.as_bytes() == b"0")
+        }
+        _ => false,
+    }
+}
+
+/// How long to wait before retrying, from `retry-after` or `x-ratelimit-reset`.
+fn rate_limit_wait(response: &Response) -> Option<Duration> {
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    if let Some(secs) = header("retry-after") {
+        return Some(Duration::from_secs(secs));
+    }
+    let reset = header("x-ratelimit-reset")?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn client(server: &MockServer) -> GithubClient {
+        GithubClient::new("ghs_test").with_base_url(&server.uri())
+    }
+
+    #[tokio::test]
+    async fn test_post_issue_comment() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/repos/owner/repo/issues/7/comments"))
+            .and(header("authorization", "Bearer ghs_test"))
+            .and(body_json(serde_json::json!({ "body": "hello" })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({ "id": 42 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let id = client(&server)
+            .post_issue_comment("owner/repo", "7", "hello")
+            .await
+            .unwrap();
+        assert_eq!(id, 42);
+    }
+
+    #[tokio::test]
+    async fn test_submit_review() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/repos/owner/repo/pulls/7/reviews"))
+            .and(body_json(
+                serde_json::json!({ "event": "REQUEST_CHANGES", "body": "fix it" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": 9 })))
+            .mount(&server)
+            .await;
+
+        let id = client(&server)
+            .submit_review("owner/repo", "7", ReviewEvent::RequestChanges, "fix it")
+            .await
+            .unwrap();
+        assert_eq!(id, 9);
+    }
+
+    #[tokio::test]
+    async fn test_update_pull_request_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/owner/repo/pulls/7"))
+            .and(body_json(serde_json::json!({ "body": "## Summary" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": 1 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client(&server)
+            .update_pull_request_body("owner/repo", "7", "## Summary")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_review_threads() {
+        let server = MockServer::start().await;
+        let thread = |id: &str, resolved: bool, own: bool| {
+            serde_json::json!({
+                "id": id,
+                "isResolved": resolved,
+                "isOutdated": false,
+                "path": "src/lib.rs",
+                "line": 12,
+                "comments": { "nodes": [{
+                    "databaseId": 101,
+                    "body": "unwrap may panic",
+                    "viewerDidAuthor": own,
+                    "author": { "login": "review-bot" }
+                }] }
+            })
+        };
+        let page = |threads: Vec<Value>, next: Option<&str>| {
+            serde_json::json!({
+                "data": { "repository": { "pullRequest": { "reviewThreads": {
+                    "pageInfo": { "hasNextPage": next.is_some(), "endCursor": next },
+                    "nodes": threads
+                } } } }
+            })
+        };
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_partial_json(
+                serde_json::json!({ "variables": { "after": null } }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(page(
+                vec![thread("T_1", false, true), thread("T_2", true, false)],
+                Some("C_2"),
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_partial_json(
+                serde_json::json!({ "variables": { "after": "C_2" } }),
+            ))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(page(vec![thread("T_3", false, true)], None)),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let threads = client(&server)
+            .review_threads("owner/repo", "7")
+            .await
+            .unwrap();
+        assert_eq!(threads.len(), 3);
+        assert_eq!(threads[0].id, "T_1");
+        assert_eq!(threads[0].line, Some(12));
+        assert_eq!(threads[0].comment_id(), Some(101));
+        assert!(threads[0].is_own());
+        assert!(threads[1].is_resolved);
+        assert!(!threads[1].is_own());
+        assert_eq!(threads[2].id, "T_3");
+    }
+
+    #[tokio::test]
+    async fn test_graphql_url_on_enterprise() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "resolveReviewThread": { "thread": { "id": "T_1" } } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let enterprise =
+            GithubClient::new("ghs_test").with_base_url(&format!("{}/api/v3/", server.uri()));
+        enterprise.resolve_review_thread("T_1").await.unwrap();
+
+        assert_eq!(
+            GithubClient::new("t").graphql_url(),
+            "https://api.github.com/graphql"
+        );
+        assert_eq!(
+            GithubClient::new("t")
+                .with_base_url("https://github.example.com/api/v3")
+                .graphql_url(),
+            "https://github.example.com/api/graphql"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graphql_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": null,
+                "errors": [{ "message": "Could not resolve to a node" }]
+            })))
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .resolve_review_thread("T_1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubError::Graphql(ref m) if m == "Could not resolve to a node"));
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(422).set_body_string("Validation Failed"))
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .post_issue_comment("owner/repo", "7", "x")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, GithubError::Api { status: 422, ref message } if message == "Validation Failed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("retry-after", "0"),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({ "id": 5 })))
+            .mount(&server)
+            .await;
+
+        let id = client(&server)
+            .post_issue_comment("owner/repo", "7", "x")
+            .await
+            .unwrap();
+        assert_eq!(id, 5);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_too_long() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "3600"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .post_issue_comment("owner/repo", "7", "x")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            GithubError::RateLimited { retry_after: Some(wait) } if wait.as_secs() == 3600
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_is_not_rate_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-ratelimit-remaining", "4999")
+                    .set_body_string("Resource not accessible by integration"),
+            )
+            .mount(&server)
+            .await;
+
+        let err = client(&server)
+            .post_issue_comment("owner/repo", "7", "x")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, GithubError::Api { status: 403, .. }));
+    }
+}
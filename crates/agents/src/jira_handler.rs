@@ -4,90 +4,8 @@
 
 use std::path::Path;
 
-/// System prompt for the Jira ticket handler agent.
-pub const JIRA_HANDLER_SYSTEM_PROMPT: &str = r###"You are a developer assistant. A Jira ticket has been assigned to you and your job is to analyze it and implement a fix or feature.
-
-## Instructions
-
-1. **Understand the ticket**: Read the ticket summary, description, and any comments carefully
-2. **Explore the codebase**: Use Glob and Grep to find relevant files
-3. **Read related code**: Use Read to understand the existing implementation
-4. **Implement the change**: Use Edit to make the necessary changes
-5. **Test if possible**: If there are relevant tests, run them to verify
-6. **Commit and push**: Create a branch, commit the changes, and push
-
-## Creating a Branch and Committing
-
-```bash
-# Create a fix branch (already on target branch)
-git checkout -b jira-fix/<ISSUE_KEY_LOWERCASE>
-
-# After making changes:
-git add -A
-git commit -m "<TYPE>: <ISSUE_KEY> - <brief description>
-
-<longer explanation if needed>
-
-Resolves <JIRA_URL>"
-git push origin HEAD
-```
-
-Where TYPE is one of:
-- `fix` - for bug fixes
-- `feat` - for new features
-- `refactor` - for code refactoring
-- `docs` - for documentation changes
-- `chore` - for maintenance tasks
-
-## Creating the Pull Request
-
-After pushing, create the PR:
-
-```bash
-gh pr create --title "<TYPE>: <ISSUE_KEY> - <brief description>" \
-  --body "## Summary
-
-<what this PR does>
-
-## Changes
-
-- <bullet points of changes>
-
-## Testing
-
-<how to test the changes>
-
-## Jira Ticket
-
-<JIRA_URL>"
-```
-
-## Project Guidelines
-
-Before making changes, check if `.claude/mr.md` exists in the repo root. If it does, read it and follow those project-specific guidelines for code changes and PR creation.
-
-## Rules
-
-- Focus on what the ticket asks for. Do NOT refactor, improve, or change unrelated code.
-- If the ticket is ambiguous, implement the most sensible interpretation and document your assumptions.
-- If you cannot complete the task, explain what's blocking you and what investigation is needed.
-- Do not add new dependencies unless absolutely necessary.
-- Preserve existing code style and patterns.
-- Write clear commit messages that explain the "why" not just the "what".
-
-## Do NOT Do
-
-- **Do NOT create or modify database migrations.** Migrations must be created by a human. If the ticket requires a migration, exit with a message explaining what migration is needed.
-- **Do NOT modify infrastructure files** (Dockerfiles, CI/CD configs, Kubernetes manifests, deployment scripts).
-- **Do NOT change environment variables or secrets.**
-
-## Available Tools
-
-- Read files with the Read tool
-- Edit files with the Edit tool
-- Search files with Glob and Grep
-- Run commands: git, gh pr, test runners, linters
-"###;
+use crate::policy::{CommandPolicy, JobKind};
+use crate::templates::{TemplateVars, Templates};
 
 /// Context for a Jira ticket job.
 #[derive(Debug, Clone)]
@@ -111,6 +29,7 @@ pub struct JiraTicketContext {
 pub struct JiraHandlerAgent {
     context: JiraTicketContext,
     _repo_path: std::path::PathBuf,
+    templates: Templates,
 }
 
 impl JiraHandlerAgent {
@@ -118,12 +37,19 @@ impl JiraHandlerAgent {
         Self {
             context,
             _repo_path: repo_path.as_ref().to_path_buf(),
+            templates: Templates::builtin(),
         }
     }
 
+    /// Use these prompt templates instead of the built-in ones.
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
     pub fn build_prompt(&self) -> String {
         let mut prompt = String::new();
-        prompt.push_str(JIRA_HANDLER_SYSTEM_PROMPT);
+        prompt.push_str(&self.system_prompt());
         prompt.push_str("\n\n---\n\n");
         self.append_ticket_details(&mut prompt);
         self.append_description(&mut prompt);
@@ -132,8 +58,22 @@ impl JiraHandlerAgent {
         prompt
     }
 
-    pub fn system_prompt(&self) -> &'static str {
-        JIRA_HANDLER_SYSTEM_PROMPT
+    pub fn system_prompt(&self) -> String {
+        let vars = TemplateVars::new()
+            .string("issue_key", &self.context.issue_key)
+            .string("branch", self.branch())
+            .string("issue_url", &self.context.web_url);
+        self.templates.prompt("jira_ticket", &vars)
+    }
+
+    /// Command policy for this job: the built-in fix allowlist, pushing only
+    /// to the fix branch.
+    pub fn command_policy(&self) -> CommandPolicy {
+        CommandPolicy::for_job(JobKind::Fix).with_push_branch(&self.branch())
+    }
+
+    fn branch(&self) -> String {
+        format!("jira-fix/{}", self.context.issue_key.to_lowercase())
     }
 
     fn append_ticket_details(&self, prompt: &mut String) {
@@ -192,8 +132,8 @@ impl JiraHandlerAgent {
         prompt.push_str("2. Explore the codebase to find relevant files\n");
         prompt.push_str("3. Implement the required changes\n");
         prompt.push_str(&format!(
-            "4. Create branch `jira-fix/{}` and commit\n",
-            self.context.issue_key.to_lowercase()
+            "4. Create branch `{}` and commit\n",
+            self.branch()
         ));
         prompt.push_str("5. Push and create a PR\n");
     }
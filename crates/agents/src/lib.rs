@@ -1,9 +1,22 @@
 //! Agent implementations for different tasks.
 
+pub mod config;
+pub mod github;
 pub mod jira_handler;
 pub mod mr_reviewer;
+pub mod policy;
 pub mod sentry_fixer;
+pub mod templates;
 
-pub use jira_handler::{JIRA_HANDLER_SYSTEM_PROMPT, JiraHandlerAgent, JiraTicketContext};
-pub use mr_reviewer::{MrReviewAgent, SYSTEM_PROMPT};
-pub use sentry_fixer::{SENTRY_FIX_SYSTEM_PROMPT, SentryFixContext, SentryFixerAgent};
+pub use config::{CONFIG_PATH, ConfigError, RepoConfig};
+pub use github::{GithubClient, GithubError, ReviewEvent, ReviewThread};
+pub use jira_handler::{JiraHandlerAgent, JiraTicketContext};
+pub use mr_reviewer::{
+    AnalyzerSettings, CiFailure, DEFAULT_MAX_CHUNK_CHARS, DependencyReport, DiffPlan, Ecosystem,
+    GithubReview, Interdiff, MrReviewAgent, PrDescription, SizeChange, SummarizedFile,
+    TicketContext, format_review_threads, merge_results, needs_description, review_dependencies,
+    run_analyzers, split_files,
+};
+pub use policy::{CommandPolicy, CommandRule, JobKind, PolicyDenial};
+pub use sentry_fixer::{SentryFixContext, SentryFixerAgent};
+pub use templates::{TEMPLATE_OVERRIDE_DIR, TemplateError, TemplateVars, Templates};
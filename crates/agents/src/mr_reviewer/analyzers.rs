@@ -0,0 +1,459 @@
+//! Static analyzers run on the changed files before the review.
+//!
+//! Each `[[analyzers]]` entry of the repository config is run in the
+//! checkout and its SARIF or tool-specific JSON output is parsed into
+//! [`Finding`]s. Only findings on lines added by the PR reach the prompt,
+//! where the reviewer confirms or dismisses them.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::process::Command;
+
+use glob::Pattern;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+use claude_agent_core::{Diff, IssueSeverity};
+
+/// Most characters of a finding's message kept for the prompt.
+const MAX_MESSAGE_CHARS: usize = 300;
+
+/// Output format of an analyzer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyzerFormat {
+    /// SARIF 2.1 (semgrep `--sarif`, many others).
+    Sarif,
+    /// `cargo clippy --message-format=json`.
+    Clippy,
+    /// `phpstan analyse --error-format=json`.
+    Phpstan,
+    /// `eslint -f json`.
+    Eslint,
+}
+
+/// `[[analyzers]]` entry of the repository config.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnalyzerSettings {
+    /// Name shown next to its findings.
+    pub name: String,
+    /// Command run in the checkout; `{files}` is replaced by the changed
+    /// files matching `files`.
+    pub command: String,
+    pub format: AnalyzerFormat,
+    /// Glob patterns of the files the analyzer applies to (all if empty).
+    #[serde(default)]
+    pub files: Vec<String>,
+}
+
+/// A finding reported by an analyzer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub tool: String,
+    pub rule: Option<String>,
+    pub severity: IssueSeverity,
+    pub file: String,
+    pub line: u32,
+    pub end_line: Option<u32>,
+    pub message: String,
+}
+
+impl AnalyzerSettings {
+    fn applies_to(&self, path: &str) -> bool {
+        self.files.is_empty()
+            || self
+                .files
+                .iter()
+                .filter_map(|p| Pattern::new(p).ok())
+                .any(|p| p.matches(path))
+    }
+}
+
+/// Run the analyzers on the changed files and keep the findings on lines
+/// added by `diff`.
+pub fn run_analyzers(
+    repo_path: &Path,
+    analyzers: &[AnalyzerSettings],
+    changed_files: &[String],
+    diff: &str,
+) -> Vec<Finding> {
+    let added = added_lines(diff);
+    let mut findings = Vec::new();
+    for analyzer in analyzers {
+        let files: Vec<&String> = changed_files
+            .iter()
+            .filter(|f| analyzer.applies_to(f) && repo_path.join(f).is_file())
+            .collect();
+        if files.is_empty() {
+            continue;
+        }
+        let quoted: Vec<String> = files.iter().map(|f| shell_quote(f)).collect();
+        let command = analyzer.command.replace("{files}", &quoted.join(" "));
+
+        let output = match Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .current_dir(repo_path)
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) => {
+                warn!(analyzer = %analyzer.name, error = %e, "Failed to run analyzer");
+                continue;
+            }
+        };
+        // Analyzers exit non-zero when they find something, so only the
+        // output decides whether the run worked.
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let parsed = match parse_findings(analyzer, &stdout, repo_path) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                warn!(
+                    analyzer = %analyzer.name,
+                    error = %e,
+                    stderr = %stderr.chars().take(MAX_MESSAGE_CHARS).collect::<String>(),
+                    "Could not parse analyzer output"
+                );
+                continue;
+            }
+        };
+        let total = parsed.len();
+        let on_changed: Vec<Finding> = parsed
+            .into_iter()
+            .filter(|f| on_added_line(f, &added))
+            .collect();
+        info!(
+            analyzer = %analyzer.name,
+            total,
+            on_changed_lines = on_changed.len(),
+            "Ran analyzer"
+        );
+        findings.extend(on_changed);
+    }
+    findings
+}
+
+/// Parse analyzer output in the analyzer's format.
+pub fn parse_findings(
+    analyzer: &AnalyzerSettings,
+    output: &str,
+    repo_path: &Path,
+) -> Result<Vec<Finding>, serde_json::Error> {
+    let mut findings = match analyzer.format {
+        AnalyzerFormat::Sarif => parse_sarif(&serde_json::from_str(output)?, &analyzer.name),
+        AnalyzerFormat::Clippy => parse_clippy(output, &analyzer.name),
+        AnalyzerFormat::Phpstan => parse_phpstan(&serde_json::from_str(output)?, &analyzer.name),
+        AnalyzerFormat::Eslint => parse_eslint(&serde_json::from_str(output)?, &analyzer.name),
+    };
+    for finding in &mut findings {
+        finding.file = relative_path(&finding.file, repo_path);
+        if finding.message.chars().count() > MAX_MESSAGE_CHARS {
+            finding.message = finding.message.chars().take(MAX_MESSAGE_CHARS).collect();
+            finding.message.push('…');
+        }
+    }
+    Ok(findings)
+}
+
+fn parse_sarif(sarif: &Value, name: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for run in sarif["runs"].as_array().into_iter().flatten() {
+        let tool = run["tool"]["driver"]["name"].as_str().unwrap_or(name);
+        for result in run["results"].as_array().into_iter().flatten() {
+            let location = &result["locations"][0]["physicalLocation"];
+            let (Some(file), Some(line)) = (
+                location["artifactLocation"]["uri"].as_str(),
+                location["region"]["startLine"].as_u64(),
+            ) else {
+                continue;
+            };
+            let severity = match result["level"].as_str().unwrap_or("warning") {
+                "error" => IssueSeverity::Error,
+                "warning" => IssueSeverity::Warning,
+                _ => IssueSeverity::Info,
+            };
+            findings.push(Finding {
+                tool: tool.to_string(),
+                rule: result["ruleId"].as_str().map(String::from),
+                severity,
+                file: file.to_string(),
+                line: line as u32,
+                end_line: location["region"]["endLine"].as_u64().map(|l| l as u32),
+                message: result["message"]["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+    }
+    findings
+}
+
+/// Clippy prints one JSON message per line, mixed with build artifacts.
+fn parse_clippy(output: &str, name: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for line in output.lines() {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if message["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &message["message"];
+        let severity = match message["level"].as_str() {
+            Some("error") => IssueSeverity::Error,
+            Some("warning") => IssueSeverity::Warning,
+            _ => continue,
+        };
+        let spans = message["spans"].as_array().into_iter().flatten();
+        let Some(span) = spans.clone().find(|s| s["is_primary"] == true) else {
+            continue;
+        };
+        let (Some(file), Some(line)) = (span["file_name"].as_str(), span["line_start"].as_u64())
+        else {
+            continue;
+        };
+        findings.push(Finding {
+            tool: name.to_string(),
+            rule: message["code"]["code"].as_str().map(String::from),
+            severity,
+            file: file.to_string(),
+            line: line as u32,
+            end_line: span["line_end"].as_u64().map(|l| l as u32),
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+        });
+    }
+    findings
+}
+
+fn parse_phpstan(report: &Value, name: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for (file, entry) in report["files"].as_object().into_iter().flatten() {
+        for message in entry["messages"].as_array().into_iter().flatten() {
+            let Some(line) = message["line"].as_u64() else {
+                continue;
+            };
+            findings.push(Finding {
+                tool: name.to_string(),
+                rule: message["identifier"].as_str().map(String::from),
+                severity: IssueSeverity::Error,
+                file: file.clone(),
+                line: line as u32,
+                end_line: None,
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    findings
+}
+
+fn parse_eslint(report: &Value, name: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for file in report.as_array().into_iter().flatten() {
+        let Some(path) = file["filePath"].as_str() else {
+            continue;
+        };
+        for message in file["messages"].as_array().into_iter().flatten() {
+            let Some(line) = message["line"].as_u64() else {
+                continue;
+            };
+            let severity = match message["severity"].as_u64() {
+                Some(2) => IssueSeverity::Error,
+                _ => IssueSeverity::Warning,
+            };
+            findings.push(Finding {
+                tool: name.to_string(),
+                rule: message["ruleId"].as_str().map(String::from),
+                severity,
+                file: path.to_string(),
+                line: line as u32,
+                end_line: message["endLine"].as_u64().map(|l| l as u32),
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+            });
+        }
+    }
+    findings
+}
+
+/// Make a reported path relative to the checkout.
+fn relative_path(path: &str, repo_path: &Path) -> String {
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    let path = Path::new(path)
+        .strip_prefix(repo_path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    path.trim_start_matches("./").to_string()
+}
+
+/// New-side line numbers of added lines, keyed by file path.
+fn added_lines(diff: &str) -> HashMap<String, HashSet<u32>> {
+    Diff::parse(diff)
+        .files
+        .iter()
+        .filter_map(|file| Some((file.new_path.clone()?, file.added_lines().collect())))
+        .collect()
+}
+
+fn on_added_line(finding: &Finding, added: &HashMap<String, HashSet<u32>>) -> bool {
+    let Some(lines) = added.get(&finding.file) else {
+        return false;
+    };
+    let end = finding.end_line.unwrap_or(finding.line).max(finding.line);
+    (finding.line..=end).any(|line| lines.contains(&line))
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyzer(format: AnalyzerFormat) -> AnalyzerSettings {
+        AnalyzerSettings {
+            name: "tool".into(),
+            command: "true".into(),
+            format,
+            files: Vec::new(),
+        }
+    }
+
+    const DIFF: &str = "\
+diff --git a/src/app.php b/src/app.php
+--- a/src/app.php
++++ b/src/app.php
+@@ -10,3 +10,4 @@
+ $a = 1;
++$b = $_GET['id'];
+ $c = 3;
+ $d = 4;
+";
+
+    #[test]
+    fn test_parse_sarif() {
+        let sarif = r#"{"runs": [{"tool": {"driver": {"name": "semgrep"}}, "results": [{
+            "ruleId": "php.lang.security.injection",
+            "level": "error",
+            "message": {"text": "User input reaches a query"},
+            "locations": [{"physicalLocation": {
+                "artifactLocation": {"uri": "file:///work/repo/src/app.php"},
+                "region": {"startLine": 11, "endLine": 11}
+            }}]
+        }]}]}"#;
+        let findings = parse_findings(
+            &analyzer(AnalyzerFormat::Sarif),
+            sarif,
+            Path::new("/work/repo"),
+        )
+        .unwrap();
+        assert_eq!(
+            findings,
+            vec![Finding {
+                tool: "semgrep".into(),
+                rule: Some("php.lang.security.injection".into()),
+                severity: IssueSeverity::Error,
+                file: "src/app.php".into(),
+                line: 11,
+                end_line: Some(11),
+                message: "User input reaches a query".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_clippy() {
+        let output = r#"{"reason":"compiler-artifact","target":{}}
+{"reason":"compiler-message","message":{"level":"warning","message":"this `if` has identical blocks","code":{"code":"clippy::if_same_then_else"},"spans":[{"file_name":"src/lib.rs","line_start":4,"line_end":6,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"level":"note","message":"n","code":null,"spans":[]}}
+{"reason":"build-finished","success":true}"#;
+        let findings =
+            parse_findings(&analyzer(AnalyzerFormat::Clippy), output, Path::new("/w")).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].rule.as_deref(),
+            Some("clippy::if_same_then_else")
+        );
+        assert_eq!((findings[0].line, findings[0].end_line), (4, Some(6)));
+    }
+
+    #[test]
+    fn test_parse_phpstan_and_eslint() {
+        let phpstan = r#"{"totals": {"errors": 0, "file_errors": 1}, "files": {
+            "/work/repo/src/app.php": {"errors": 1, "messages": [
+                {"message": "Undefined variable: $x", "line": 12, "identifier": "variable.undefined"}
+            ]}
+        }}"#;
+        let findings = parse_findings(
+            &analyzer(AnalyzerFormat::Phpstan),
+            phpstan,
+            Path::new("/work/repo"),
+        )
+        .unwrap();
+        assert_eq!(findings[0].file, "src/app.php");
+        assert_eq!(findings[0].rule.as_deref(), Some("variable.undefined"));
+
+        let eslint = r#"[{"filePath": "/work/repo/web/app.js", "messages": [
+            {"ruleId": "no-unused-vars", "severity": 1, "message": "'x' is unused", "line": 3, "endLine": 3}
+        ]}]"#;
+        let findings = parse_findings(
+            &analyzer(AnalyzerFormat::Eslint),
+            eslint,
+            Path::new("/work/repo"),
+        )
+        .unwrap();
+        assert_eq!(findings[0].file, "web/app.js");
+        assert_eq!(findings[0].severity, IssueSeverity::Warning);
+    }
+
+    #[test]
+    fn test_on_added_line() {
+        let added = added_lines(DIFF);
+        let finding = |line| Finding {
+            tool: "t".into(),
+            rule: None,
+            severity: IssueSeverity::Warning,
+            file: "src/app.php".into(),
+            line,
+            end_line: None,
+            message: "m".into(),
+        };
+        assert!(on_added_line(&finding(11), &added));
+        assert!(!on_added_line(&finding(10), &added));
+        assert!(!on_added_line(&finding(12), &added));
+    }
+
+    #[test]
+    fn test_run_analyzers() {
+        let repo = std::env::temp_dir().join(format!("analyzers-{}", std::process::id()));
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::write(repo.join("src/app.php"), "<?php\n").unwrap();
+        let report = r#"[{"filePath": "src/app.php", "messages": [
+            {"ruleId": "r", "severity": 2, "message": "on added line", "line": 11},
+            {"ruleId": "r", "severity": 2, "message": "on context line", "line": 12}
+        ]}]"#;
+        let analyzers = vec![
+            AnalyzerSettings {
+                name: "fake".into(),
+                command: format!("test -f {{files}} && printf '%s' '{report}'; exit 1"),
+                format: AnalyzerFormat::Eslint,
+                files: vec!["*.php".into()],
+            },
+            AnalyzerSettings {
+                name: "js-only".into(),
+                command: "echo never run".into(),
+                format: AnalyzerFormat::Eslint,
+                files: vec!["*.js".into()],
+            },
+        ];
+        let changed = vec!["src/app.php".to_string(), "src/deleted.php".to_string()];
+        let findings = run_analyzers(&repo, &analyzers, &changed, DIFF);
+        std::fs::remove_dir_all(&repo).unwrap();
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].message, "on added line");
+    }
+}
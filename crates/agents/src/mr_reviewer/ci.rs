@@ -0,0 +1,367 @@
+//! Failing CI checks, for lint-fix jobs.
+//!
+//! The server attaches the failing check's log, trimmed to the failing step.
+//! Errors with a file and line are parsed out of it for the prompt, and the
+//! step's commands are offered for verification when the tools they need are
+//! installed in the worker.
+
+use std::path::Path;
+
+use super::MrReviewAgent;
+
+/// Maximum number of parsed errors listed in the prompt.
+const MAX_CI_ERRORS: usize = 50;
+
+/// Failing CI check of the PR.
+#[derive(Debug, Clone)]
+pub struct CiFailure {
+    pub name: String,
+    pub url: Option<String>,
+    /// Log of the failing step.
+    pub log: String,
+}
+
+/// An error reported by a CI tool on a source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CiError {
+    pub file: String,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+/// Parse errors with a file location out of a CI log.
+///
+/// Understands workflow commands (`::error file=…,line=…::msg`), the
+/// `path:line[:col]: message` format most linters print, rustc/clippy's
+/// `--> path:line:col` arrows and ESLint's stylish output.
+pub fn parse_ci_errors(log: &str) -> Vec<CiError> {
+    let mut errors: Vec<CiError> = Vec::new();
+    let mut pending: Option<String> = None;
+    let mut stylish_file: Option<String> = None;
+
+    for raw in log.lines() {
+        let line = raw.strip_prefix("##[error]").unwrap_or(raw);
+        let trimmed = line.trim();
+        let found = if let Some(error) = parse_workflow_command(trimmed) {
+            Some(error)
+        } else if let Some(location) = trimmed.strip_prefix("--> ") {
+            pending.take().and_then(|message| {
+                let (file, line) = split_location(location)?;
+                Some(CiError {
+                    file,
+                    line: Some(line),
+                    message,
+                })
+            })
+        } else if let Some(message) = rustc_message(trimmed) {
+            pending = Some(message);
+            None
+        } else if let Some(error) = parse_colon_format(trimmed) {
+            Some(error)
+        } else if let Some(file) = &stylish_file
+            && let Some((line, message)) = parse_stylish_entry(trimmed)
+        {
+            Some(CiError {
+                file: file.clone(),
+                line: Some(line),
+                message,
+            })
+        } else {
+            if is_path(trimmed) {
+                stylish_file = Some(normalize_path(trimmed));
+            } else if trimmed.is_empty() {
+                stylish_file = None;
+            }
+            None
+        };
+
+        if let Some(error) = found
+            && !errors.contains(&error)
+        {
+            errors.push(error);
+        }
+    }
+    errors
+}
+
+/// `::error file=src/a.js,line=3,col=5::message`
+fn parse_workflow_command(line: &str) -> Option<CiError> {
+    let rest = line
+        .strip_prefix("::error ")
+        .or_else(|| line.strip_prefix("::warning "))?;
+    let (params, message) = rest.split_once("::")?;
+    let mut file = None;
+    let mut line = None;
+    for param in params.split(',') {
+        match param.split_once('=') {
+            Some(("file", value)) => file = Some(normalize_path(value)),
+            Some(("line", value)) => line = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(CiError {
+        file: file?,
+        line,
+        message: message.trim().to_string(),
+    })
+}
+
+/// Header of a rustc/clippy diagnostic, whose location follows on an
+/// `-->` line.
+fn rustc_message(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("error")?;
+    let is_header = rest.starts_with(": ") || (rest.starts_with('[') && rest.contains("]: "));
+    (is_header && !rest.contains("could not compile")).then(|| line.to_string())
+}
+
+/// `path:line[:col]: message` (also `path:line message` and
+/// `path:line:col - message`).
+fn parse_colon_format(line: &str) -> Option<CiError> {
+    let (path, rest) = line.split_once(':')?;
+    if !is_path(path) {
+        return None;
+    }
+    let (line_no, mut rest) = split_number(rest)?;
+    if let Some(after) = rest.strip_prefix(':') {
+        rest = split_number(after).map_or(after, |(_, after_col)| after_col);
+    }
+    let message = rest
+        .trim_start_matches(':')
+        .trim()
+        .trim_start_matches('-')
+        .trim();
+    (!message.is_empty()).then(|| CiError {
+        file: normalize_path(path),
+        line: Some(line_no),
+        message: message.to_string(),
+    })
+}
+
+/// Split a leading number off `text`.
+fn split_number(text: &str) -> Option<(u32, &str)> {
+    let end = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let number = text[..end].parse().ok()?;
+    Some((number, &text[end..]))
+}
+
+/// `  12:5  error  'x' is not defined  no-undef` under a file name.
+fn parse_stylish_entry(line: &str) -> Option<(u32, String)> {
+    let (position, rest) = line.split_once(char::is_whitespace)?;
+    let (line_no, col) = position.split_once(':')?;
+    col.parse::<u32>().ok()?;
+    let line_no = line_no.parse().ok()?;
+    let rest = rest.trim_start();
+    let message = rest
+        .strip_prefix("error")
+        .or_else(|| rest.strip_prefix("warning"))?;
+    Some((line_no, message.trim().to_string()))
+}
+
+fn split_location(location: &str) -> Option<(String, u32)> {
+    let mut parts = location.trim().splitn(3, ':');
+    let file = parts.next()?;
+    let line = parts.next()?.parse().ok()?;
+    Some((normalize_path(file), line))
+}
+
+/// Whether a token looks like a source file path.
+fn is_path(text: &str) -> bool {
+    let name = text.rsplit('/').next().unwrap_or(text);
+    !text.is_empty()
+        && !text.contains(char::is_whitespace)
+        && !text.contains("://")
+        && name.contains('.')
+        && !name.starts_with('.')
+        && name
+            .rsplit('.')
+            .next()
+            .is_some_and(|ext| !ext.is_empty() && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Make a path from a CI runner relative to the repository root.
+fn normalize_path(path: &str) -> String {
+    let path = path.trim();
+    // Actions checks out to /home/runner/work/<repo>/<repo>/.
+    if let Some((_, rest)) = path.split_once("/work/") {
+        let mut parts = rest.splitn(3, '/');
+        if let (Some(_), Some(_), Some(relative)) = (parts.next(), parts.next(), parts.next()) {
+            return relative.to_string();
+        }
+    }
+    path.trim_start_matches("./").to_string()
+}
+
+/// Commands of the failing steps (`##[group]Run <command>` lines).
+pub fn step_commands(log: &str) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    for line in log.lines() {
+        if let Some(command) = line.strip_prefix("##[group]Run ") {
+            let command = command.trim().to_string();
+            // `uses:` steps show the action, not a command.
+            if !command.contains('@') && !commands.contains(&command) {
+                commands.push(command);
+            }
+        }
+    }
+    commands
+}
+
+/// Whether the program a command runs exists in the repository (e.g.
+/// `vendor/bin/phpstan`, `npx eslint` with `node_modules/.bin/eslint`) or on
+/// `PATH`.
+pub fn is_available(repo_path: &Path, command: &str) -> bool {
+    let mut words = command.split_whitespace();
+    let Some(mut program) = words.next() else {
+        return false;
+    };
+    if program == "npx" {
+        let Some(bin) = words.find(|w| !w.starts_with('-')) else {
+            return false;
+        };
+        return repo_path.join("node_modules/.bin").join(bin).exists();
+    }
+    if let Some(relative) = program.strip_prefix("./") {
+        program = relative;
+    }
+    if program.contains('/') {
+        return repo_path.join(program).exists();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+impl MrReviewAgent {
+    /// Set the failing CI check for a lint-fix job.
+    pub fn with_ci_failure(mut self, failure: Option<CiFailure>) -> Self {
+        self.ci_failure = failure;
+        self
+    }
+
+    /// Commands of the failing step that can run in this checkout and are
+    /// allowed by the command policy.
+    pub fn verification_commands(&self) -> Vec<String> {
+        let Some(failure) = &self.ci_failure else {
+            return Vec::new();
+        };
+        let policy = self.command_policy();
+        step_commands(&failure.log)
+            .into_iter()
+            .filter(|command| is_available(&self.repo_path, command))
+            .filter(|command| policy.check(command).is_ok())
+            .collect()
+    }
+
+    /// Describe the failing check: its errors with locations, the log and
+    /// the commands to verify the fix with.
+    pub(super) fn append_ci_failure(&self, prompt: &mut String) {
+        let Some(failure) = &self.ci_failure else {
+            prompt.push_str("\n## Failing Check\n\n");
+            prompt.push_str(
+                "_The CI log is not available. Run the project's linters, if installed, to find the errors._\n",
+            );
+            return;
+        };
+
+        prompt.push_str(&format!("\n## Failing Check: {}\n\n", failure.name));
+        if let Some(url) = &failure.url {
+            prompt.push_str(&format!("**Check run**: {url}\n\n"));
+        }
+
+        let errors = parse_ci_errors(&failure.log);
+        if !errors.is_empty() {
+            prompt.push_str("### Errors\n\n");
+            for error in errors.iter().take(MAX_CI_ERRORS) {
+                let location = match error.line {
+                    Some(line) => format!("{}:{line}", error.file),
+                    None => error.file.clone(),
+                };
+                prompt.push_str(&format!("- `{location}` {}\n", error.message));
+            }
+            if errors.len() > MAX_CI_ERRORS {
+                prompt.push_str(&format!("- …and {} more\n", errors.len() - MAX_CI_ERRORS));
+            }
+            prompt.push('\n');
+        }
+
+        prompt.push_str("### Log\n\n```\n");
+        prompt.push_str(failure.log.trim_end());
+        prompt.push_str("\n```\n");
+
+        prompt.push_str("\n### Verification\n\n");
+        let commands = self.verification_commands();
+        if commands.is_empty() {
+            prompt.push_str(
+                "None of the failing commands can run in this environment. Do not try to install tools; check your fixes against the errors above by reading the code.\n",
+            );
+        } else {
+            prompt.push_str(
+                "After fixing, re-run these commands from the failing step (the only ones available here) and make sure the errors are gone:\n\n",
+            );
+            for command in commands {
+                prompt.push_str(&format!("- `{command}`\n"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ci_errors() {
+        let log = "\
+##[group]Run cargo clippy -- -D warnings
+cargo clippy -- -D warnings
+##[endgroup]
+error: unused variable: `x`
+  --> src/main.rs:4:9
+   |
+error[E0308]: mismatched types
+ --> /home/runner/work/app/app/src/lib.rs:10:5
+error: could not compile `app`
+./web/app.js:3:1: Missing semicolon.
+src/User.php:12 Undefined variable $name
+::error file=src/api.ts,line=7,col=2::Unexpected any
+##[error]Process completed with exit code 101.
+
+/repo/web/form.js
+  12:5  error  'x' is not defined  no-undef
+  14:1  warning  Unexpected console statement  no-console
+
+See https://example.com:443/docs for more
+";
+        let errors = parse_ci_errors(log);
+        let rendered: Vec<String> = errors
+            .iter()
+            .map(|e| format!("{}:{} {}", e.file, e.line.unwrap_or(0), e.message))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "src/main.rs:4 error: unused variable: `x`",
+                "src/lib.rs:10 error[E0308]: mismatched types",
+                "web/app.js:3 Missing semicolon.",
+                "src/User.php:12 Undefined variable $name",
+                "src/api.ts:7 Unexpected any",
+                "/repo/web/form.js:12 'x' is not defined  no-undef",
+                "/repo/web/form.js:14 Unexpected console statement  no-console",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_step_commands_and_availability() {
+        let log = "##[group]Run actions/checkout@v4\n##[endgroup]\n##[group]Run vendor/bin/phpstan analyse\n##[group]Run sh -c 'true'\n";
+        let commands = step_commands(log);
+        assert_eq!(commands, vec!["vendor/bin/phpstan analyse", "sh -c 'true'"]);
+
+        let repo = Path::new("/nonexistent-repo");
+        assert!(!is_available(repo, "vendor/bin/phpstan analyse"));
+        assert!(!is_available(repo, "npx eslint ."));
+        assert!(is_available(repo, "sh -c 'true'"));
+        assert!(!is_available(repo, "definitely-not-installed-tool --check"));
+    }
+}
@@ -0,0 +1,907 @@
+//! Dependency changes from lockfile diffs.
+//!
+//! The lockfiles before and after the PR are parsed into package lists and
+//! compared. Added and upgraded packages are checked against a local mirror
+//! of the OSV advisory database (the RustSec, npm and Packagist advisories
+//! are all published as OSV JSON), so no network access is needed. Major
+//! version jumps and new transitive dependencies are flagged for the
+//! reviewer.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+/// Maximum number of dependency changes listed in a section.
+const MAX_LISTED_CHANGES: usize = 40;
+
+/// Error parsing a lockfile.
+#[derive(Debug, thiserror::Error)]
+pub enum LockfileError {
+    #[error("invalid Cargo.lock: {0}")]
+    Toml(#[from] toml::de::Error),
+
+    #[error("invalid lockfile: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Package ecosystem of a lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Composer,
+}
+
+impl Ecosystem {
+    /// Recognise a supported lockfile by its file name.
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path.rsplit('/').next()? {
+            "Cargo.lock" => Some(Ecosystem::Cargo),
+            "package-lock.json" => Some(Ecosystem::Npm),
+            "composer.lock" => Some(Ecosystem::Composer),
+            _ => None,
+        }
+    }
+
+    /// Ecosystem name used by OSV advisories.
+    fn osv_name(self) -> &'static str {
+        match self {
+            Ecosystem::Cargo => "crates.io",
+            Ecosystem::Npm => "npm",
+            Ecosystem::Composer => "Packagist",
+        }
+    }
+}
+
+/// A locked package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    /// Whether the project depends on the package itself rather than through
+    /// another package.
+    pub direct: bool,
+}
+
+/// How a dependency changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+
+/// A known vulnerability affecting a new package version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Advisory {
+    pub id: String,
+    /// CVE and other identifiers of the same vulnerability.
+    pub aliases: Vec<String>,
+    pub summary: Option<String>,
+}
+
+/// A package added, removed or changed by the PR.
+#[derive(Debug, Clone)]
+pub struct DependencyChange {
+    pub lockfile: String,
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    pub kind: ChangeKind,
+    pub old_version: Option<String>,
+    pub new_version: Option<String>,
+    pub direct: bool,
+    pub advisories: Vec<Advisory>,
+}
+
+impl DependencyChange {
+    /// Whether the version crosses a major version (or a minor version below
+    /// 1.0, which semver treats as breaking too).
+    pub fn is_major(&self) -> bool {
+        let (Some(old), Some(new)) = (&self.old_version, &self.new_version) else {
+            return false;
+        };
+        match (numeric_parts(old).as_slice(), numeric_parts(new).as_slice()) {
+            ([0, old_minor, ..], [0, new_minor, ..]) => old_minor != new_minor,
+            ([old_major, ..], [new_major, ..]) => old_major != new_major,
+            _ => false,
+        }
+    }
+
+    /// Whether this is a newly added package the project does not depend on
+    /// directly.
+    pub fn is_new_transitive(&self) -> bool {
+        self.kind == ChangeKind::Added && !self.direct
+    }
+
+    fn rank(&self) -> u8 {
+        if !self.advisories.is_empty() {
+            0
+        } else if self.is_major() {
+            1
+        } else if self.is_new_transitive() {
+            2
+        } else {
+            3
+        }
+    }
+
+    fn render(&self) -> String {
+        let version = match (&self.old_version, &self.new_version) {
+            (Some(old), Some(new)) => format!("{old} → {new}"),
+            (Some(version), None) | (None, Some(version)) => version.clone(),
+            (None, None) => String::new(),
+        };
+        let mut notes = vec![match self.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Upgraded => "upgraded",
+            ChangeKind::Downgraded => "downgraded",
+        }];
+        if self.is_major() {
+            notes.push("major version");
+        }
+        if self.kind == ChangeKind::Added {
+            notes.push(if self.direct { "direct" } else { "transitive" });
+        }
+        let mut line = format!("- `{}` {version} ({})", self.name, notes.join(", "));
+        for advisory in &self.advisories {
+            let aliases = if advisory.aliases.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", advisory.aliases.join(", "))
+            };
+            line.push_str(&format!("\n  - **{}**{aliases}", advisory.id));
+            if let Some(summary) = &advisory.summary {
+                line.push_str(&format!(": {}", summary.trim()));
+            }
+        }
+        line
+    }
+}
+
+/// Dependency changes of a PR across all its lockfiles.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyReport {
+    pub changes: Vec<DependencyChange>,
+    /// Whether the changes were checked against an advisory database.
+    pub advisories_checked: bool,
+}
+
+impl DependencyReport {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changes with known advisories.
+    pub fn vulnerable_count(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|c| !c.advisories.is_empty())
+            .count()
+    }
+
+    /// Check added and upgraded packages against the advisory database.
+    pub fn check_advisories(&mut self, db: &AdvisoryDb) {
+        for change in &mut self.changes {
+            if let Some(version) = &change.new_version {
+                change.advisories = db.matching(change.ecosystem, &change.name, version);
+            }
+        }
+        self.advisories_checked = true;
+    }
+
+    /// Render the changes as Markdown: a count line per lockfile, then the
+    /// changes, most important first.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut lockfiles: BTreeMap<&str, Vec<&DependencyChange>> = BTreeMap::new();
+        for change in &self.changes {
+            lockfiles.entry(&change.lockfile).or_default().push(change);
+        }
+        for (lockfile, changes) in &lockfiles {
+            let count = |kind| changes.iter().filter(|c| c.kind == kind).count();
+            let transitive = changes.iter().filter(|c| c.is_new_transitive()).count();
+            out.push_str(&format!(
+                "`{lockfile}`: {} added ({transitive} transitive), {} removed, {} upgraded, {} downgraded\n",
+                count(ChangeKind::Added),
+                count(ChangeKind::Removed),
+                count(ChangeKind::Upgraded),
+                count(ChangeKind::Downgraded),
+            ));
+        }
+        if !self.advisories_checked {
+            out.push_str(
+                "\n_No advisory database is available; known vulnerabilities were not checked._\n",
+            );
+        }
+
+        let mut changes: Vec<&DependencyChange> = self.changes.iter().collect();
+        changes.sort_by_key(|c| c.rank());
+        out.push('\n');
+        for change in changes.iter().take(MAX_LISTED_CHANGES) {
+            out.push_str(&change.render());
+            out.push('\n');
+        }
+        if changes.len() > MAX_LISTED_CHANGES {
+            out.push_str(&format!(
+                "- …and {} more\n",
+                changes.len() - MAX_LISTED_CHANGES
+            ));
+        }
+        out
+    }
+}
+
+/// Compare the versions of a lockfile before and after the PR. A missing
+/// side means the lockfile was added or deleted.
+pub fn lockfile_changes(
+    lockfile: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+) -> Result<Vec<DependencyChange>, LockfileError> {
+    let Some(ecosystem) = Ecosystem::from_path(lockfile) else {
+        return Ok(Vec::new());
+    };
+    let parse = |content: Option<&str>| match content {
+        Some(content) => parse_lockfile(ecosystem, content),
+        None => Ok(Vec::new()),
+    };
+    let old = group_versions(parse(old)?);
+    let new = group_versions(parse(new)?);
+
+    let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    let mut changes = Vec::new();
+    for name in names {
+        let (kind, old_version, new_version, direct) = match (old.get(name), new.get(name)) {
+            (None, Some(new)) => (ChangeKind::Added, None, Some(&new.0), new.1),
+            (Some(old), None) => (ChangeKind::Removed, Some(&old.0), None, old.1),
+            (Some(old), Some(new)) => match compare_versions(&new.0, &old.0) {
+                Ordering::Greater => (ChangeKind::Upgraded, Some(&old.0), Some(&new.0), new.1),
+                Ordering::Less => (ChangeKind::Downgraded, Some(&old.0), Some(&new.0), new.1),
+                Ordering::Equal => continue,
+            },
+            (None, None) => continue,
+        };
+        changes.push(DependencyChange {
+            lockfile: lockfile.to_string(),
+            ecosystem,
+            name: name.clone(),
+            kind,
+            old_version: old_version.cloned(),
+            new_version: new_version.cloned(),
+            direct,
+            advisories: Vec::new(),
+        });
+    }
+    Ok(changes)
+}
+
+/// Highest locked version of every package, and whether any copy is a
+/// direct dependency. npm can lock several versions of one package.
+fn group_versions(packages: Vec<Package>) -> HashMap<String, (String, bool)> {
+    let mut grouped: HashMap<String, (String, bool)> = HashMap::new();
+    for package in packages {
+        match grouped.get_mut(&package.name) {
+            Some((version, direct)) => {
+                if compare_versions(&package.version, version) == Ordering::Greater {
+                    *version = package.version;
+                }
+                *direct |= package.direct;
+            }
+            None => {
+                grouped.insert(package.name, (package.version, package.direct));
+            }
+        }
+    }
+    grouped
+}
+
+/// Parse the packages locked by a lockfile.
+pub fn parse_lockfile(ecosystem: Ecosystem, content: &str) -> Result<Vec<Package>, LockfileError> {
+    match ecosystem {
+        Ecosystem::Cargo => parse_cargo_lock(content),
+        Ecosystem::Npm => parse_package_lock(content),
+        Ecosystem::Composer => parse_composer_lock(content),
+    }
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<CargoPackage>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// Packages without a source are workspace members (or path dependencies):
+/// they are left out, and what they depend on is direct.
+fn parse_cargo_lock(content: &str) -> Result<Vec<Package>, LockfileError> {
+    let lock: CargoLock = toml::from_str(content)?;
+    let direct: HashSet<&str> = lock
+        .package
+        .iter()
+        .filter(|p| p.source.is_none())
+        .flat_map(|p| &p.dependencies)
+        .filter_map(|dep| dep.split_whitespace().next())
+        .collect();
+    Ok(lock
+        .package
+        .iter()
+        .filter(|p| p.source.is_some())
+        .map(|p| Package {
+            name: p.name.clone(),
+            version: p.version.clone(),
+            direct: direct.contains(p.name.as_str()),
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct PackageLock {
+    /// Lockfile version 2 and 3, keyed by install path.
+    packages: Option<BTreeMap<String, NpmPackage>>,
+    /// Lockfile version 1, nested by dependency.
+    dependencies: Option<BTreeMap<String, NpmDependency>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NpmPackage {
+    version: Option<String>,
+    #[serde(default)]
+    link: bool,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    dev_dependencies: BTreeMap<String, String>,
+    #[serde(default)]
+    optional_dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct NpmDependency {
+    version: String,
+    #[serde(default)]
+    requires: BTreeMap<String, String>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, NpmDependency>,
+}
+
+fn parse_package_lock(content: &str) -> Result<Vec<Package>, LockfileError> {
+    let lock: PackageLock = serde_json::from_str(content)?;
+    if let Some(packages) = lock.packages {
+        let direct: HashSet<&String> = packages
+            .get("")
+            .map(|root| {
+                root.dependencies
+                    .keys()
+                    .chain(root.dev_dependencies.keys())
+                    .chain(root.optional_dependencies.keys())
+                    .collect()
+            })
+            .unwrap_or_default();
+        return Ok(packages
+            .iter()
+            .filter(|(_, package)| !package.link)
+            .filter_map(|(path, package)| {
+                let (parent, name) = path.rsplit_once("node_modules/")?;
+                Some(Package {
+                    name: name.to_string(),
+                    version: package.version.clone()?,
+                    direct: parent.is_empty() && direct.contains(&name.to_string()),
+                })
+            })
+            .collect());
+    }
+
+    let mut packages = Vec::new();
+    let mut required = HashSet::new();
+    fn walk<'a>(
+        dependencies: &'a BTreeMap<String, NpmDependency>,
+        top_level: bool,
+        packages: &mut Vec<(&'a str, &'a str, bool)>,
+        required: &mut HashSet<&'a str>,
+    ) {
+        for (name, dependency) in dependencies {
+            packages.push((name, &dependency.version, top_level));
+            required.extend(dependency.requires.keys().map(String::as_str));
+            walk(&dependency.dependencies, false, packages, required);
+        }
+    }
+    let dependencies = lock.dependencies.unwrap_or_default();
+    walk(&dependencies, true, &mut packages, &mut required);
+    // Version 1 does not list the project's own dependencies: a top-level
+    // package no other package requires must be one of them.
+    Ok(packages
+        .into_iter()
+        .map(|(name, version, top_level)| Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            direct: top_level && !required.contains(name),
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct ComposerLock {
+    #[serde(default)]
+    packages: Vec<ComposerPackage>,
+    #[serde(default, rename = "packages-dev")]
+    packages_dev: Vec<ComposerPackage>,
+}
+
+#[derive(Deserialize)]
+struct ComposerPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    require: BTreeMap<String, String>,
+}
+
+/// `composer.lock` does not record which packages `composer.json` requires,
+/// so packages no other locked package requires are taken as direct.
+fn parse_composer_lock(content: &str) -> Result<Vec<Package>, LockfileError> {
+    let lock: ComposerLock = serde_json::from_str(content)?;
+    let all = lock.packages.iter().chain(&lock.packages_dev);
+    let required: HashSet<String> = all
+        .clone()
+        .flat_map(|p| p.require.keys())
+        .map(|name| name.to_lowercase())
+        .collect();
+    Ok(all
+        .map(|p| Package {
+            name: p.name.clone(),
+            version: p.version.clone(),
+            direct: !required.contains(&p.name.to_lowercase()),
+        })
+        .collect())
+}
+
+/// Numeric components of a version's release part ("v1.2.3-beta" → [1, 2, 3]).
+fn numeric_parts(version: &str) -> Vec<u64> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let release = version.split(['-', '+']).next().unwrap_or("");
+    release
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// Compare versions by their numeric release parts; a pre-release sorts
+/// before its release. Versions without numbers (e.g. Composer's
+/// `dev-main`) are compared as text.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (parts_a, parts_b) = (numeric_parts(a), numeric_parts(b));
+    if parts_a.is_empty() || parts_b.is_empty() {
+        return a.cmp(b);
+    }
+    let len = parts_a.len().max(parts_b.len());
+    let padded = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+    for i in 0..len {
+        match padded(&parts_a, i).cmp(&padded(&parts_b, i)) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+    }
+    let pre = |v: &str| {
+        v.split('+')
+            .next()
+            .and_then(|v| v.split_once('-'))
+            .map(|(_, pre)| pre.to_string())
+    };
+    match (pre(a), pre(b)) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => a.cmp(&b),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum OsvFile {
+    One(OsvAdvisory),
+    Many(Vec<OsvAdvisory>),
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvAdvisory {
+    id: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    summary: Option<String>,
+    withdrawn: Option<String>,
+    #[serde(default)]
+    affected: Vec<OsvAffected>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvAffected {
+    package: OsvPackage,
+    #[serde(default)]
+    ranges: Vec<OsvRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsvRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<OsvEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OsvEvent {
+    Introduced(String),
+    Fixed(String),
+    LastAffected(String),
+    Limit(String),
+}
+
+impl OsvAffected {
+    fn affects(&self, version: &str) -> bool {
+        if self.versions.iter().any(|v| v == version) {
+            return true;
+        }
+        self.ranges
+            .iter()
+            .filter(|range| range.kind != "GIT")
+            .any(|range| range.affects(version))
+    }
+}
+
+impl OsvRange {
+    /// Walk the events in version order up to `version`, as the OSV schema
+    /// describes.
+    fn affects(&self, version: &str) -> bool {
+        let event_version = |event: &OsvEvent| match event {
+            OsvEvent::Introduced(v)
+            | OsvEvent::Fixed(v)
+            | OsvEvent::LastAffected(v)
+            | OsvEvent::Limit(v) => v.clone(),
+        };
+        let mut events: Vec<&OsvEvent> = self.events.iter().collect();
+        events.sort_by(|a, b| compare_versions(&event_version(a), &event_version(b)));
+
+        let mut affected = false;
+        for event in events {
+            let at = event_version(event);
+            let order = if at == "0" {
+                Ordering::Less
+            } else {
+                compare_versions(&at, version)
+            };
+            match event {
+                OsvEvent::Introduced(_) if order != Ordering::Greater => affected = true,
+                OsvEvent::Fixed(_) | OsvEvent::Limit(_) if order != Ordering::Greater => {
+                    affected = false
+                }
+                OsvEvent::LastAffected(_) if order == Ordering::Less => affected = false,
+                _ => {}
+            }
+        }
+        affected
+    }
+}
+
+/// Advisories loaded from a local mirror of OSV JSON files.
+#[derive(Debug, Default)]
+pub struct AdvisoryDb {
+    advisories: Vec<OsvAdvisory>,
+}
+
+impl AdvisoryDb {
+    /// Load the advisories that mention any of `names` from the `*.json`
+    /// files under `dir`. Files that cannot be parsed are skipped.
+    pub fn load(dir: &Path, names: &[&str]) -> std::io::Result<Self> {
+        let mut db = AdvisoryDb::default();
+        if names.is_empty() {
+            return Ok(db);
+        }
+        let quoted: Vec<String> = names.iter().map(|name| format!("\"{name}\"")).collect();
+        let mut pending = vec![dir.to_path_buf()];
+        let mut files = 0;
+        while let Some(dir) = pending.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if path.extension().is_none_or(|ext| ext != "json") {
+                    continue;
+                }
+                files += 1;
+                let Ok(content) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                if !quoted.iter().any(|name| content.contains(name.as_str())) {
+                    continue;
+                }
+                match serde_json::from_str(&content) {
+                    Ok(OsvFile::One(advisory)) => db.advisories.push(advisory),
+                    Ok(OsvFile::Many(advisories)) => db.advisories.extend(advisories),
+                    Err(e) => debug!(path = %path.display(), error = %e, "Skipping advisory file"),
+                }
+            }
+        }
+        info!(
+            files,
+            loaded = db.advisories.len(),
+            "Loaded advisories for changed dependencies"
+        );
+        Ok(db)
+    }
+
+    /// Advisories affecting a package version.
+    pub fn matching(&self, ecosystem: Ecosystem, name: &str, version: &str) -> Vec<Advisory> {
+        self.advisories
+            .iter()
+            .filter(|advisory| advisory.withdrawn.is_none())
+            .filter(|advisory| {
+                advisory.affected.iter().any(|affected| {
+                    affected
+                        .package
+                        .ecosystem
+                        .eq_ignore_ascii_case(ecosystem.osv_name())
+                        && affected.package.name.eq_ignore_ascii_case(name)
+                        && affected.affects(version)
+                })
+            })
+            .map(|advisory| Advisory {
+                id: advisory.id.clone(),
+                aliases: advisory.aliases.clone(),
+                summary: advisory.summary.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Build the dependency report of a PR from its changed lockfiles.
+///
+/// `read` returns a lockfile's content before and after the PR. Without an
+/// advisory database directory (or if it cannot be read) the report says
+/// vulnerabilities were not checked.
+pub fn review_dependencies(
+    lockfiles: &[String],
+    advisory_db: Option<&Path>,
+    mut read: impl FnMut(&str) -> (Option<String>, Option<String>),
+) -> DependencyReport {
+    let mut report = DependencyReport::default();
+    for lockfile in lockfiles {
+        let (old, new) = read(lockfile);
+        match lockfile_changes(lockfile, old.as_deref(), new.as_deref()) {
+            Ok(changes) => report.changes.extend(changes),
+            Err(e) => warn!(lockfile = %lockfile, error = %e, "Failed to parse lockfile"),
+        }
+    }
+    if report.is_empty() {
+        return report;
+    }
+
+    let Some(dir) = advisory_db else {
+        return report;
+    };
+    let names: Vec<&str> = report
+        .changes
+        .iter()
+        .filter(|c| c.new_version.is_some())
+        .map(|c| c.name.as_str())
+        .collect();
+    match AdvisoryDb::load(dir, &names) {
+        Ok(db) => report.check_advisories(&db),
+        Err(e) => warn!(path = %dir.display(), error = %e, "Failed to read advisory database"),
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_OLD: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde", "time 0.1.45"]
+
+[[package]]
+name = "serde"
+version = "1.0.100"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "time"
+version = "0.1.45"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    const CARGO_NEW: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["serde", "time 0.2.0"]
+
+[[package]]
+name = "itoa"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde"
+version = "1.0.150"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+dependencies = ["itoa"]
+
+[[package]]
+name = "time"
+version = "0.2.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    fn change<'a>(changes: &'a [DependencyChange], name: &str) -> &'a DependencyChange {
+        changes.iter().find(|c| c.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_cargo_lock_changes() {
+        let changes = lockfile_changes("Cargo.lock", Some(CARGO_OLD), Some(CARGO_NEW)).unwrap();
+        assert_eq!(changes.len(), 3);
+
+        let serde = change(&changes, "serde");
+        assert_eq!(serde.kind, ChangeKind::Upgraded);
+        assert!(serde.direct && !serde.is_major());
+
+        let time = change(&changes, "time");
+        assert!(time.is_major());
+
+        let itoa = change(&changes, "itoa");
+        assert_eq!(itoa.kind, ChangeKind::Added);
+        assert!(itoa.is_new_transitive());
+
+        let removed = lockfile_changes("Cargo.lock", Some(CARGO_OLD), None).unwrap();
+        assert!(removed.iter().all(|c| c.kind == ChangeKind::Removed));
+    }
+
+    #[test]
+    fn test_package_lock_changes() {
+        let old = r#"{"lockfileVersion": 3, "packages": {
+            "": {"dependencies": {"lodash": "^4.17.0"}},
+            "node_modules/lodash": {"version": "4.17.20"}
+        }}"#;
+        let new = r#"{"lockfileVersion": 3, "packages": {
+            "": {"dependencies": {"lodash": "^4.17.0"}, "devDependencies": {"@scope/tool": "^2.0.0"}},
+            "node_modules/lodash": {"version": "4.17.21"},
+            "node_modules/@scope/tool": {"version": "2.0.0"},
+            "node_modules/@scope/tool/node_modules/minimist": {"version": "1.2.5"},
+            "packages/local": {"version": "1.0.0"},
+            "node_modules/local": {"link": true}
+        }}"#;
+        let changes = lockfile_changes("web/package-lock.json", Some(old), Some(new)).unwrap();
+        assert_eq!(changes.len(), 3);
+        assert!(change(&changes, "@scope/tool").direct);
+        assert!(change(&changes, "minimist").is_new_transitive());
+        assert_eq!(change(&changes, "lodash").kind, ChangeKind::Upgraded);
+
+        let v1 = r#"{"lockfileVersion": 1, "dependencies": {
+            "express": {"version": "4.17.1", "requires": {"qs": "6.7.0"}},
+            "qs": {"version": "6.7.0"}
+        }}"#;
+        let packages = parse_lockfile(Ecosystem::Npm, v1).unwrap();
+        assert!(packages.iter().any(|p| p.name == "express" && p.direct));
+        assert!(packages.iter().any(|p| p.name == "qs" && !p.direct));
+    }
+
+    #[test]
+    fn test_composer_lock_changes() {
+        let old = r#"{"packages": [
+            {"name": "guzzlehttp/guzzle", "version": "6.5.5", "require": {"guzzlehttp/psr7": "^1.6"}},
+            {"name": "guzzlehttp/psr7", "version": "1.9.0"}
+        ]}"#;
+        let new = r#"{"packages": [
+            {"name": "guzzlehttp/guzzle", "version": "7.5.0", "require": {"guzzlehttp/psr7": "^2.0"}},
+            {"name": "guzzlehttp/psr7", "version": "2.4.3"}
+        ], "packages-dev": [
+            {"name": "phpunit/phpunit", "version": "dev-main"}
+        ]}"#;
+        let changes = lockfile_changes("composer.lock", Some(old), Some(new)).unwrap();
+        let guzzle = change(&changes, "guzzlehttp/guzzle");
+        assert!(guzzle.direct && guzzle.is_major());
+        assert!(!change(&changes, "guzzlehttp/psr7").direct);
+        assert_eq!(change(&changes, "phpunit/phpunit").kind, ChangeKind::Added);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("v2.0", "2.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.0-beta", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0+build", "1.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_advisories() {
+        let dir = std::env::temp_dir().join(format!("advisory-db-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("crates.io")).unwrap();
+        std::fs::write(
+            dir.join("crates.io/RUSTSEC-2020-0071.json"),
+            r#"{"id": "RUSTSEC-2020-0071", "aliases": ["CVE-2020-26235"],
+                "summary": "Potential segfault in the time crate",
+                "affected": [{"package": {"ecosystem": "crates.io", "name": "time"},
+                  "ranges": [{"type": "SEMVER", "events": [
+                    {"introduced": "0.0.0-0"}, {"fixed": "0.2.23"}]}]}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("crates.io/withdrawn.json"),
+            r#"{"id": "RUSTSEC-2000-0001", "withdrawn": "2021-01-01T00:00:00Z",
+                "affected": [{"package": {"ecosystem": "crates.io", "name": "serde"},
+                  "versions": ["1.0.150"]}]}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "\"time\"").unwrap();
+
+        let lockfiles = vec!["Cargo.lock".to_string()];
+        let report = review_dependencies(&lockfiles, Some(&dir), |_| {
+            (Some(CARGO_OLD.into()), Some(CARGO_NEW.into()))
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(report.advisories_checked);
+        assert_eq!(report.vulnerable_count(), 1);
+        let time = change(&report.changes, "time");
+        assert_eq!(time.advisories[0].id, "RUSTSEC-2020-0071");
+
+        let rendered = report.render();
+        assert!(rendered.starts_with(
+            "`Cargo.lock`: 1 added (1 transitive), 0 removed, 2 upgraded, 0 downgraded\n"
+        ));
+        assert!(rendered.contains(
+            "\n- `time` 0.1.45 → 0.2.0 (upgraded, major version)\n  - **RUSTSEC-2020-0071** (CVE-2020-26235): Potential segfault"
+        ));
+        assert!(rendered.contains("- `itoa` 1.0.0 (added, transitive)"));
+    }
+
+    #[test]
+    fn test_osv_ranges() {
+        let range = |events: &str| -> OsvRange {
+            serde_json::from_str(&format!(r#"{{"type": "ECOSYSTEM", "events": {events}}}"#))
+                .unwrap()
+        };
+        let fixed = range(r#"[{"introduced": "0"}, {"fixed": "4.17.21"}]"#);
+        assert!(fixed.affects("4.17.20"));
+        assert!(!fixed.affects("4.17.21"));
+
+        let last = range(r#"[{"introduced": "2.0.0"}, {"last_affected": "2.4.3"}]"#);
+        assert!(!last.affects("1.9.0"));
+        assert!(last.affects("2.4.3"));
+        assert!(!last.affects("2.4.4"));
+    }
+}
@@ -0,0 +1,374 @@
+//! PR description generation.
+//!
+//! The model replies with the parts of a description as JSON. They are
+//! rendered as Markdown sections and merged into the existing PR body: empty
+//! sections of the author's template are filled in, sections the author
+//! already wrote are kept as they are, and missing sections are appended.
+
+use serde::Deserialize;
+use tracing::info;
+
+use super::{DEFAULT_MAX_CHUNK_CHARS, MrReviewAgent, append_diff, append_summarized_files};
+use crate::github::GithubError;
+
+/// Error parsing the model's description.
+#[derive(Debug, thiserror::Error)]
+pub enum DescribeError {
+    #[error("no JSON object found in the response")]
+    NoJson,
+
+    #[error("invalid description: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+/// A generated PR description.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PrDescription {
+    pub summary: String,
+    pub motivation: String,
+    pub changes: Vec<FileChange>,
+    pub risks: Vec<String>,
+    pub testing: String,
+}
+
+/// Walkthrough entry for one changed file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileChange {
+    pub file: String,
+    pub change: String,
+}
+
+/// Kind of a description section, matched against template headings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SectionKind {
+    Summary,
+    Motivation,
+    Changes,
+    Risks,
+    Testing,
+}
+
+impl SectionKind {
+    const ALL: [SectionKind; 5] = [
+        SectionKind::Summary,
+        SectionKind::Motivation,
+        SectionKind::Changes,
+        SectionKind::Risks,
+        SectionKind::Testing,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SectionKind::Summary => "Summary",
+            SectionKind::Motivation => "Motivation",
+            SectionKind::Changes => "Changes",
+            SectionKind::Risks => "Risk Areas",
+            SectionKind::Testing => "Testing",
+        }
+    }
+
+    /// Recognise a template heading such as "## How to test" or "Why?".
+    fn from_heading(heading: &str) -> Option<Self> {
+        let heading = heading.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|w| heading.contains(w));
+        if has(&["test", "qa", "verif"]) {
+            Some(SectionKind::Testing)
+        } else if has(&["risk", "impact"]) {
+            Some(SectionKind::Risks)
+        } else if has(&["change", "walkthrough"]) {
+            Some(SectionKind::Changes)
+        } else if has(&["motivation", "why", "context", "background"]) {
+            Some(SectionKind::Motivation)
+        } else if has(&["summary", "description", "what", "overview"]) {
+            Some(SectionKind::Summary)
+        } else {
+            None
+        }
+    }
+}
+
+/// A Markdown section of a PR body; the preamble has no heading.
+struct Section<'a> {
+    heading: Option<&'a str>,
+    content: String,
+}
+
+impl PrDescription {
+    /// Parse the model's reply, which may wrap the JSON object in prose or a
+    /// code fence.
+    pub fn parse(text: &str) -> Result<Self, DescribeError> {
+        let start = text.find('{').ok_or(DescribeError::NoJson)?;
+        let end = text.rfind('}').ok_or(DescribeError::NoJson)?;
+        if end < start {
+            return Err(DescribeError::NoJson);
+        }
+        Ok(serde_json::from_str(&text[start..=end])?)
+    }
+
+    /// Render the description as a PR body.
+    pub fn render(&self) -> String {
+        self.merge_into(None)
+    }
+
+    /// Merge the description into an existing PR body, keeping every
+    /// section the author filled in.
+    pub fn merge_into(&self, existing: Option<&str>) -> String {
+        let mut used = Vec::new();
+        let mut parts = Vec::new();
+        for section in parse_sections(existing.unwrap_or("")) {
+            let kind = section.heading.and_then(SectionKind::from_heading);
+            let generated = kind
+                .filter(|kind| !used.contains(kind))
+                .and_then(|kind| self.section(kind));
+            match (section.heading, generated) {
+                (Some(heading), Some(generated)) if !is_filled(&section.content) => {
+                    used.extend(kind);
+                    parts.push(format!("{heading}\n\n{generated}"));
+                }
+                _ => {
+                    if is_filled(&section.content) {
+                        used.extend(kind);
+                    }
+                    let text = match section.heading {
+                        Some(heading) => format!("{heading}\n{}", section.content),
+                        None => section.content,
+                    };
+                    if !text.trim().is_empty() {
+                        parts.push(text.trim_end().to_string());
+                    }
+                }
+            }
+        }
+        for kind in SectionKind::ALL {
+            if !used.contains(&kind)
+                && let Some(generated) = self.section(kind)
+            {
+                parts.push(format!("## {}\n\n{generated}", kind.title()));
+            }
+        }
+        let mut body = parts.join("\n\n");
+        body.push('\n');
+        body
+    }
+
+    fn section(&self, kind: SectionKind) -> Option<String> {
+        let text = match kind {
+            SectionKind::Summary => self.summary.trim().to_string(),
+            SectionKind::Motivation => self.motivation.trim().to_string(),
+            SectionKind::Testing => self.testing.trim().to_string(),
+            SectionKind::Changes => self
+                .changes
+                .iter()
+                .map(|c| format!("- `{}`: {}", c.file, c.change.trim()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            SectionKind::Risks => self
+                .risks
+                .iter()
+                .map(|r| format!("- {}", r.trim()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+/// Whether a PR body has nothing the author wrote: it is empty or only an
+/// unfilled template.
+pub fn needs_description(body: Option<&str>) -> bool {
+    parse_sections(body.unwrap_or(""))
+        .iter()
+        .all(|section| !is_filled(&section.content))
+}
+
+fn parse_sections(body: &str) -> Vec<Section<'_>> {
+    let mut sections = vec![Section {
+        heading: None,
+        content: String::new(),
+    }];
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && is_heading(line) {
+            sections.push(Section {
+                heading: Some(line.trim_end()),
+                content: String::new(),
+            });
+            continue;
+        }
+        let current = sections.last_mut().expect("sections start non-empty");
+        current.content.push_str(line);
+        current.content.push('\n');
+    }
+    sections
+}
+
+/// ATX heading (`## Title`), as opposed to e.g. an issue reference (`#12`).
+fn is_heading(line: &str) -> bool {
+    let rest = line.trim_start_matches('#');
+    line.starts_with('#') && (rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Whether a section has content beyond template comments, blank lines and
+/// unchecked checklist items.
+fn is_filled(content: &str) -> bool {
+    let mut rest = content;
+    let mut text = String::new();
+    while let Some(start) = rest.find("<!--") {
+        text.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find("-->")
+            .map_or("", |end| &rest[start + end + 3..]);
+    }
+    text.push_str(rest);
+    text.lines().map(str::trim).any(|line| {
+        !line.is_empty()
+            && !line.starts_with("- [ ]")
+            && !line.starts_with("* [ ]")
+            && line != "-"
+            && line != "N/A"
+    })
+}
+
+impl MrReviewAgent {
+    /// Replace the PR description, e.g. after generating it.
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.context.description = description;
+        self
+    }
+
+    /// Build the prompt for writing the PR description.
+    pub fn build_describe_prompt(&self) -> String {
+        let mut prompt = String::new();
+
+        prompt.push_str(&self.render_template("describe"));
+        prompt.push_str("\n\n---\n\n");
+        prompt.push_str("## Pull Request Details\n\n");
+        self.append_pr_info(&mut prompt);
+        if let Some(ticket) = &self.ticket {
+            prompt.push_str(&format!(
+                "**Jira Ticket**: [{}]({})",
+                ticket.key, ticket.web_url
+            ));
+            if let Some(summary) = &ticket.summary {
+                prompt.push_str(&format!(" — {summary}"));
+            }
+            prompt.push('\n');
+        }
+
+        prompt.push_str("\n## Current Description\n\n");
+        match self.context.description.as_deref().map(str::trim) {
+            Some(body) if !body.is_empty() => {
+                prompt.push_str("Sections the author already filled in are kept as they are.\n\n");
+                prompt.push_str(&format!("```markdown\n{body}\n```\n"));
+            }
+            _ => prompt.push_str("_The PR has no description._\n"),
+        }
+
+        self.append_changed_files(&mut prompt);
+        let plan = self.plan_review(DEFAULT_MAX_CHUNK_CHARS);
+        append_summarized_files(&mut prompt, &plan.summarized);
+        let diff = plan.chunks.first().map_or("", |c| c.diff.as_str());
+        if plan.is_chunked() {
+            prompt.push_str(
+                "\nThe diff is too large to show in full; only its first part is below. Read the other files if needed.\n",
+            );
+        }
+        append_diff(&mut prompt, diff);
+
+        prompt
+            .push_str("Write the description of this pull request and reply with the JSON object.");
+        prompt
+    }
+
+    /// Merge a generated description into the PR body and post it,
+    /// returning the new body.
+    pub async fn update_description(
+        &self,
+        description: &PrDescription,
+    ) -> Result<String, GithubError> {
+        let body = description.merge_into(self.context.description.as_deref());
+        self.github()?
+            .update_pull_request_body(&self.context.project, &self.context.mr_id, &body)
+            .await?;
+        info!(len = body.len(), "Updated PR description");
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description() -> PrDescription {
+        PrDescription::parse(
+            r#"Here it is: {
+                "summary": "Add rate limiting to the login endpoint.",
+                "motivation": "Brute-force attempts.",
+                "changes": [{"file": "src/login.rs", "change": "Count failed attempts."}],
+                "risks": ["Shared IPs may be blocked."],
+                "testing": "Unit tests for the limiter."
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let body = description().render();
+        assert!(body.starts_with("## Summary\n\nAdd rate limiting"));
+        assert!(body.contains("## Changes\n\n- `src/login.rs`: Count failed attempts."));
+        assert!(body.contains("## Risk Areas\n\n- Shared IPs may be blocked."));
+        assert!(body.ends_with("Unit tests for the limiter.\n"));
+    }
+
+    #[test]
+    fn test_merge_keeps_filled_template_sections() {
+        let template = "\
+## What does this PR do?
+<!-- Describe your change -->
+
+## How to test
+Log in five times with a wrong password.
+
+## Checklist
+- [ ] Tests added
+";
+        let body = description().merge_into(Some(template));
+
+        assert!(body.contains("## What does this PR do?\n\nAdd rate limiting"));
+        assert!(body.contains("## How to test\nLog in five times"));
+        assert!(!body.contains("Unit tests for the limiter"));
+        assert!(body.contains("## Checklist\n- [ ] Tests added"));
+        assert!(body.contains("## Motivation\n\nBrute-force attempts."));
+        assert!(!body.contains("## Summary"));
+    }
+
+    #[test]
+    fn test_needs_description() {
+        assert!(needs_description(None));
+        assert!(needs_description(Some("  \n")));
+        assert!(needs_description(Some(
+            "## Summary\n<!-- what and why -->\n\n## Checklist\n- [ ] Tests\n"
+        )));
+        assert!(!needs_description(Some("Fixes the login bug.")));
+        assert!(!needs_description(Some(
+            "## Summary\nFixes the login bug.\n"
+        )));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(matches!(
+            PrDescription::parse("no json"),
+            Err(DescribeError::NoJson)
+        ));
+        assert!(matches!(
+            PrDescription::parse("{\"changes\": 3}"),
+            Err(DescribeError::Invalid(_))
+        ));
+    }
+}
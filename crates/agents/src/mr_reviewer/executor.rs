@@ -8,13 +8,14 @@ use tracing::{debug, info, warn};
 use claude_agent_core::{Action, ActionExecutor, Error, Observation};
 
 use super::MrReviewAgent;
+use crate::github::{GithubError, ReviewEvent};
 
 #[async_trait]
 impl ActionExecutor for MrReviewAgent {
     async fn execute(&self, action: &Action) -> Result<Observation, Error> {
         match action {
             Action::ReadFile { path } => execute_read_file(&self.repo_path, path),
-            Action::RunCommand { cmd } => execute_command(&self.repo_path, cmd),
+            Action::RunCommand { cmd } => self.execute_command(cmd),
             Action::PostComment { body } => self.execute_post_comment(body).await,
             Action::Approve => self.execute_approve().await,
             Action::RequestChanges { reason } => self.execute_request_changes(reason).await,
@@ -43,16 +44,7 @@ fn execute_read_file(repo_path: &std::path::Path, path: &str) -> Result<Observat
     }
 }
 
-fn execute_command(repo_path: &std::path::Path, cmd: &str) -> Result<Observation, Error> {
-    info!(cmd = %cmd, "Running command");
-
-    if !is_safe_command(cmd) {
-        warn!(cmd = %cmd, "Blocked unsafe command");
-        return Ok(Observation::Error {
-            message: "Command not allowed for security reasons".into(),
-        });
-    }
-
+fn run_command(repo_path: &std::path::Path, cmd: &str) -> Result<Observation, Error> {
     let output = Command::new("sh")
         .arg("-c")
         .arg(cmd)
@@ -68,71 +60,68 @@ fn execute_command(repo_path: &std::path::Path, cmd: &str) -> Result<Observation
 }
 
 impl MrReviewAgent {
+    fn execute_command(&self, cmd: &str) -> Result<Observation, Error> {
+        info!(cmd = %cmd, "Running command");
+
+        if let Err(denial) = self.command_policy().check(cmd) {
+            warn!(cmd = %cmd, reason = %denial, "Blocked command");
+            return Ok(Observation::Error {
+                message: format!("Command denied: {denial}"),
+            });
+        }
+        run_command(&self.repo_path, cmd)
+    }
+
     async fn execute_post_comment(&self, body: &str) -> Result<Observation, Error> {
-        info!(
-            body_len = body.len(),
-            "Would post comment (no VCS client configured)"
-        );
-        Ok(Observation::CommentPosted {
-            comment_id: "mock".into(),
+        info!(body_len = body.len(), "Posting comment");
+        let posted = async {
+            self.github()?
+                .post_issue_comment(&self.context.project, &self.context.mr_id, body)
+                .await
+        };
+        Ok(match posted.await {
+            Ok(id) => Observation::CommentPosted {
+                comment_id: id.to_string(),
+            },
+            Err(e) => github_error(e),
         })
     }
 
     async fn execute_approve(&self) -> Result<Observation, Error> {
-        info!("Would approve MR (no VCS client configured)");
-        Ok(Observation::Approved)
+        info!("Approving PR");
+        Ok(match self.submit_review(ReviewEvent::Approve, "").await {
+            Ok(id) => Observation::Approved {
+                review_id: id.to_string(),
+            },
+            Err(e) => github_error(e),
+        })
     }
 
     async fn execute_request_changes(&self, reason: &str) -> Result<Observation, Error> {
-        info!(reason = %reason, "Would request changes (no VCS client configured)");
-        Ok(Observation::ChangesRequested)
+        info!(reason = %reason, "Requesting changes");
+        Ok(
+            match self
+                .submit_review(ReviewEvent::RequestChanges, reason)
+                .await
+            {
+                Ok(id) => Observation::ChangesRequested {
+                    review_id: id.to_string(),
+                },
+                Err(e) => github_error(e),
+            },
+        )
     }
-}
-
-/// Check if a command is safe to run.
-pub(crate) fn is_safe_command(cmd: &str) -> bool {
-    let cmd_lower = cmd.to_lowercase();
-    is_build_tool(&cmd_lower) || is_read_tool(&cmd_lower) || is_vcs_tool(&cmd_lower)
-}
 
-fn is_build_tool(cmd: &str) -> bool {
-    let prefixes = [
-        "cargo ",
-        "cargo clippy",
-        "cargo test",
-        "cargo check",
-        "cargo fmt",
-        "npm ",
-        "yarn ",
-        "pnpm ",
-        "phpstan ",
-        "mago lint",
-        "eslint ",
-        "prettier ",
-        "black ",
-        "ruff ",
-        "mypy ",
-        "pytest ",
-        "go test",
-        "go vet",
-        "golangci-lint",
-        "php -l",
-        "php --syntax-check",
-        "jq ",
-        "sentry ",
-        "jira ",
-    ];
-    prefixes.iter().any(|p| cmd.starts_with(p))
-}
-
-fn is_read_tool(cmd: &str) -> bool {
-    let prefixes = [
-        "cat ", "head ", "tail ", "wc ", "grep ", "rg ", "ls ", "find ",
-    ];
-    prefixes.iter().any(|p| cmd.starts_with(p))
+    async fn submit_review(&self, event: ReviewEvent, body: &str) -> Result<u64, GithubError> {
+        self.github()?
+            .submit_review(&self.context.project, &self.context.mr_id, event, body)
+            .await
+    }
 }
 
-fn is_vcs_tool(cmd: &str) -> bool {
-    let prefixes = ["git add ", "git commit ", "git push ", "github pr "];
-    prefixes.iter().any(|p| cmd.starts_with(p))
+fn github_error(e: GithubError) -> Observation {
+    warn!(error = %e, "GitHub request failed");
+    Observation::Error {
+        message: e.to_string(),
+    }
 }
@@ -0,0 +1,248 @@
+//! Merging of per-part review results for chunked reviews.
+
+use claude_agent_core::{
+    ComplianceStatus, ReviewDecision, ReviewIssue, ReviewResult, TestsVerdict, TicketCompliance,
+};
+
+/// Lines apart two findings on the same file can be and still count as the same.
+const DUPLICATE_LINE_DISTANCE: u32 = 2;
+
+/// Merge the results of a review done in parts into one result.
+///
+/// The decision is the strictest of the parts, the score the lowest and the
+/// effort the highest. Duplicate issues are merged, keeping the most severe.
+pub fn merge_results(results: Vec<ReviewResult>) -> ReviewResult {
+    if results.len() <= 1 {
+        let result = results.into_iter().next();
+        return result.unwrap_or_else(|| ReviewResult::from_summary(""));
+    }
+
+    let decision = merge_decision(&results);
+    let summary = merge_summary(&results);
+    let score = results.iter().filter_map(|r| r.score).min();
+    let effort = results.iter().filter_map(|r| r.effort).max();
+    let tests = merge_tests(&results);
+    let ticket_compliance = merge_compliance(&results);
+    let resolved_threads = results
+        .iter()
+        .flat_map(|r| r.resolved_threads.iter().cloned())
+        .collect();
+
+    let mut issues: Vec<ReviewIssue> = Vec::new();
+    for issue in results.into_iter().flat_map(|r| r.issues) {
+        match issues.iter_mut().find(|kept| is_duplicate(kept, &issue)) {
+            Some(kept) if issue.severity.rank() > kept.severity.rank() => {
+                *kept = issue;
+            }
+            Some(_) => {}
+            None => issues.push(issue),
+        }
+    }
+
+    ReviewResult {
+        decision,
+        summary,
+        issues,
+        score,
+        effort,
+        tests,
+        todos: Vec::new(),
+        ticket_compliance,
+        resolved_threads,
+    }
+}
+
+fn merge_decision(results: &[ReviewResult]) -> ReviewDecision {
+    let decisions = || results.iter().map(|r| r.decision);
+    if decisions().any(|d| d == ReviewDecision::ChangesRequested) {
+        ReviewDecision::ChangesRequested
+    } else if decisions().all(|d| d == ReviewDecision::Approved) {
+        ReviewDecision::Approved
+    } else {
+        ReviewDecision::Comment
+    }
+}
+
+fn merge_summary(results: &[ReviewResult]) -> String {
+    let mut summary = format!("Reviewed in {} parts.\n", results.len());
+    for (i, result) in results.iter().enumerate() {
+        let text = result.summary.trim();
+        if !text.is_empty() {
+            summary.push_str(&format!("\n**Part {}:** {}\n", i + 1, text));
+        }
+    }
+    summary
+}
+
+fn merge_tests(results: &[ReviewResult]) -> Option<TestsVerdict> {
+    let verdicts: Vec<TestsVerdict> = results.iter().filter_map(|r| r.tests).collect();
+    [
+        TestsVerdict::Missing,
+        TestsVerdict::Included,
+        TestsVerdict::NotNeeded,
+    ]
+    .into_iter()
+    .find(|v| verdicts.contains(v))
+}
+
+/// Each part only sees some of the code, so a requirement takes the best
+/// status any part gave it.
+fn merge_compliance(results: &[ReviewResult]) -> Option<TicketCompliance> {
+    let mut merged: Option<TicketCompliance> = None;
+    for compliance in results.iter().filter_map(|r| r.ticket_compliance.as_ref()) {
+        let merged = merged.get_or_insert_with(Default::default);
+        for check in &compliance.requirements {
+            let existing = merged
+                .requirements
+                .iter_mut()
+                .find(|c| normalize(&c.requirement) == normalize(&check.requirement));
+            match existing {
+                Some(existing) if status_rank(check.status) > status_rank(existing.status) => {
+                    *existing = check.clone();
+                }
+                Some(_) => {}
+                None => merged.requirements.push(check.clone()),
+            }
+        }
+    }
+    merged
+}
+
+fn is_duplicate(a: &ReviewIssue, b: &ReviewIssue) -> bool {
+    if a.file != b.file {
+        return false;
+    }
+    if normalize(&a.message) == normalize(&b.message) {
+        return true;
+    }
+    if a.category != b.category {
+        return false;
+    }
+    match (a.line, b.line) {
+        (Some(a_start), Some(b_start)) => {
+            let a_end = a.end_line.unwrap_or(a_start) + DUPLICATE_LINE_DISTANCE;
+            let b_end = b.end_line.unwrap_or(b_start) + DUPLICATE_LINE_DISTANCE;
+            a_start <= b_end && b_start <= a_end
+        }
+        _ => false,
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn status_rank(status: ComplianceStatus) -> u8 {
+    match status {
+        ComplianceStatus::NotMet => 0,
+        ComplianceStatus::NeedsVerification => 1,
+        ComplianceStatus::Met => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_agent_core::{IssueCategory, IssueSeverity, RequirementCheck};
+
+    fn issue(file: &str, line: u32, severity: IssueSeverity, message: &str) -> ReviewIssue {
+        ReviewIssue {
+            severity,
+            category: IssueCategory::Bug,
+            file: Some(file.into()),
+            line: Some(line),
+            end_line: None,
+            message: message.into(),
+            suggested_fix: None,
+            replacement: None,
+        }
+    }
+
+    fn part(decision: ReviewDecision, score: u8, issues: Vec<ReviewIssue>) -> ReviewResult {
+        ReviewResult {
+            decision,
+            summary: format!("score {score}"),
+            issues,
+            score: Some(score),
+            effort: Some(score / 20),
+            tests: None,
+            todos: Vec::new(),
+            ticket_compliance: None,
+            resolved_threads: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge_signals() {
+        let merged = merge_results(vec![
+            part(ReviewDecision::Approved, 90, vec![]),
+            part(ReviewDecision::ChangesRequested, 60, vec![]),
+        ]);
+
+        assert_eq!(merged.decision, ReviewDecision::ChangesRequested);
+        assert_eq!(merged.score, Some(60));
+        assert_eq!(merged.effort, Some(4));
+        assert!(merged.summary.starts_with("Reviewed in 2 parts."));
+        assert!(merged.summary.contains("**Part 2:** score 60"));
+    }
+
+    #[test]
+    fn test_merge_dedupes_issues() {
+        let merged = merge_results(vec![
+            part(
+                ReviewDecision::Comment,
+                80,
+                vec![
+                    issue("src/a.rs", 10, IssueSeverity::Warning, "Possible panic"),
+                    issue("src/b.rs", 5, IssueSeverity::Info, "Unused import"),
+                ],
+            ),
+            part(
+                ReviewDecision::Comment,
+                80,
+                vec![
+                    issue("src/a.rs", 11, IssueSeverity::Error, "unwrap on None"),
+                    issue("src/b.rs", 90, IssueSeverity::Info, "unused import."),
+                    issue("src/c.rs", 10, IssueSeverity::Info, "Possible panic"),
+                ],
+            ),
+        ]);
+
+        assert_eq!(merged.decision, ReviewDecision::Comment);
+        assert_eq!(merged.issues.len(), 3);
+        assert_eq!(merged.issues[0].message, "unwrap on None");
+        assert_eq!(merged.issues[0].severity, IssueSeverity::Error);
+        assert_eq!(merged.issues[1].line, Some(5));
+        assert_eq!(merged.issues[2].file.as_deref(), Some("src/c.rs"));
+    }
+
+    #[test]
+    fn test_merge_tests_and_compliance() {
+        let check = |status| TicketCompliance {
+            ticket: None,
+            url: None,
+            requirements: vec![RequirementCheck {
+                requirement: "Reset password by email".into(),
+                status,
+                note: None,
+            }],
+        };
+        let mut first = part(ReviewDecision::Approved, 90, vec![]);
+        first.tests = Some(TestsVerdict::Included);
+        first.ticket_compliance = Some(check(ComplianceStatus::NeedsVerification));
+        let mut second = part(ReviewDecision::Approved, 90, vec![]);
+        second.tests = Some(TestsVerdict::NotNeeded);
+        second.ticket_compliance = Some(check(ComplianceStatus::Met));
+
+        let merged = merge_results(vec![first, second]);
+        assert_eq!(merged.decision, ReviewDecision::Approved);
+        assert_eq!(merged.tests, Some(TestsVerdict::Included));
+        let requirements = merged.ticket_compliance.unwrap().requirements;
+        assert_eq!(requirements.len(), 1);
+        assert_eq!(requirements[0].status, ComplianceStatus::Met);
+    }
+}
@@ -0,0 +1,420 @@
+//! Diff planner for large pull requests.
+//!
+//! Splits a unified diff into review chunks that each fit in one session,
+//! keeping files of the same directory together where possible. Lockfiles,
+//! generated and binary files are summarized instead of reviewed.
+
+use std::collections::BTreeMap;
+
+use claude_agent_core::Diff;
+
+/// Default maximum diff size (in bytes) reviewed in a single session.
+pub const DEFAULT_MAX_CHUNK_CHARS: usize = 60_000;
+
+const LOCKFILES: [&str; 9] = [
+    "Cargo.lock",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "composer.lock",
+    "Gemfile.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "go.sum",
+];
+
+const GENERATED_SUFFIXES: [&str; 8] = [
+    ".min.js",
+    ".min.css",
+    ".map",
+    ".pb.go",
+    "_pb2.py",
+    ".snap",
+    ".generated.ts",
+    ".g.dart",
+];
+
+/// The diff of a single file.
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    pub text: String,
+    pub added: usize,
+    pub removed: usize,
+    /// Whether git shows the file as binary ("Binary files … differ" or a
+    /// binary patch).
+    pub binary: bool,
+}
+
+/// A changed file left out of the review diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummarizedFile {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+    /// Why the file is summarized ("lockfile", "generated", "vendored",
+    /// "ignored" or "binary").
+    pub reason: &'static str,
+    /// Size before and after the change, for binary files.
+    pub size: Option<SizeChange>,
+}
+
+/// Size of a binary file before and after the change; `None` when the file
+/// does not exist on that side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeChange {
+    pub old: Option<u64>,
+    pub new: Option<u64>,
+}
+
+impl std::fmt::Display for SizeChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.old, self.new) {
+            (Some(old), Some(new)) => write!(f, "{} → {}", format_size(old), format_size(new)),
+            (None, Some(new)) => write!(f, "added, {}", format_size(new)),
+            (Some(old), None) => write!(f, "deleted, {}", format_size(old)),
+            (None, None) => write!(f, "size unknown"),
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} B"),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+impl SummarizedFile {
+    /// One line for the prompt, e.g. "`a.min.js` skipped (generated), +120 -80".
+    pub fn describe(&self) -> String {
+        if self.reason == "binary" {
+            let size = self
+                .size
+                .map_or_else(|| "size unknown".to_string(), |size| size.to_string());
+            return format!("`{}` binary, {size}", self.path);
+        }
+        format!(
+            "`{}` skipped ({}), +{} -{}",
+            self.path, self.reason, self.added, self.removed
+        )
+    }
+}
+
+/// A part of the diff reviewed in its own session.
+#[derive(Debug, Clone, Default)]
+pub struct ReviewChunk {
+    pub files: Vec<String>,
+    pub diff: String,
+}
+
+/// How a pull request diff is split for review.
+#[derive(Debug, Clone, Default)]
+pub struct DiffPlan {
+    pub chunks: Vec<ReviewChunk>,
+    pub summarized: Vec<SummarizedFile>,
+}
+
+impl DiffPlan {
+    /// Whether the PR needs more than one review session.
+    pub fn is_chunked(&self) -> bool {
+        self.chunks.len() > 1
+    }
+}
+
+/// Plan the review of `diff`, splitting it into chunks of at most
+/// `max_chunk_chars` (a single hunk larger than that gets its own chunk).
+pub fn plan_review(diff: &str, max_chunk_chars: usize) -> DiffPlan {
+    let files = split_files(diff);
+    if files.is_empty() {
+        // Not a git diff; review it as-is.
+        let chunks = (!diff.trim().is_empty()).then(|| ReviewChunk {
+            files: Vec::new(),
+            diff: diff.to_string(),
+        });
+        return DiffPlan {
+            chunks: chunks.into_iter().collect(),
+            summarized: Vec::new(),
+        };
+    }
+
+    let mut summarized = Vec::new();
+    let mut groups: BTreeMap<String, Vec<FileDiff>> = BTreeMap::new();
+
+    for file in files {
+        if let Some(reason) = summary_reason(&file) {
+            summarized.push(SummarizedFile {
+                path: file.path,
+                added: file.added,
+                removed: file.removed,
+                reason,
+                size: None,
+            });
+        } else {
+            groups.entry(module_of(&file.path)).or_default().push(file);
+        }
+    }
+
+    let mut packer = Packer::new(max_chunk_chars);
+    for files in groups.values() {
+        let group_size: usize = files.iter().map(|f| f.text.len()).sum();
+        if group_size <= max_chunk_chars {
+            packer.reserve(group_size);
+            for file in files {
+                packer.push(&file.path, &file.text);
+            }
+            continue;
+        }
+        for file in files {
+            if file.text.len() <= max_chunk_chars {
+                packer.push(&file.path, &file.text);
+            } else {
+                for piece in split_hunks(&file.text, max_chunk_chars) {
+                    packer.push(&file.path, &piece);
+                }
+            }
+        }
+    }
+
+    DiffPlan {
+        chunks: packer.finish(),
+        summarized,
+    }
+}
+
+/// Split a unified diff into per-file diffs.
+pub fn split_files(diff: &str) -> Vec<FileDiff> {
+    Diff::parse(diff)
+        .files
+        .iter()
+        .map(|file| FileDiff {
+            path: file.path().to_string(),
+            text: file.to_string(),
+            added: file.added(),
+            removed: file.removed(),
+            binary: file.binary,
+        })
+        .collect()
+}
+
+fn summary_reason(file: &FileDiff) -> Option<&'static str> {
+    if file.binary {
+        return Some("binary");
+    }
+    let path = file.path.as_str();
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if LOCKFILES.contains(&name) {
+        return Some("lockfile");
+    }
+    let generated = GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s))
+        || path
+            .split('/')
+            .any(|dir| dir == "dist" || dir == "generated");
+    generated.then_some("generated")
+}
+
+/// Directory used to group files: the first two path components.
+fn module_of(path: &str) -> String {
+    let dirs: Vec<&str> = path.split('/').collect();
+    let depth = dirs.len().saturating_sub(1).min(2);
+    dirs[..depth].join("/")
+}
+
+/// Split one file's diff into pieces at hunk boundaries, repeating the file
+/// header in every piece.
+fn split_hunks(text: &str, max_chars: usize) -> Vec<String> {
+    let diff = Diff::parse(text);
+    let Some(file) = diff.files.first() else {
+        return vec![text.to_string()];
+    };
+    let header = &file.header;
+    let hunks = file.hunks.iter().map(ToString::to_string);
+
+    let mut pieces = Vec::new();
+    let mut piece = header.clone();
+    for hunk in hunks {
+        if piece.len() > header.len() && piece.len() + hunk.len() > max_chars {
+            pieces.push(std::mem::replace(&mut piece, header.clone()));
+        }
+        piece.push_str(&hunk);
+    }
+    pieces.push(piece);
+    pieces
+}
+
+/// Greedy packing of file diffs into chunks.
+struct Packer {
+    max_chars: usize,
+    chunks: Vec<ReviewChunk>,
+    current: ReviewChunk,
+}
+
+impl Packer {
+    fn new(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            chunks: Vec::new(),
+            current: ReviewChunk::default(),
+        }
+    }
+
+    /// Start a new chunk unless `size` more bytes fit in the current one.
+    fn reserve(&mut self, size: usize) {
+        if self.current.diff.len() + size > self.max_chars {
+            self.flush();
+        }
+    }
+
+    fn push(&mut self, path: &str, text: &str) {
+        self.reserve(text.len());
+        self.current.diff.push_str(text);
+        if !self.current.files.iter().any(|f| f == path) {
+            self.current.files.push(path.to_string());
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.current.diff.is_empty() {
+            self.chunks.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    fn finish(mut self) -> Vec<ReviewChunk> {
+        self.flush();
+        self.chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_diff(path: &str, hunks: usize, lines_per_hunk: usize) -> String {
+        let mut diff = format!(
+            "diff --git a/{path} b/{path}\nindex 111..222 100644\n--- a/{path}\n+++ b/{path}\n"
+        );
+        for h in 0..hunks {
+            let start = h * 100 + 1;
+            diff.push_str(&format!("@@ -{start},1 +{start},{lines_per_hunk} @@\n"));
+            for i in 0..lines_per_hunk {
+                diff.push_str(&format!("+line {i} of hunk {h}\n"));
+            }
+        }
+        diff
+    }
+
+    #[test]
+    fn test_split_files_counts() {
+        let diff = file_diff("src/a.rs", 1, 3) + &file_diff("src/b.rs", 2, 1);
+        let files = split_files(&diff);
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/a.rs");
+        assert_eq!(files[0].added, 3);
+        assert_eq!(files[1].added, 2);
+        assert!(files[1].text.starts_with("diff --git a/src/b.rs"));
+    }
+
+    #[test]
+    fn test_small_diff_single_chunk() {
+        let diff = file_diff("src/a.rs", 1, 3) + &file_diff("Cargo.lock", 1, 50);
+        let plan = plan_review(&diff, DEFAULT_MAX_CHUNK_CHARS);
+
+        assert!(!plan.is_chunked());
+        assert_eq!(plan.chunks[0].files, vec!["src/a.rs"]);
+        assert!(!plan.chunks[0].diff.contains("Cargo.lock"));
+        assert_eq!(
+            plan.summarized,
+            vec![SummarizedFile {
+                path: "Cargo.lock".into(),
+                added: 50,
+                removed: 0,
+                reason: "lockfile",
+                size: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_groups_by_directory() {
+        let diff = file_diff("api/users/a.rs", 1, 20)
+            + &file_diff("web/app.js", 1, 20)
+            + &file_diff("api/users/b.rs", 1, 20);
+        let size = split_files(&diff)
+            .iter()
+            .map(|f| f.text.len())
+            .max()
+            .unwrap();
+        let plan = plan_review(&diff, size * 2 + 10);
+
+        assert_eq!(plan.chunks.len(), 2);
+        assert_eq!(
+            plan.chunks[0].files,
+            vec!["api/users/a.rs", "api/users/b.rs"]
+        );
+        assert_eq!(plan.chunks[1].files, vec!["web/app.js"]);
+    }
+
+    #[test]
+    fn test_large_file_split_by_hunk() {
+        let diff = file_diff("src/big.rs", 4, 30);
+        let plan = plan_review(&diff, diff.len() / 2);
+
+        assert!(plan.chunks.len() >= 2);
+        for chunk in &plan.chunks {
+            assert_eq!(chunk.files, vec!["src/big.rs"]);
+            assert!(chunk.diff.starts_with("diff --git a/src/big.rs"));
+            assert!(chunk.diff.contains("+++ b/src/big.rs"));
+        }
+        let hunks: usize = plan
+            .chunks
+            .iter()
+            .map(|c| c.diff.matches("\n@@ ").count())
+            .sum();
+        assert_eq!(hunks, 4);
+    }
+
+    #[test]
+    fn test_summary_reason() {
+        let reason = |path: &str| summary_reason(&split_files(&file_diff(path, 1, 1))[0]);
+        assert_eq!(reason("web/package-lock.json"), Some("lockfile"));
+        assert_eq!(reason("public/app.min.js"), Some("generated"));
+        assert_eq!(reason("web/dist/bundle.js"), Some("generated"));
+        assert_eq!(reason("src/lib.rs"), None);
+
+        let binary = "diff --git a/logo.png b/logo.png\nindex 111..222 100644\nBinary files a/logo.png and b/logo.png differ\n";
+        assert_eq!(summary_reason(&split_files(binary)[0]), Some("binary"));
+    }
+
+    #[test]
+    fn test_describe_summarized_file() {
+        let file = |reason, size| SummarizedFile {
+            path: "assets/logo.png".into(),
+            added: 3,
+            removed: 1,
+            reason,
+            size,
+        };
+        assert_eq!(
+            file("generated", None).describe(),
+            "`assets/logo.png` skipped (generated), +3 -1"
+        );
+        let size = SizeChange {
+            old: Some(2048),
+            new: Some(3_500_000),
+        };
+        assert_eq!(
+            file("binary", Some(size)).describe(),
+            "`assets/logo.png` binary, 2.0 KB → 3.3 MB"
+        );
+        let added = SizeChange {
+            old: None,
+            new: Some(512),
+        };
+        assert_eq!(
+            file("binary", Some(added)).describe(),
+            "`assets/logo.png` binary, added, 512 B"
+        );
+    }
+}
@@ -0,0 +1,465 @@
+//! Rendering and posting of structured review results.
+//!
+//! The reviewer returns a [`ReviewResult`]; this module turns it into a single
+//! GitHub review with inline comments on the diff and a summary body.
+
+use serde::Serialize;
+use tracing::info;
+
+use claude_agent_core::{
+    ComplianceStatus, Diff, IssueCategory, IssueSeverity, ReviewIssue, ReviewResult, Side,
+    TestsVerdict, TicketCompliance,
+};
+
+use super::MrReviewAgent;
+use crate::github::{GithubError, ReviewEvent};
+
+/// Maximum number of TODO markers listed in the summary.
+const MAX_LISTED_TODOS: usize = 20;
+
+/// Inline comment on the new side of the diff.
+#[derive(Debug, Clone, Serialize)]
+pub struct InlineComment {
+    pub path: String,
+    /// Last line of the commented range.
+    pub line: u32,
+    pub side: &'static str,
+    /// First line of a multi-line range.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_side: Option<&'static str>,
+    pub body: String,
+}
+
+/// A GitHub pull request review: summary body plus inline comments.
+#[derive(Debug, Clone, Serialize)]
+pub struct GithubReview {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+    pub event: ReviewEvent,
+    pub body: String,
+    pub comments: Vec<InlineComment>,
+}
+
+/// Render a review result against the diff it was produced for.
+///
+/// Issues on lines inside a diff hunk become inline comments; everything else
+/// is listed in the summary body so no finding is dropped.
+pub fn render_review(result: &ReviewResult, diff: &str, head_sha: Option<&str>) -> GithubReview {
+    let diff = Diff::parse(diff);
+    let mut comments = Vec::new();
+    let mut unanchored = Vec::new();
+
+    for issue in &result.issues {
+        match anchor(issue, &diff) {
+            Some(comment) => comments.push(comment),
+            None => unanchored.push(issue),
+        }
+    }
+
+    GithubReview {
+        commit_id: head_sha.map(String::from),
+        event: result.decision.into(),
+        body: render_body(result, &unanchored),
+        comments,
+    }
+}
+
+fn anchor(issue: &ReviewIssue, diff: &Diff) -> Option<InlineComment> {
+    let path = issue.file.as_deref()?;
+    let start = issue.line?;
+    let hunk = diff.file(path)?.hunk_at(Side::Right, start)?;
+    let hunk_end = *hunk.new_range()?.end();
+
+    // Multi-line comments must stay within one hunk
+    let end = issue
+        .end_line
+        .filter(|end| *end > start && *end <= hunk_end)
+        .unwrap_or(start);
+    let multi_line = end > start;
+
+    let mut body = render_issue(issue);
+    // A suggestion replaces exactly the commented lines, so only attach it
+    // when the comment covers the issue's whole range.
+    if let Some(replacement) = &issue.replacement
+        && end == issue.end_line.unwrap_or(start)
+    {
+        body.push_str(&render_suggestion(replacement));
+    }
+
+    Some(InlineComment {
+        path: path.to_string(),
+        line: end,
+        side: Side::Right.as_str(),
+        start_line: multi_line.then_some(start),
+        start_side: multi_line.then_some(Side::Right.as_str()),
+        body,
+    })
+}
+
+/// A ```suggestion block, fenced longer than any backtick run it contains.
+fn render_suggestion(replacement: &str) -> String {
+    let longest_run = replacement
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!(
+        "\n\n{fence}suggestion\n{}\n{fence}",
+        replacement.trim_end_matches('\n')
+    )
+}
+
+fn render_issue(issue: &ReviewIssue) -> String {
+    let mut body = format!(
+        "**{}** · {}\n\n{}",
+        severity_label(issue.severity),
+        category_label(issue),
+        issue.message.trim()
+    );
+    if let Some(fix) = &issue.suggested_fix
+        && !fix.trim().is_empty()
+    {
+        body.push_str(&format!("\n\n**Suggested fix:**\n{}", fix.trim()));
+    }
+    body
+}
+
+fn render_body(result: &ReviewResult, unanchored: &[&ReviewIssue]) -> String {
+    let mut body = String::new();
+    if let Some(header) = render_header(result) {
+        body.push_str(&header);
+        body.push_str("\n\n");
+    }
+    body.push_str(result.summary.trim());
+
+    if !result.issues.is_empty() {
+        body.push_str(&format!(
+            "\n\n**Findings:** {}",
+            severity_counts(&result.issues)
+        ));
+    }
+
+    if !unanchored.is_empty() {
+        body.push_str("\n\n### Other findings\n");
+        for issue in unanchored {
+            let location = match (&issue.file, issue.line) {
+                (Some(file), Some(line)) => format!(" `{file}:{line}`"),
+                (Some(file), None) => format!(" `{file}`"),
+                _ => String::new(),
+            };
+            body.push_str(&format!(
+                "\n- **{}** · {}{} — {}",
+                severity_label(issue.severity),
+                category_label(issue),
+                location,
+                issue.message.trim()
+            ));
+            if let Some(fix) = &issue.suggested_fix
+                && !fix.trim().is_empty()
+            {
+                body.push_str(&format!("\n  Suggested fix: {}", fix.trim()));
+            }
+        }
+    }
+
+    if let Some(compliance) = &result.ticket_compliance {
+        body.push_str("\n\n");
+        body.push_str(&render_compliance(compliance));
+    }
+
+    if !result.todos.is_empty() {
+        body.push_str("\n\n### TODOs added\n");
+        for todo in result.todos.iter().take(MAX_LISTED_TODOS) {
+            body.push_str(&format!("\n- `{}:{}` {}", todo.file, todo.line, todo.text));
+        }
+        if result.todos.len() > MAX_LISTED_TODOS {
+            body.push_str(&format!(
+                "\n- …and {} more",
+                result.todos.len() - MAX_LISTED_TODOS
+            ));
+        }
+    }
+
+    body
+}
+
+fn render_compliance(compliance: &TicketCompliance) -> String {
+    let Some(ticket) = &compliance.ticket else {
+        return "### Ticket compliance\n\nNo linked Jira ticket found in the title, branch or description."
+            .into();
+    };
+    let title = match &compliance.url {
+        Some(url) => format!("[{ticket}]({url})"),
+        None => ticket.clone(),
+    };
+    let mut section = format!("### Ticket compliance: {title}\n");
+    if compliance.requirements.is_empty() {
+        section.push_str(
+            "\n_Requirements could not be assessed automatically; needs human verification._",
+        );
+        return section;
+    }
+    for check in &compliance.requirements {
+        section.push_str(&format!(
+            "\n- **{}** — {}",
+            compliance_label(check.status),
+            check.requirement.trim()
+        ));
+        if let Some(note) = &check.note
+            && !note.trim().is_empty()
+        {
+            section.push_str(&format!(": {}", note.trim()));
+        }
+    }
+    section
+}
+
+fn compliance_label(status: ComplianceStatus) -> &'static str {
+    match status {
+        ComplianceStatus::Met => "Met",
+        ComplianceStatus::NotMet => "Not met",
+        ComplianceStatus::NeedsVerification => "Needs human verification",
+    }
+}
+
+/// Compact one-line header with score, effort, tests verdict and TODO count.
+fn render_header(result: &ReviewResult) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(score) = result.score {
+        parts.push(format!("**Score:** {score}/100"));
+    }
+    if let Some(effort) = result.effort {
+        parts.push(format!("**Effort:** {effort}/5"));
+    }
+    if let Some(tests) = result.tests {
+        parts.push(format!("**Tests:** {}", tests_label(tests)));
+    }
+    if !result.todos.is_empty() {
+        parts.push(format!("**TODOs:** {}", result.todos.len()));
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+fn tests_label(tests: TestsVerdict) -> &'static str {
+    match tests {
+        TestsVerdict::Included => "included",
+        TestsVerdict::Missing => "missing for changed logic",
+        TestsVerdict::NotNeeded => "not needed",
+    }
+}
+
+fn severity_counts(issues: &[ReviewIssue]) -> String {
+    let count = |severity| issues.iter().filter(|i| i.severity == severity).count();
+    format!(
+        "{} error(s), {} warning(s), {} info",
+        count(IssueSeverity::Error),
+        count(IssueSeverity::Warning),
+        count(IssueSeverity::Info)
+    )
+}
+
+fn severity_label(severity: IssueSeverity) -> &'static str {
+    match severity {
+        IssueSeverity::Error => "Error",
+        IssueSeverity::Warning => "Warning",
+        IssueSeverity::Info => "Info",
+    }
+}
+
+fn category_label(issue: &ReviewIssue) -> &'static str {
+    match issue.category {
+        IssueCategory::Bug => "Bug",
+        IssueCategory::Security => "Security",
+        IssueCategory::Performance => "Performance",
+        IssueCategory::Maintainability => "Maintainability",
+        IssueCategory::Testing => "Testing",
+        IssueCategory::Other => "Other",
+    }
+}
+
+impl MrReviewAgent {
+    /// Post a rendered review to the pull request, returning the review ID.
+    pub async fn post_review(&self, review: &GithubReview) -> Result<u64, GithubError> {
+        info!(
+            event = ?review.event,
+            inline_comments = review.comments.len(),
+            "Posting review"
+        );
+        self.github()?
+            .create_review(&self.context.project, &self.context.mr_id, review)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_agent_core::ReviewDecision;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,4 +10,6 @@ fn main() {
+ let a = 1;
++let b = 2;
++let c = 3;
+ let d = 4;
+";
+
+    fn issue(line: Option<u32>, end_line: Option<u32>) -> ReviewIssue {
+        ReviewIssue {
+            severity: IssueSeverity::Warning,
+            category: IssueCategory::Bug,
+            file: Some("src/lib.rs".into()),
+            line,
+            end_line,
+            message: "Shadowed value".into(),
+            suggested_fix: Some("Rename `b`".into()),
+            replacement: None,
+        }
+    }
+
+    fn result(issues: Vec<ReviewIssue>) -> ReviewResult {
+        ReviewResult {
+            decision: ReviewDecision::ChangesRequested,
+            summary: "Needs a fix.".into(),
+            issues,
+            score: None,
+            effort: None,
+            tests: None,
+            todos: Vec::new(),
+            ticket_compliance: None,
+            resolved_threads: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_render_inline_and_multiline() {
+        let review = render_review(
+            &result(vec![issue(Some(11), None), issue(Some(11), Some(12))]),
+            DIFF,
+            Some("abc"),
+        );
+
+        assert_eq!(review.event, ReviewEvent::RequestChanges);
+        assert_eq!(review.commit_id.as_deref(), Some("abc"));
+        assert_eq!(review.comments.len(), 2);
+        assert_eq!(review.comments[0].line, 11);
+        assert_eq!(review.comments[0].start_line, None);
+        assert_eq!(review.comments[1].start_line, Some(11));
+        assert_eq!(review.comments[1].line, 12);
+        assert!(review.comments[0].body.starts_with("**Warning** · Bug"));
+        assert!(review.comments[0].body.contains("Rename `b`"));
+        assert!(!review.body.contains("Other findings"));
+    }
+
+    #[test]
+    fn test_render_suggestion() {
+        let mut whole = issue(Some(11), Some(12));
+        whole.replacement = Some("let b = 20;\nlet c = 30;\n".into());
+        let mut clipped = issue(Some(12), Some(30));
+        clipped.replacement = Some("let c = 3;".into());
+        let review = render_review(&result(vec![whole, clipped]), DIFF, None);
+
+        assert!(
+            review.comments[0]
+                .body
+                .ends_with("\n\n```suggestion\nlet b = 20;\nlet c = 30;\n```")
+        );
+        assert_eq!(review.comments[1].line, 12);
+        assert!(!review.comments[1].body.contains("suggestion"));
+
+        assert_eq!(
+            render_suggestion("let s = \"```\";"),
+            "\n\n````suggestion\nlet s = \"```\";\n````"
+        );
+    }
+
+    #[test]
+    fn test_render_unanchored_in_body() {
+        let mut outside = issue(Some(200), None);
+        outside.message = "Missing migration".into();
+        let review = render_review(&result(vec![outside, issue(None, None)]), DIFF, None);
+
+        assert!(review.comments.is_empty());
+        assert!(review.body.starts_with("Needs a fix."));
+        assert!(review.body.contains("0 error(s), 2 warning(s), 0 info"));
+        assert!(review.body.contains("`src/lib.rs:200` — Missing migration"));
+        assert!(review.body.contains("· Bug `src/lib.rs` — Shadowed value"));
+    }
+
+    #[test]
+    fn test_render_header_and_todos() {
+        let mut result = result(Vec::new());
+        result.score = Some(82);
+        result.effort = Some(3);
+        result.tests = Some(TestsVerdict::Missing);
+        result.todos = vec![claude_agent_core::TodoMarker {
+            file: "src/lib.rs".into(),
+            line: 11,
+            text: "TODO: handle errors".into(),
+        }];
+        let review = render_review(&result, DIFF, None);
+
+        assert!(review.body.starts_with(
+            "**Score:** 82/100 · **Effort:** 3/5 · **Tests:** missing for changed logic · **TODOs:** 1\n\nNeeds a fix."
+        ));
+        assert!(
+            review
+                .body
+                .contains("- `src/lib.rs:11` TODO: handle errors")
+        );
+    }
+
+    #[test]
+    fn test_render_without_header() {
+        let review = render_review(&result(Vec::new()), DIFF, None);
+        assert_eq!(review.body, "Needs a fix.");
+    }
+
+    #[test]
+    fn test_render_ticket_compliance() {
+        let mut result = result(Vec::new());
+        result.ticket_compliance = Some(TicketCompliance {
+            ticket: Some("GC-42".into()),
+            url: Some("https://example.atlassian.net/browse/GC-42".into()),
+            requirements: vec![
+                claude_agent_core::RequirementCheck {
+                    requirement: "Reset link expires after 1h".into(),
+                    status: ComplianceStatus::NotMet,
+                    note: Some("Expiry is 24h".into()),
+                },
+                claude_agent_core::RequirementCheck {
+                    requirement: "Email copy matches design".into(),
+                    status: ComplianceStatus::NeedsVerification,
+                    note: None,
+                },
+            ],
+        });
+        let body = render_review(&result, DIFF, None).body;
+
+        assert!(body.contains(
+            "### Ticket compliance: [GC-42](https://example.atlassian.net/browse/GC-42)"
+        ));
+        assert!(body.contains("- **Not met** — Reset link expires after 1h: Expiry is 24h"));
+        assert!(body.contains("- **Needs human verification** — Email copy matches design"));
+
+        result.ticket_compliance = Some(TicketCompliance::default());
+        let body = render_review(&result, DIFF, None).body;
+        assert!(body.contains("No linked Jira ticket found"));
+    }
+
+    #[test]
+    fn test_review_serialization() {
+        let review = render_review(&result(vec![issue(Some(11), Some(12))]), DIFF, None);
+        let json = serde_json::to_value(&review).unwrap();
+
+        assert_eq!(json["event"], "REQUEST_CHANGES");
+        assert!(json.get("commit_id").is_none());
+        assert_eq!(json["comments"][0]["start_side"], "RIGHT");
+    }
+}
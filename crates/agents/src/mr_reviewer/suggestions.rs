@@ -0,0 +1,259 @@
+//! Validation of suggested changes before they are posted.
+//!
+//! Each suggestion is applied to the checkout on its own, checked with the
+//! repository's `review.suggestion_check` command and reverted. Suggestions
+//! that do not apply or break the check are dropped; the issue itself is
+//! still posted.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use tracing::{info, warn};
+
+use claude_agent_core::{ReviewIssue, SuggestionStats};
+
+/// Most characters of check output kept in a failure reason.
+const MAX_CHECK_OUTPUT_CHARS: usize = 300;
+
+/// Validate the suggestions of `issues` against the checkout at `repo_path`,
+/// removing those that fail.
+pub fn validate_suggestions(
+    repo_path: &Path,
+    issues: &mut [ReviewIssue],
+    check: Option<&str>,
+) -> SuggestionStats {
+    let mut stats = SuggestionStats::default();
+    let mut baseline = HashMap::new();
+
+    for issue in issues.iter_mut().filter(|i| i.replacement.is_some()) {
+        match apply_and_check(repo_path, issue, check, &mut baseline) {
+            Ok(()) => stats.applied += 1,
+            Err(reason) => {
+                warn!(
+                    file = issue.file.as_deref().unwrap_or(""),
+                    line = issue.line,
+                    reason = %reason,
+                    "Dropping suggestion"
+                );
+                issue.replacement = None;
+                stats.failed += 1;
+            }
+        }
+    }
+    if stats != SuggestionStats::default() {
+        info!(
+            applied = stats.applied,
+            failed = stats.failed,
+            "Validated suggestions"
+        );
+    }
+    stats
+}
+
+/// Apply one suggestion, run the check and restore the file.
+///
+/// `baseline` caches whether each check command passes on the unmodified
+/// tree; a check that already fails cannot validate anything.
+fn apply_and_check(
+    repo_path: &Path,
+    issue: &ReviewIssue,
+    check: Option<&str>,
+    baseline: &mut HashMap<String, bool>,
+) -> Result<(), String> {
+    let (Some(file), Some(line), Some(replacement)) = (&issue.file, issue.line, &issue.replacement)
+    else {
+        return Err("suggestion without file and line".into());
+    };
+    if Path::new(file)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("invalid path {file:?}"));
+    }
+
+    let path = resolve_in_repo(repo_path, file)?;
+    let original =
+        std::fs::read_to_string(&path).map_err(|e| format!("cannot read {file}: {e}"))?;
+    let patched = apply(&original, line, issue.end_line.unwrap_or(line), replacement)?;
+    if patched == original {
+        return Err("suggestion does not change the code".into());
+    }
+
+    let Some(check) = check else {
+        return Ok(());
+    };
+    let command = check.replace("{file}", &shell_quote(file));
+    let passes_without = match baseline.get(&command) {
+        Some(passes) => *passes,
+        None => {
+            let passes = run_check(repo_path, &command).is_ok();
+            baseline.insert(command.clone(), passes);
+            passes
+        }
+    };
+    if !passes_without {
+        return Err(format!("`{command}` fails without the suggestion"));
+    }
+
+    std::fs::write(&path, &patched).map_err(|e| format!("cannot write {file}: {e}"))?;
+    let checked = run_check(repo_path, &command);
+    std::fs::write(&path, &original).map_err(|e| format!("cannot restore {file}: {e}"))?;
+    checked.map_err(|output| format!("`{command}` failed: {output}"))
+}
+
+/// Resolve `file` in the checkout, following symlinks, and require it to
+/// stay inside: a symlinked file or directory in the PR could otherwise make
+/// the check write outside the repository.
+fn resolve_in_repo(repo_path: &Path, file: &str) -> Result<PathBuf, String> {
+    let root = repo_path
+        .canonicalize()
+        .map_err(|e| format!("cannot resolve the checkout: {e}"))?;
+    let path = repo_path
+        .join(file)
+        .canonicalize()
+        .map_err(|e| format!("cannot read {file}: {e}"))?;
+    if !path.starts_with(&root) {
+        return Err(format!("{file} resolves outside the repository"));
+    }
+    Ok(path)
+}
+
+/// Replace lines `start..=end` (1-based) of `text` with `replacement`.
+fn apply(text: &str, start: u32, end: u32, replacement: &str) -> Result<String, String> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let (start, end) = (start as usize, end as usize);
+    if start == 0 || end < start || end > lines.len() {
+        return Err(format!(
+            "lines {start}-{end} are outside the file ({} lines)",
+            lines.len()
+        ));
+    }
+
+    let mut patched: String = lines[..start - 1].concat();
+    patched.push_str(replacement.trim_end_matches('\n'));
+    if lines[end - 1].ends_with('\n') && !replacement.is_empty() {
+        patched.push('\n');
+    }
+    patched.push_str(&lines[end..].concat());
+    Ok(patched)
+}
+
+fn run_check(repo_path: &Path, command: &str) -> Result<(), String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(repo_path)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let mut text = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if text.is_empty() {
+        text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    }
+    Err(text.chars().take(MAX_CHECK_OUTPUT_CHARS).collect())
+}
+
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_agent_core::{IssueCategory, IssueSeverity};
+
+    fn suggestion(line: u32, end_line: Option<u32>, replacement: &str) -> ReviewIssue {
+        ReviewIssue {
+            severity: IssueSeverity::Warning,
+            category: IssueCategory::Bug,
+            file: Some("src/app.sh".into()),
+            line: Some(line),
+            end_line,
+            message: "m".into(),
+            suggested_fix: None,
+            replacement: Some(replacement.into()),
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let text = "a\nb\nc\n";
+        assert_eq!(apply(text, 2, 2, "B").unwrap(), "a\nB\nc\n");
+        assert_eq!(apply(text, 1, 2, "x\ny\nz\n").unwrap(), "x\ny\nz\nc\n");
+        assert_eq!(apply(text, 2, 3, "").unwrap(), "a\n");
+        assert_eq!(apply("a\nb", 2, 2, "B").unwrap(), "a\nB");
+        assert!(apply(text, 3, 4, "x").is_err());
+    }
+
+    #[test]
+    fn test_validate_suggestions() {
+        let repo = std::env::temp_dir().join(format!("suggestions-{}", std::process::id()));
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        let script = "if true; then\n  echo hi\nfi\n";
+        std::fs::write(repo.join("src/app.sh"), script).unwrap();
+
+        let mut issues = vec![
+            suggestion(2, None, "  echo hello"),
+            suggestion(1, None, "if true then"),
+            suggestion(2, None, "  echo hi"),
+            suggestion(9, None, "x"),
+        ];
+        let stats = validate_suggestions(&repo, &mut issues, Some("sh -n {file}"));
+        let unchanged = std::fs::read_to_string(repo.join("src/app.sh")).unwrap();
+
+        let mut unchecked = vec![suggestion(1, None, "if true then")];
+        let unchecked_stats = validate_suggestions(&repo, &mut unchecked, None);
+        std::fs::remove_dir_all(&repo).unwrap();
+
+        assert_eq!(
+            stats,
+            SuggestionStats {
+                applied: 1,
+                failed: 3
+            }
+        );
+        assert!(issues[0].replacement.is_some());
+        assert!(issues[1..].iter().all(|i| i.replacement.is_none()));
+        assert_eq!(unchanged, script);
+        assert_eq!(unchecked_stats.applied, 1);
+    }
+
+    #[test]
+    fn test_symlinks_outside_repo() {
+        let base = std::env::temp_dir().join(format!("suggestions-links-{}", std::process::id()));
+        let repo = base.join("repo");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("app.sh"), "echo hi\n").unwrap();
+        std::os::unix::fs::symlink(outside.join("app.sh"), repo.join("src/app.sh")).unwrap();
+        std::os::unix::fs::symlink(&outside, repo.join("linked")).unwrap();
+        std::fs::write(repo.join("src/inner.sh"), "echo hi\n").unwrap();
+        std::os::unix::fs::symlink("inner.sh", repo.join("src/alias.sh")).unwrap();
+
+        let mut issues = vec![suggestion(1, None, "echo hello")];
+        let mut through_dir = vec![ReviewIssue {
+            file: Some("linked/app.sh".into()),
+            ..suggestion(1, None, "echo hello")
+        }];
+        let mut inside = vec![ReviewIssue {
+            file: Some("src/alias.sh".into()),
+            ..suggestion(1, None, "echo hello")
+        }];
+        let stats = validate_suggestions(&repo, &mut issues, Some("true"));
+        let dir_stats = validate_suggestions(&repo, &mut through_dir, Some("true"));
+        let inside_stats = validate_suggestions(&repo, &mut inside, Some("true"));
+        let outside_text = std::fs::read_to_string(outside.join("app.sh")).unwrap();
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(stats.failed, 1);
+        assert_eq!(dir_stats.failed, 1);
+        assert!(issues[0].replacement.is_none());
+        assert_eq!(inside_stats.applied, 1);
+        assert_eq!(outside_text, "echo hi\n");
+        assert!(resolve_in_repo(Path::new("/nonexistent"), "a.rs").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Review threads of earlier reviews, for update reviews.
+//!
+//! Threads are listed in the update prompt with their resolution state. The
+//! model names the threads started by this bot that the new changes address,
+//! and those are answered and resolved; threads started by people are never
+//! touched.
+
+use tracing::{info, warn};
+
+use claude_agent_core::ResolvedThread;
+
+use super::MrReviewAgent;
+use crate::github::{GithubError, ReviewThread};
+
+/// Format review threads for the update prompt.
+///
+/// Open threads are shown in full; resolved ones only with their first
+/// comment, as context.
+pub fn format_review_threads(threads: &[ReviewThread]) -> String {
+    let mut out = String::new();
+    for thread in threads {
+        let Some(id) = thread.comment_id() else {
+            continue;
+        };
+        let location = match thread.line {
+            Some(line) => format!("{}:{line}", thread.path),
+            None => thread.path.clone(),
+        };
+        let mut state = vec![if thread.is_resolved {
+            "resolved"
+        } else {
+            "open"
+        }];
+        if thread.is_outdated {
+            state.push("outdated");
+        }
+        state.push(if thread.is_own() {
+            "started by this bot"
+        } else {
+            "started by a person"
+        });
+
+        out.push_str(&format!(
+            "### Thread {id} ({location}, {})\n\n",
+            state.join(", ")
+        ));
+        let shown = if thread.is_resolved { 1 } else { usize::MAX };
+        for comment in thread.comments.iter().take(shown) {
+            out.push_str(&format!("**@{}**: {}\n\n", comment.author, comment.body));
+        }
+    }
+    out
+}
+
+impl MrReviewAgent {
+    /// Fetch the review threads of the PR.
+    pub async fn fetch_review_threads(&self) -> Result<Vec<ReviewThread>, GithubError> {
+        self.github()?
+            .review_threads(&self.context.project, &self.context.mr_id)
+            .await
+    }
+
+    /// Reply to and resolve the threads the review marked as addressed.
+    ///
+    /// Only open threads started by this bot are resolved; anything else the
+    /// model names is skipped. Returns the number of resolved threads.
+    pub async fn resolve_threads(
+        &self,
+        threads: &[ReviewThread],
+        resolved: &[ResolvedThread],
+    ) -> Result<usize, GithubError> {
+        let github = self.github()?;
+        let mut count = 0;
+        for entry in resolved {
+            let thread = threads
+                .iter()
+                .find(|t| t.comment_id() == Some(entry.comment_id));
+            let Some(thread) = thread.filter(|t| t.is_own() && !t.is_resolved) else {
+                warn!(
+                    comment_id = entry.comment_id,
+                    "Not resolving thread: unknown, already resolved or not started by this bot"
+                );
+                continue;
+            };
+
+            let replied = github
+                .reply_to_review_comment(
+                    &self.context.project,
+                    &self.context.mr_id,
+                    entry.comment_id,
+                    &entry.reply,
+                )
+                .await;
+            let resolved = match replied {
+                Ok(_) => github.resolve_review_thread(&thread.id).await,
+                Err(e) => Err(e),
+            };
+            match resolved {
+                Ok(()) => count += 1,
+                Err(e) => warn!(error = %e, thread = %thread.id, "Failed to resolve thread"),
+            }
+        }
+        if count > 0 {
+            info!(resolved = count, "Resolved addressed review threads");
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::{GithubClient, ThreadComment};
+    use claude_agent_core::ReviewContext;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn thread(id: &str, comment_id: u64, own: bool, resolved: bool) -> ReviewThread {
+        ReviewThread {
+            id: id.into(),
+            path: "src/lib.rs".into(),
+            line: Some(12),
+            is_resolved: resolved,
+            is_outdated: false,
+            comments: vec![
+                ThreadComment {
+                    id: comment_id,
+                    author: if own { "review-bot" } else { "alice" }.into(),
+                    body: "unwrap may panic".into(),
+                    by_viewer: own,
+                },
+                ThreadComment {
+                    id: comment_id + 1,
+                    author: "bob".into(),
+                    body: "will fix".into(),
+                    by_viewer: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_format_review_threads() {
+        let out =
+            format_review_threads(&[thread("T_1", 1, true, false), thread("T_2", 5, false, true)]);
+        assert!(out.contains("### Thread 1 (src/lib.rs:12, open, started by this bot)"));
+        assert!(out.contains("### Thread 5 (src/lib.rs:12, resolved, started by a person)"));
+        assert_eq!(out.matches("**@bob**: will fix").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_threads() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/repos/owner/repo/pulls/7/comments/1/replies"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({ "id": 2 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": { "resolveReviewThread": { "thread": { "id": "T_1" } } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let context = ReviewContext {
+            project: "owner/repo".into(),
+            mr_id: "7".into(),
+            source_branch: "feature".into(),
+            target_branch: "main".into(),
+            diff: String::new(),
+            changed_files: Vec::new(),
+            title: "Fix".into(),
+            description: None,
+            author: "alice".into(),
+            base_sha: None,
+            head_sha: None,
+            start_sha: None,
+        };
+        let agent = MrReviewAgent::new(context, "/tmp/repo")
+            .with_github(GithubClient::new("ghs_test").with_base_url(&server.uri()));
+        let threads = [
+            thread("T_1", 1, true, false),
+            thread("T_2", 3, false, false),
+            thread("T_3", 5, true, true),
+        ];
+        let resolved: Vec<ResolvedThread> = [1, 3, 5, 9]
+            .into_iter()
+            .map(|comment_id| ResolvedThread {
+                comment_id,
+                reply: "Fixed.".into(),
+            })
+            .collect();
+
+        let count = agent.resolve_threads(&threads, &resolved).await.unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,86 @@
+//! TODO marker scan of added lines.
+
+use claude_agent_core::{Diff, LineKind, TodoMarker};
+
+const MARKERS: [&str; 4] = ["TODO", "FIXME", "XXX", "HACK"];
+const MAX_TEXT_CHARS: usize = 120;
+
+/// Find TODO/FIXME/XXX/HACK markers on lines added by the diff.
+pub fn scan_todos(diff: &str) -> Vec<TodoMarker> {
+    let mut todos = Vec::new();
+    for file in &Diff::parse(diff).files {
+        let Some(path) = &file.new_path else {
+            continue;
+        };
+        for line in file.lines().filter(|line| line.kind == LineKind::Added) {
+            if let Some(text) = find_marker(&line.content)
+                && let Some(number) = line.new_line
+            {
+                todos.push(TodoMarker {
+                    file: path.clone(),
+                    line: number,
+                    text,
+                });
+            }
+        }
+    }
+    todos
+}
+
+/// Return the text from the first marker onwards, if the line has one.
+fn find_marker(line: &str) -> Option<String> {
+    let start = MARKERS
+        .iter()
+        .filter_map(|marker| find_word(line, marker))
+        .min()?;
+    let text: String = line[start..].trim().chars().take(MAX_TEXT_CHARS).collect();
+    Some(text)
+}
+
+/// Find `word` in `line` as a whole word.
+fn find_word(line: &str, word: &str) -> Option<usize> {
+    let is_word_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    line.match_indices(word).map(|(i, _)| i).find(|&i| {
+        let before = line[..i].chars().next_back();
+        let after = line[i + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,5 @@
+ fn main() {
+-    // TODO: removed marker
++    // TODO(jdoe): handle errors
++    let todo_list = Vec::new();
+     run();
++    // FIXME
+ }
+";
+
+    #[test]
+    fn test_scan_todos_added_lines_only() {
+        let todos = scan_todos(DIFF);
+
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[0].file, "src/lib.rs");
+        assert_eq!(todos[0].line, 2);
+        assert_eq!(todos[0].text, "TODO(jdoe): handle errors");
+        assert_eq!(todos[1].line, 5);
+        assert_eq!(todos[1].text, "FIXME");
+    }
+
+    #[test]
+    fn test_find_word() {
+        assert_eq!(find_word("// XXX fix", "XXX"), Some(3));
+        assert_eq!(find_word("let TODOS = 1;", "TODO"), None);
+        assert_eq!(find_word("MY_HACK", "HACK"), None);
+    }
+}
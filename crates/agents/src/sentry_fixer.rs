@@ -4,105 +4,8 @@
 
 use std::path::Path;
 
-/// System prompt for the Sentry fixer agent.
-pub const SENTRY_FIX_SYSTEM_PROMPT: &str = r###"You are a code fixer. A Sentry error has been reported and your job is to analyze and fix it.
-
-## Instructions
-
-1. **Understand the error**: Read the stacktrace carefully to identify the root cause
-2. **Locate the code**: Use the Read tool to examine the files mentioned in the stacktrace
-3. **Implement the fix**: Use the Edit tool to fix the bug
-4. **Test if possible**: If there are relevant tests, run them to verify the fix
-5. **Commit and push**: Create a branch, commit the fix, and push
-
-## Creating a Branch and Committing
-
-```bash
-# Create a fix branch (already on target branch)
-git checkout -b sentry-fix/<SHORT_ID>
-
-# After making changes:
-git add -A
-git commit -m "fix: <SHORT_ID> - <brief description>
-
-Resolves <SENTRY_URL>"
-git push origin HEAD
-```
-
-## Creating the Pull Request
-
-After pushing, create the PR:
-
-```bash
-gh pr create --title "fix: <SHORT_ID> - <brief description>" \
-  --body "## Summary
-
-Fixes Sentry issue <SHORT_ID>: <ERROR_TITLE>
-
-## Root Cause
-
-<explain what caused the error>
-
-## Fix
-
-<explain what you changed>
-
-## Sentry Issue
-
-<SENTRY_URL>"
-```
-
-## Project Guidelines
-
-Before making changes, check if `.claude/mr.md` exists in the repo root. If it does, read it and follow those project-specific guidelines for code changes and PR creation.
-
-## Rules
-
-- Only fix the specific error reported. Do NOT refactor, improve, or change other code.
-- If the fix requires significant design decisions, explain the options and pick the safest one.
-- If you cannot determine a fix, explain what investigation is needed and create a PR with your analysis.
-- Do not add new dependencies unless absolutely necessary.
-- Preserve existing code style and patterns.
-- Do NOT add self-review comments to PRs. Do not comment "LGTM" on your own PRs.
-
-## CRITICAL: Never Suppress Error Reporting
-
-**NEVER remove, skip, or conditionally suppress `log_exception_in_sentry()` calls or similar error reporting.** Removing error reporting is destructive — it makes production issues invisible.
-
-If an error is expected (e.g., user input validation, known edge cases), **downgrade its severity** instead of removing the log:
-
-```php
-// WRONG — suppresses error reporting entirely
-if (!$isExpectedError) {
-    log_exception_in_sentry($e);
-}
-
-// CORRECT — logs at info level so it's still visible but doesn't alert
-log_exception_in_sentry($e, level: $isExpectedError ? \Sentry\Severity::info() : null);
-```
-
-The `log_exception_in_sentry()` function accepts a `$level` parameter. Use `\Sentry\Severity::info()` for expected errors. This keeps errors visible in Sentry for monitoring while preventing alert noise.
-
-Similarly, never move errors from info-level lists to expected/ignored lists. If an error is already logged at info level, that's the correct handling.
-
-**Also never:** add errors to "expected errors" documentation tables as a substitute for a code fix.
-
-## Do NOT Fix
-
-Some errors cannot be fixed with code changes alone. If the error falls into any of these categories, do NOT create a branch or PR. Instead, exit with a message explaining why you cannot fix it:
-
-- **Missing database migrations**: Errors caused by missing columns, tables, or schema changes. These require a migration to be created and deployed by a human. Do not attempt workarounds like commenting out code that references the missing column.
-- **Infrastructure/deployment issues**: Errors caused by deployment timing, missing environment variables, misconfigured services, or DNS problems.
-- **Data issues**: Errors caused by corrupt or unexpected data that needs manual cleanup.
-- **Third-party service outages**: Errors caused by external APIs being down or returning unexpected responses temporarily.
-- **Rate limiting or resource exhaustion**: Errors caused by hitting API limits, running out of disk space, or memory issues.
-
-## Available Tools
-
-- Read files with the Read tool
-- Edit files with the Edit tool
-- Run commands: git, gh pr, test runners, linters
-"###;
+use crate::policy::{CommandPolicy, JobKind};
+use crate::templates::{TemplateVars, Templates};
 
 /// Context for a Sentry fix job.
 #[derive(Debug, Clone)]
@@ -113,6 +16,12 @@ pub struct SentryFixContext {
     pub platform: String,
     pub web_url: String,
     pub stacktrace: String,
+    /// What the sampled recent events have in common and where they differ;
+    /// empty when only one event was available.
+    pub occurrences: String,
+    /// Commits that last changed the lines of the in-app frames before the
+    /// issue was first seen; empty when none were found.
+    pub suspect_commits: String,
     pub tags: Vec<(String, String)>,
     pub vcs_project: String,
     pub target_branch: String,
@@ -123,6 +32,7 @@ pub struct SentryFixContext {
 pub struct SentryFixerAgent {
     context: SentryFixContext,
     _repo_path: std::path::PathBuf,
+    templates: Templates,
 }
 
 impl SentryFixerAgent {
@@ -130,12 +40,19 @@ impl SentryFixerAgent {
         Self {
             context,
             _repo_path: repo_path.as_ref().to_path_buf(),
+            templates: Templates::builtin(),
         }
     }
 
+    /// Use these prompt templates instead of the built-in ones.
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
     pub fn build_prompt(&self) -> String {
         let mut prompt = String::new();
-        prompt.push_str(SENTRY_FIX_SYSTEM_PROMPT);
+        prompt.push_str(&self.system_prompt());
         prompt.push_str("\n\n---\n\n");
         self.append_issue_details(&mut prompt);
         self.append_error_details(&mut prompt);
@@ -143,8 +60,22 @@ impl SentryFixerAgent {
         prompt
     }
 
-    pub fn system_prompt(&self) -> &'static str {
-        SENTRY_FIX_SYSTEM_PROMPT
+    pub fn system_prompt(&self) -> String {
+        let vars = TemplateVars::new()
+            .string("short_id", &self.context.short_id)
+            .string("branch", self.branch())
+            .string("issue_url", &self.context.web_url);
+        self.templates.prompt("sentry_fix", &vars)
+    }
+
+    /// Command policy for this job: the built-in fix allowlist, pushing only
+    /// to the fix branch.
+    pub fn command_policy(&self) -> CommandPolicy {
+        CommandPolicy::for_job(JobKind::Fix).with_push_branch(&self.branch())
+    }
+
+    fn branch(&self) -> String {
+        format!("sentry-fix/{}", self.context.short_id.to_lowercase())
     }
 
     fn append_issue_details(&self, prompt: &mut String) {
@@ -181,6 +112,14 @@ impl SentryFixerAgent {
         } else {
             prompt.push_str(&self.context.stacktrace);
         }
+        if !self.context.occurrences.is_empty() {
+            prompt.push('\n');
+            prompt.push_str(&self.context.occurrences);
+        }
+        if !self.context.suspect_commits.is_empty() {
+            prompt.push('\n');
+            prompt.push_str(&self.context.suspect_commits);
+        }
     }
 
     fn append_task(&self, prompt: &mut String) {
@@ -192,8 +131,8 @@ impl SentryFixerAgent {
         prompt.push_str("2. Read the relevant source files to understand the context\n");
         prompt.push_str("3. Implement a fix for the root cause\n");
         prompt.push_str(&format!(
-            "4. Create branch `sentry-fix/{}` and commit the fix\n",
-            self.context.short_id.to_lowercase()
+            "4. Create branch `{}` and commit the fix\n",
+            self.branch()
         ));
         prompt.push_str("5. Push and create a PR\n");
     }
@@ -211,6 +150,8 @@ mod tests {
             platform: "php".into(),
             web_url: "https://sentry.io/issues/12345".into(),
             stacktrace: "## NullPointerException\n\ndoSomething in FooService.php:42\n".into(),
+            occurrences: String::new(),
+            suspect_commits: String::new(),
             tags: vec![
                 ("environment".into(), "production".into()),
                 ("browser".into(), "Chrome".into()),
@@ -237,6 +178,22 @@ mod tests {
         assert!(prompt.contains("Severity::info()"));
     }
 
+    #[test]
+    fn test_command_policy() {
+        let policy = SentryFixerAgent::new(make_context(), "/tmp/repo").command_policy();
+        for cmd in [
+            "git checkout -b sentry-fix/web-123",
+            "git add -A",
+            "git commit -m 'fix: WEB-123 - guard null'",
+            "git push origin HEAD",
+            "gh pr create --title 'fix: WEB-123' --body 'Fixes WEB-123'",
+        ] {
+            assert_eq!(policy.check(cmd), Ok(()), "{cmd}");
+        }
+        assert!(policy.check("git push origin HEAD:master").is_err());
+        assert!(policy.check("curl https://example.com").is_err());
+    }
+
     #[test]
     fn test_build_prompt_github() {
         let agent = SentryFixerAgent::new(make_context(), "/tmp/repo");
@@ -255,4 +212,33 @@ mod tests {
 
         assert!(prompt.contains("No stacktrace available"));
     }
+
+    #[test]
+    fn test_build_prompt_occurrences() {
+        let mut ctx = make_context();
+        ctx.occurrences = "## Occurrences\n\nSampled the 5 most recent events.\n".into();
+
+        let prompt = SentryFixerAgent::new(ctx, "/tmp/repo").build_prompt();
+        let stacktrace = prompt.find("doSomething in FooService.php:42").unwrap();
+        let occurrences = prompt.find("## Occurrences").unwrap();
+        assert!(stacktrace < occurrences);
+
+        let single = SentryFixerAgent::new(make_context(), "/tmp/repo").build_prompt();
+        assert!(!single.contains("## Occurrences"));
+    }
+
+    #[test]
+    fn test_build_prompt_suspect_commits() {
+        let mut ctx = make_context();
+        ctx.suspect_commits = "## Likely introducing commits\n\n- `0123456789` 2024-04-20 by Jane Doe: Fix totals — `FooService.php:42`\n".into();
+
+        let prompt = SentryFixerAgent::new(ctx, "/tmp/repo").build_prompt();
+        let stacktrace = prompt.find("doSomething in FooService.php:42").unwrap();
+        let suspects = prompt.find("## Likely introducing commits").unwrap();
+        assert!(stacktrace < suspects);
+        assert!(prompt.contains("by Jane Doe: Fix totals"));
+
+        let none = SentryFixerAgent::new(make_context(), "/tmp/repo").build_prompt();
+        assert!(!none.contains("## Likely introducing commits"));
+    }
 }
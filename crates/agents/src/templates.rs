@@ -0,0 +1,794 @@
+//! Prompt templates.
+//!
+//! Every system prompt is a named template. The built-in set is compiled in
+//! from `crates/agents/prompts/`; a directory mounted into the worker and a
+//! repository's `.claude/prompts/` can replace templates, so prompt wording
+//! can change without a new image.
+//!
+//! Templates are Markdown with an optional TOML front matter declaring the
+//! variables they use and their types:
+//!
+//! ```text
+//! +++
+//! [vars]
+//! repository = "string"
+//! pr_number = "number"
+//! +++
+//! Comment with `github pr comment {{repository}} {{pr_number}}`.
+//! {{#if draft}}The PR is a draft.{{/if}}
+//! {{> rules}}
+//! ```
+//!
+//! `{{> name}}` includes `partials/name.md` without its final newline, so it
+//! can stand on its own line. In a template that replaces another one,
+//! `{{> base}}` includes the replaced template, which lets a repository
+//! extend a prompt instead of copying it. A replacement may only declare
+//! variables of the template it replaces, with the same types.
+//!
+//! Every set has a version (a label and a hash of its sources) that is
+//! recorded in the job report.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde::Deserialize;
+use tracing::warn;
+
+/// Directory of template overrides, relative to the repository root.
+pub const TEMPLATE_OVERRIDE_DIR: &str = ".claude/prompts";
+
+/// Built-in templates, by name.
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("review", include_str!("../prompts/review.md")),
+    ("update_review", include_str!("../prompts/update_review.md")),
+    ("describe", include_str!("../prompts/describe.md")),
+    ("lint_fix", include_str!("../prompts/lint_fix.md")),
+    ("comment", include_str!("../prompts/comment.md")),
+    ("sentry_fix", include_str!("../prompts/sentry_fix.md")),
+    ("jira_ticket", include_str!("../prompts/jira_ticket.md")),
+];
+
+/// Built-in partials, by name.
+const BUILTIN_PARTIALS: &[(&str, &str)] = &[
+    (
+        "review_output_format",
+        include_str!("../prompts/partials/review_output_format.md"),
+    ),
+    (
+        "posting_comments",
+        include_str!("../prompts/partials/posting_comments.md"),
+    ),
+    ("rules", include_str!("../prompts/partials/rules.md")),
+];
+
+/// Partial name that includes the template being replaced.
+const BASE_PARTIAL: &str = "base";
+
+/// Maximum nesting of partials.
+const MAX_DEPTH: usize = 8;
+
+/// Error loading or rendering templates.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("template `{template}`: invalid front matter: {message}")]
+    FrontMatter { template: String, message: String },
+
+    #[error("template `{template}`: {message}")]
+    Syntax { template: String, message: String },
+
+    #[error("unknown template `{0}`")]
+    UnknownTemplate(String),
+
+    #[error("template `{template}` includes unknown partial `{partial}`")]
+    UnknownPartial { template: String, partial: String },
+
+    #[error("template `{template}` uses undeclared variable `{name}`")]
+    Undeclared { template: String, name: String },
+
+    #[error("template `{template}` declares `{name}`, which is not passed to it")]
+    NotPassed { template: String, name: String },
+
+    #[error("template `{template}`: variable `{name}` is {found}, expected {expected}")]
+    TypeMismatch {
+        template: String,
+        name: String,
+        expected: VarType,
+        found: VarType,
+    },
+
+    #[error("template `{template}` needs variable `{name}`")]
+    Missing { template: String, name: String },
+
+    #[error("template `{template}`: partials nest more than {MAX_DEPTH} levels")]
+    TooDeep { template: String },
+}
+
+/// Type of a template variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    String,
+    Number,
+    Bool,
+    List,
+}
+
+impl fmt::Display for VarType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Bool => "bool",
+            Self::List => "list",
+        })
+    }
+}
+
+/// Value of a template variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Number(i64),
+    Bool(bool),
+    /// Rendered comma-separated.
+    List(Vec<String>),
+}
+
+impl Value {
+    pub fn var_type(&self) -> VarType {
+        match self {
+            Self::String(_) => VarType::String,
+            Self::Number(_) => VarType::Number,
+            Self::Bool(_) => VarType::Bool,
+            Self::List(_) => VarType::List,
+        }
+    }
+
+    /// Whether `{{#if}}` includes its block: true, non-zero or non-empty.
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::String(s) => !s.is_empty(),
+            Self::Number(n) => *n != 0,
+            Self::Bool(b) => *b,
+            Self::List(items) => !items.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(s) => f.write_str(s),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::List(items) => f.write_str(&items.join(", ")),
+        }
+    }
+}
+
+/// Variables passed to a template.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars(BTreeMap<String, Value>);
+
+impl TemplateVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn string(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.0.insert(name.to_string(), Value::String(value.into()));
+        self
+    }
+
+    pub fn number(mut self, name: &str, value: i64) -> Self {
+        self.0.insert(name.to_string(), Value::Number(value));
+        self
+    }
+
+    pub fn bool(mut self, name: &str, value: bool) -> Self {
+        self.0.insert(name.to_string(), Value::Bool(value));
+        self
+    }
+
+    pub fn list(mut self, name: &str, value: Vec<String>) -> Self {
+        self.0.insert(name.to_string(), Value::List(value));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FrontMatter {
+    vars: BTreeMap<String, VarType>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var(String),
+    If(String, Vec<Node>),
+    Partial(String),
+}
+
+/// A parsed template, with the one it replaces.
+#[derive(Debug, Clone)]
+struct Template {
+    source: String,
+    vars: BTreeMap<String, VarType>,
+    nodes: Vec<Node>,
+    base: Option<Box<Template>>,
+}
+
+impl Template {
+    /// Parse a template, replacing `base` if given. A replacement gets the
+    /// variables of `base` and may not declare others.
+    fn parse(name: &str, source: &str, base: Option<Template>) -> Result<Self, TemplateError> {
+        let (mut vars, body) = split_front_matter(name, source)?;
+        let nodes = parse_body(name, body)?;
+        if let Some(base) = &base {
+            for (var, ty) in &vars {
+                match base.vars.get(var) {
+                    None => {
+                        return Err(TemplateError::NotPassed {
+                            template: name.to_string(),
+                            name: var.clone(),
+                        });
+                    }
+                    Some(expected) if expected != ty => {
+                        return Err(TemplateError::TypeMismatch {
+                            template: name.to_string(),
+                            name: var.clone(),
+                            expected: *expected,
+                            found: *ty,
+                        });
+                    }
+                    Some(_) => {}
+                }
+            }
+            vars = base.vars.clone();
+        }
+
+        let mut used = Vec::new();
+        collect_vars(&nodes, &mut used);
+        if let Some(undeclared) = used.into_iter().find(|v| !vars.contains_key(*v)) {
+            return Err(TemplateError::Undeclared {
+                template: name.to_string(),
+                name: undeclared.to_string(),
+            });
+        }
+        Ok(Self {
+            source: source.to_string(),
+            vars,
+            nodes,
+            base: base.map(Box::new),
+        })
+    }
+
+    /// Sources of the template and the ones it replaces.
+    fn sources(&self) -> impl Iterator<Item = &str> {
+        std::iter::successors(Some(self), |t| t.base.as_deref()).map(|t| t.source.as_str())
+    }
+}
+
+fn split_front_matter<'a>(
+    name: &str,
+    source: &'a str,
+) -> Result<(BTreeMap<String, VarType>, &'a str), TemplateError> {
+    let Some(rest) = source.strip_prefix("+++\n") else {
+        return Ok((BTreeMap::new(), source));
+    };
+    let (header, body) = match rest.find("\n+++\n") {
+        Some(end) => (&rest[..end], &rest[end + 5..]),
+        None => match rest.strip_suffix("\n+++") {
+            Some(header) => (header, ""),
+            None => {
+                return Err(TemplateError::FrontMatter {
+                    template: name.to_string(),
+                    message: "missing closing `+++`".to_string(),
+                });
+            }
+        },
+    };
+    let front: FrontMatter = toml::from_str(header).map_err(|e| TemplateError::FrontMatter {
+        template: name.to_string(),
+        message: e.message().to_string(),
+    })?;
+    Ok((front.vars, body))
+}
+
+fn parse_body(name: &str, body: &str) -> Result<Vec<Node>, TemplateError> {
+    let syntax = |message: String| TemplateError::Syntax {
+        template: name.to_string(),
+        message,
+    };
+    // Open `{{#if}}` blocks: (variable, nodes before the block).
+    let mut open: Vec<(String, Vec<Node>)> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            nodes.push(Node::Text(rest[..start].to_string()));
+        }
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| syntax("unclosed `{{`".to_string()))?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        if let Some(var) = tag.strip_prefix("#if ") {
+            let var =
+                identifier(var.trim()).ok_or_else(|| syntax(format!("invalid tag `{tag}`")))?;
+            open.push((var.to_string(), std::mem::take(&mut nodes)));
+        } else if tag == "/if" {
+            let (var, outer) = open
+                .pop()
+                .ok_or_else(|| syntax("`{{/if}}` without `{{#if}}`".to_string()))?;
+            let block = std::mem::replace(&mut nodes, outer);
+            nodes.push(Node::If(var, block));
+        } else if let Some(partial) = tag.strip_prefix('>') {
+            let partial =
+                identifier(partial.trim()).ok_or_else(|| syntax(format!("invalid tag `{tag}`")))?;
+            nodes.push(Node::Partial(partial.to_string()));
+        } else {
+            let var = identifier(tag).ok_or_else(|| syntax(format!("invalid tag `{tag}`")))?;
+            nodes.push(Node::Var(var.to_string()));
+        }
+    }
+    if let Some((var, _)) = open.last() {
+        return Err(syntax(format!("`{{{{#if {var}}}}}` is not closed")));
+    }
+    if !rest.is_empty() {
+        nodes.push(Node::Text(rest.to_string()));
+    }
+    Ok(nodes)
+}
+
+fn identifier(text: &str) -> Option<&str> {
+    let valid = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    valid.then_some(text)
+}
+
+fn collect_vars<'a>(nodes: &'a [Node], used: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            Node::Var(var) => used.push(var),
+            Node::If(var, block) => {
+                used.push(var);
+                collect_vars(block, used);
+            }
+            Node::Text(_) | Node::Partial(_) => {}
+        }
+    }
+}
+
+/// A set of templates and partials.
+#[derive(Debug, Clone)]
+pub struct Templates {
+    templates: BTreeMap<String, Template>,
+    partials: BTreeMap<String, Template>,
+    version: String,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Templates {
+    /// The templates compiled into the binary.
+    pub fn builtin() -> Self {
+        let parse = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(name, source)| {
+                    let template = Template::parse(name, source, None)
+                        .unwrap_or_else(|e| panic!("invalid built-in template: {e}"));
+                    (name.to_string(), template)
+                })
+                .collect()
+        };
+        let mut templates = Self {
+            templates: parse(BUILTIN_TEMPLATES),
+            partials: parse(BUILTIN_PARTIALS),
+            version: String::new(),
+        };
+        templates.version = format!("builtin-{}", templates.hash());
+        templates
+    }
+
+    /// The built-in templates with those in `dir` replacing them.
+    ///
+    /// The version is labelled with the contents of `dir/VERSION`, if any.
+    /// A missing directory gives the built-in set.
+    pub fn load(dir: &Path) -> Result<Self, TemplateError> {
+        let mut templates = Self::builtin();
+        if !dir.is_dir() {
+            return Ok(templates);
+        }
+        templates.overlay(dir)?;
+        let label = std::fs::read_to_string(dir.join("VERSION"))
+            .ok()
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .unwrap_or_else(|| "custom".to_string());
+        templates.version = format!("{label}-{}", templates.hash());
+        Ok(templates)
+    }
+
+    /// Apply the repository's overrides from [`TEMPLATE_OVERRIDE_DIR`].
+    pub fn with_overrides(mut self, repo_path: &Path) -> Result<Self, TemplateError> {
+        let dir = repo_path.join(TEMPLATE_OVERRIDE_DIR);
+        if !dir.is_dir() {
+            return Ok(self);
+        }
+        self.overlay(&dir)?;
+        self.version = format!("{}+repo-{}", self.version, self.hash());
+        Ok(self)
+    }
+
+    /// Version of the set, recorded with every job.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Render a template.
+    pub fn render(&self, name: &str, vars: &TemplateVars) -> Result<String, TemplateError> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| TemplateError::UnknownTemplate(name.to_string()))?;
+        for (var, expected) in &template.vars {
+            let value = vars.get(var).ok_or_else(|| TemplateError::Missing {
+                template: name.to_string(),
+                name: var.clone(),
+            })?;
+            if value.var_type() != *expected {
+                return Err(TemplateError::TypeMismatch {
+                    template: name.to_string(),
+                    name: var.clone(),
+                    expected: *expected,
+                    found: value.var_type(),
+                });
+            }
+        }
+        let mut out = String::new();
+        self.render_nodes(name, template, &template.nodes, vars, &mut out, 0)?;
+        Ok(out)
+    }
+
+    /// Render a system prompt, falling back to the built-in template when
+    /// an override fails to render.
+    pub fn prompt(&self, name: &str, vars: &TemplateVars) -> String {
+        self.render(name, vars).unwrap_or_else(|e| {
+            warn!(template = name, version = %self.version, error = %e, "Failed to render prompt template, using the built-in one");
+            Self::builtin()
+                .render(name, vars)
+                .unwrap_or_else(|e| panic!("built-in template failed to render: {e}"))
+        })
+    }
+
+    fn render_nodes(
+        &self,
+        name: &str,
+        template: &Template,
+        nodes: &[Node],
+        vars: &TemplateVars,
+        out: &mut String,
+        depth: usize,
+    ) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var(var) => {
+                    let value = vars.get(var).ok_or_else(|| TemplateError::Missing {
+                        template: name.to_string(),
+                        name: var.clone(),
+                    })?;
+                    out.push_str(&value.to_string());
+                }
+                Node::If(var, block) => {
+                    if vars.get(var).is_some_and(Value::is_truthy) {
+                        self.render_nodes(name, template, block, vars, out, depth)?;
+                    }
+                }
+                Node::Partial(partial) => {
+                    let included = self.resolve_partial(name, template, partial, depth)?;
+                    let mut text = String::new();
+                    self.render_nodes(name, included, &included.nodes, vars, &mut text, depth + 1)?;
+                    out.push_str(text.strip_suffix('\n').unwrap_or(&text));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_partial<'a>(
+        &'a self,
+        name: &str,
+        template: &'a Template,
+        partial: &str,
+        depth: usize,
+    ) -> Result<&'a Template, TemplateError> {
+        if depth >= MAX_DEPTH {
+            return Err(TemplateError::TooDeep {
+                template: name.to_string(),
+            });
+        }
+        let included = if partial == BASE_PARTIAL {
+            template.base.as_deref()
+        } else {
+            self.partials.get(partial)
+        };
+        included.ok_or_else(|| TemplateError::UnknownPartial {
+            template: name.to_string(),
+            partial: partial.to_string(),
+        })
+    }
+
+    /// Replace templates and partials with the `*.md` files of `dir` and
+    /// `dir/partials`, then check the set.
+    fn overlay(&mut self, dir: &Path) -> Result<(), TemplateError> {
+        for (name, source) in read_templates(dir)? {
+            let base = self
+                .templates
+                .remove(&name)
+                .ok_or_else(|| TemplateError::UnknownTemplate(name.clone()))?;
+            let template = Template::parse(&name, &source, Some(base))?;
+            self.templates.insert(name, template);
+        }
+        for (name, source) in read_templates(&dir.join("partials"))? {
+            let base = self.partials.remove(&name);
+            let partial = Template::parse(&name, &source, base)?;
+            self.partials.insert(name, partial);
+        }
+        self.check()
+    }
+
+    /// Check that every partial exists and gets the variables it declares.
+    fn check(&self) -> Result<(), TemplateError> {
+        for (name, template) in self.templates.iter().chain(&self.partials) {
+            self.check_includes(name, &template.vars, template, &template.nodes, 0)?;
+        }
+        Ok(())
+    }
+
+    fn check_includes(
+        &self,
+        name: &str,
+        vars: &BTreeMap<String, VarType>,
+        template: &Template,
+        nodes: &[Node],
+        depth: usize,
+    ) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::If(_, block) => self.check_includes(name, vars, template, block, depth)?,
+                Node::Partial(partial) => {
+                    let included = self.resolve_partial(name, template, partial, depth)?;
+                    for (var, expected) in &included.vars {
+                        match vars.get(var) {
+                            None => {
+                                return Err(TemplateError::Missing {
+                                    template: name.to_string(),
+                                    name: var.clone(),
+                                });
+                            }
+                            Some(found) if found != expected => {
+                                return Err(TemplateError::TypeMismatch {
+                                    template: name.to_string(),
+                                    name: var.clone(),
+                                    expected: *expected,
+                                    found: *found,
+                                });
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                    self.check_includes(name, vars, included, &included.nodes, depth + 1)?;
+                }
+                Node::Text(_) | Node::Var(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// FNV-1a hash of every source in the set.
+    fn hash(&self) -> String {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let entries = self.templates.iter().chain(&self.partials);
+        for (name, template) in entries {
+            for part in std::iter::once(name.as_str()).chain(template.sources()) {
+                for byte in part.bytes().chain([0]) {
+                    hash ^= u64::from(byte);
+                    hash = hash.wrapping_mul(0x0100_0000_01b3);
+                }
+            }
+        }
+        format!("{:08x}", hash >> 32)
+    }
+}
+
+/// `(name, source)` of the `*.md` files directly in `dir`, sorted by name.
+fn read_templates(dir: &Path) -> Result<Vec<(String, String)>, TemplateError> {
+    let read_error = |source| TemplateError::Read {
+        path: dir.display().to_string(),
+        source,
+    };
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(read_error(e)),
+    };
+    let mut templates = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(read_error)?.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".md"))
+        else {
+            continue;
+        };
+        let source = std::fs::read_to_string(&path).map_err(|source| TemplateError::Read {
+            path: path.display().to_string(),
+            source,
+        })?;
+        templates.push((name.to_string(), source));
+    }
+    templates.sort();
+    Ok(templates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, path: &str, contents: &str) {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn comment_vars() -> TemplateVars {
+        TemplateVars::new()
+            .string("repository", "acme/app")
+            .string("pr_number", "42")
+            .string("source_branch", "feature")
+            .string("target_branch", "main")
+            .string("author", "alice")
+    }
+
+    #[test]
+    fn test_builtin_templates_render() {
+        let templates = Templates::builtin();
+        assert!(templates.version().starts_with("builtin-"));
+
+        let comment = templates.render("comment", &comment_vars()).unwrap();
+        assert!(comment.contains("github pr comment acme/app 42 -m"));
+        assert!(comment.contains("- Preserve existing code style and patterns.\n\n## Posting"));
+        assert!(comment.contains("```\n\n## Making Code Changes"));
+
+        let review = templates.render("review", &comment_vars()).unwrap();
+        assert!(review.contains("## Output Format"));
+        assert!(review.ends_with("production behaviour)\n"));
+
+        let err = templates
+            .render("comment", &TemplateVars::new())
+            .unwrap_err();
+        assert!(matches!(err, TemplateError::Missing { .. }));
+        let vars = comment_vars().number("pr_number", 42);
+        let err = templates.render("comment", &vars).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template `comment`: variable `pr_number` is number, expected string"
+        );
+    }
+
+    #[test]
+    fn test_syntax() {
+        let template = Template::parse(
+            "t",
+            "+++\n[vars]\ndraft = \"bool\"\nlabels = \"list\"\n+++\nA{{#if draft}} draft{{/if}}{{#if labels}} [{{labels}}]{{/if}}.\n",
+            None,
+        )
+        .unwrap();
+        let templates = Templates {
+            templates: BTreeMap::from([("t".to_string(), template)]),
+            partials: BTreeMap::new(),
+            version: String::new(),
+        };
+        let vars = TemplateVars::new()
+            .bool("draft", true)
+            .list("labels", vec!["bug".into(), "ui".into()]);
+        assert_eq!(
+            templates.render("t", &vars).unwrap(),
+            "A draft [bug, ui].\n"
+        );
+        let vars = TemplateVars::new()
+            .bool("draft", false)
+            .list("labels", vec![]);
+        assert_eq!(templates.render("t", &vars).unwrap(), "A.\n");
+
+        for (source, error) in [
+            ("{{name}}", "template `t` uses undeclared variable `name`"),
+            ("{{#if Draft}}", "template `t`: invalid tag `#if Draft`"),
+            ("{{#if name}}", "template `t`: `{{#if name}}` is not closed"),
+            ("{{oops", "template `t`: unclosed `{{`"),
+            ("{{/if}}", "template `t`: `{{/if}}` without `{{#if}}`"),
+            (
+                "+++\n[vars]\nx = \"date\"\n+++\n",
+                "template `t`: invalid front matter: unknown variant `date`",
+            ),
+        ] {
+            let err = Template::parse("t", source, None).unwrap_err().to_string();
+            assert!(err.starts_with(error), "{source}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_overrides() {
+        let dir = std::env::temp_dir().join(format!("templates-{}", std::process::id()));
+        let service = dir.join("service");
+        let repo = dir.join("repo");
+        write(&service, "VERSION", "2024.06\n");
+        write(&service, "partials/rules.md", "- Keep changes small.\n");
+        write(
+            &repo,
+            ".claude/prompts/comment.md",
+            "+++\n[vars]\nrepository = \"string\"\n+++\n{{> base}}\nThis is {{repository}}.\n",
+        );
+
+        let templates = Templates::load(&service).unwrap();
+        assert!(templates.version().starts_with("2024.06-"));
+        let overridden = templates.clone().with_overrides(&repo).unwrap();
+        assert!(overridden.version().starts_with(templates.version()));
+        assert!(overridden.version().contains("+repo-"));
+
+        let comment = overridden.render("comment", &comment_vars()).unwrap();
+        assert!(comment.contains("- Keep changes small.\n\n## Posting"));
+        assert!(!comment.contains("Preserve existing code style"));
+        assert!(comment.ends_with("configured.\nThis is acme/app.\n"));
+
+        write(
+            &repo,
+            ".claude/prompts/comment.md",
+            "+++\n[vars]\nbranch = \"string\"\n+++\n{{branch}}\n",
+        );
+        let err = templates.clone().with_overrides(&repo).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template `comment` declares `branch`, which is not passed to it"
+        );
+
+        write(&repo, ".claude/prompts/comment.md", "{{> missing}}\n");
+        let err = templates.clone().with_overrides(&repo).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template `comment` includes unknown partial `missing`"
+        );
+
+        std::fs::remove_file(repo.join(".claude/prompts/comment.md")).unwrap();
+        write(&repo, ".claude/prompts/reveiw.md", "typo\n");
+        let err = templates.clone().with_overrides(&repo).unwrap_err();
+        assert_eq!(err.to_string(), "unknown template `reveiw`");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
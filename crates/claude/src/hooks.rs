@@ -0,0 +1,91 @@
+//! Claude Code hooks.
+//!
+//! Claude Code runs with `--dangerously-skip-permissions`, so its own tools
+//! are not checked by the agent's command policy. A `PreToolUse` hook on the
+//! Bash tool closes that gap: Claude Code runs the hook command before every
+//! Bash call, passing the call as JSON on stdin, and skips the call when the
+//! hook denies it.
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+/// Input of a `PreToolUse` hook, read from its stdin.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PreToolUseInput {
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: Value,
+}
+
+impl PreToolUseInput {
+    /// The command line of a Bash tool call.
+    pub fn bash_command(&self) -> Option<&str> {
+        if self.tool_name != "Bash" {
+            return None;
+        }
+        self.tool_input["command"].as_str()
+    }
+}
+
+/// Hook output denying the tool call; Claude Code shows `reason` to the
+/// model.
+pub fn deny(reason: &str) -> Value {
+    json!({
+        "hookSpecificOutput": {
+            "hookEventName": "PreToolUse",
+            "permissionDecision": "deny",
+            "permissionDecisionReason": reason,
+        }
+    })
+}
+
+/// Settings running `command` before every Bash tool call.
+pub fn bash_hook_settings(command: &str) -> Value {
+    json!({
+        "hooks": {
+            "PreToolUse": [{
+                "matcher": "Bash",
+                "hooks": [{ "type": "command", "command": command }]
+            }]
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bash_command() {
+        let input: PreToolUseInput = serde_json::from_value(json!({
+            "session_id": "abc",
+            "hook_event_name": "PreToolUse",
+            "tool_name": "Bash",
+            "tool_input": { "command": "cargo test", "description": "Run tests" }
+        }))
+        .unwrap();
+        assert_eq!(input.bash_command(), Some("cargo test"));
+
+        let input: PreToolUseInput = serde_json::from_value(json!({
+            "tool_name": "Read",
+            "tool_input": { "file_path": "/work/repo/src/main.rs" }
+        }))
+        .unwrap();
+        assert_eq!(input.bash_command(), None);
+    }
+
+    #[test]
+    fn test_settings() {
+        let settings = bash_hook_settings("/usr/bin/worker policy-hook /tmp/policy.json");
+        let hook = &settings["hooks"]["PreToolUse"][0];
+        assert_eq!(hook["matcher"], "Bash");
+        assert_eq!(
+            hook["hooks"][0]["command"],
+            "/usr/bin/worker policy-hook /tmp/policy.json"
+        );
+        assert_eq!(
+            deny("no")["hookSpecificOutput"]["permissionDecision"],
+            "deny"
+        );
+    }
+}
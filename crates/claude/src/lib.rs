@@ -1,5 +1,6 @@
 //! Claude Code integration for the agent system.
 
+pub mod hooks;
 pub mod output;
 pub mod process;
 
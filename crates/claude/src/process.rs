@@ -18,26 +18,23 @@ pub struct ClaudeProcess {
     child: Child,
     stdin: ChildStdin,
     stdout: BufReader<ChildStdout>,
+    /// Number of conversation messages already sent to the session.
+    sent_messages: usize,
 }
 
 impl ClaudeProcess {
     /// Spawn a new Claude Code process.
     pub fn spawn(working_dir: &Path) -> Result<Self, Error> {
-        info!(cwd = %working_dir.display(), "Spawning Claude Code process");
-
-        let mut child = Command::new("claude")
-            .arg("--print")
-            .args(["--model", "claude-opus-4-6"])
-            .args(["--input-format", "stream-json"])
-            .args(["--output-format", "stream-json"])
-            .arg("--verbose")
-            .args(["--dangerously-skip-permissions"]) // Running in isolated container
-            .current_dir(working_dir)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit()) // Let stderr pass through for debugging
-            .spawn()
-            .map_err(Error::Io)?;
+        Self::start(command(working_dir, None))
+    }
+
+    /// Spawn a Claude Code process with a settings file, e.g. for hooks.
+    pub fn spawn_with_settings(working_dir: &Path, settings: &Path) -> Result<Self, Error> {
+        Self::start(command(working_dir, Some(settings)))
+    }
+
+    fn start(mut command: Command) -> Result<Self, Error> {
+        let mut child = command.spawn().map_err(Error::Io)?;
 
         let stdin = child
             .stdin
@@ -52,6 +49,7 @@ impl ClaudeProcess {
             child,
             stdin,
             stdout: BufReader::new(stdout),
+            sent_messages: 0,
         })
     }
 
@@ -113,6 +111,29 @@ impl ClaudeProcess {
     }
 }
 
+/// The Claude Code command line, in stream-json mode.
+fn command(working_dir: &Path, settings: Option<&Path>) -> Command {
+    info!(cwd = %working_dir.display(), "Spawning Claude Code process");
+
+    let mut command = Command::new("claude");
+    command
+        .arg("--print")
+        .args(["--model", "claude-opus-4-6"])
+        .args(["--input-format", "stream-json"])
+        .args(["--output-format", "stream-json"])
+        .arg("--verbose")
+        .args(["--dangerously-skip-permissions"]); // Running in isolated container
+    if let Some(settings) = settings {
+        command.arg("--settings").arg(settings);
+    }
+    command
+        .current_dir(working_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit()); // Let stderr pass through for debugging
+    command
+}
+
 /// Log a Claude output event for visibility.
 fn log_claude_output(output: &ClaudeOutput) {
     match output {
@@ -184,23 +205,39 @@ impl Drop for ClaudeProcess {
 #[async_trait]
 impl ClaudeBackend for ClaudeProcess {
     async fn prompt(&mut self, messages: &[Message]) -> Result<Vec<ClaudeResponse>, Error> {
-        // Build prompt from messages
-        let prompt = build_prompt(messages);
+        // The session keeps its own context, so only send what it hasn't seen
+        let prompt = build_prompt(&new_messages(messages, self.sent_messages));
+        self.sent_messages = messages.len();
 
         // Send and collect outputs
         let outputs = self.send(&prompt)?;
 
         // Convert to ClaudeResponse
-        let responses = outputs.into_iter().filter_map(convert_output).collect();
+        let responses = outputs.into_iter().flat_map(convert_output).collect();
 
         Ok(responses)
     }
+
+    /// Claude Code runs its own tools (Read, Edit, Bash, ...).
+    fn runs_own_tools(&self) -> bool {
+        true
+    }
+}
+
+/// Messages added since the last turn, excluding Claude's own output.
+fn new_messages(messages: &[Message], sent: usize) -> Vec<Message> {
+    messages
+        .iter()
+        .skip(sent)
+        .filter(|msg| msg.role != MessageRole::Assistant)
+        .cloned()
+        .collect()
 }
 
 fn build_prompt(messages: &[Message]) -> String {
     let mut prompt = String::new();
 
-    for msg in messages {
+    for msg in messages.iter().filter(|msg| !msg.content.is_empty()) {
         match msg.role {
             MessageRole::System => {
                 // System messages are typically set via --system-prompt,
@@ -230,35 +267,75 @@ fn build_prompt(messages: &[Message]) -> String {
     prompt
 }
 
-fn convert_output(output: ClaudeOutput) -> Option<ClaudeResponse> {
+fn convert_output(output: ClaudeOutput) -> Vec<ClaudeResponse> {
     match output {
         ClaudeOutput::Assistant {
             message: Some(msg), ..
         } => convert_assistant_message(msg.content),
         ClaudeOutput::Result {
-            subtype, result, ..
-        } => Some(ClaudeResponse::Result { subtype, result }),
-        _ => None,
+            subtype,
+            result,
+            is_error,
+            total_cost_usd,
+            usage,
+            ..
+        } => {
+            // The result carries cumulative usage for the whole session.
+            let mut responses = Vec::new();
+            if let Some(usage) = usage {
+                responses.push(ClaudeResponse::Usage {
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                });
+            }
+            responses.push(ClaudeResponse::Result {
+                subtype,
+                result,
+                is_error,
+                cost_usd: total_cost_usd,
+            });
+            responses
+        }
+        _ => Vec::new(),
     }
 }
 
-fn convert_assistant_message(blocks: Vec<ContentBlock>) -> Option<ClaudeResponse> {
-    for block in blocks {
-        match block {
-            ContentBlock::Text { text } => return Some(ClaudeResponse::Text(text)),
+fn convert_assistant_message(blocks: Vec<ContentBlock>) -> Vec<ClaudeResponse> {
+    blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text { text } => Some(ClaudeResponse::Text(text)),
             ContentBlock::ToolUse { id, name, input } => {
-                return Some(ClaudeResponse::ToolUse { id, name, input });
+                Some(ClaudeResponse::ToolUse { id, name, input })
             }
-            _ => {}
-        }
-    }
-    None
+            ContentBlock::ToolResult { .. } => None,
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_command_settings() {
+        let args = |command: Command| -> Vec<String> {
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect()
+        };
+        let plain = args(command(Path::new("/work/repo"), None));
+        assert!(!plain.contains(&"--settings".to_string()));
+
+        let hooked = args(command(
+            Path::new("/work/repo"),
+            Some(Path::new("/tmp/settings.json")),
+        ));
+        let at = hooked.iter().position(|arg| arg == "--settings").unwrap();
+        assert_eq!(hooked[at + 1], "/tmp/settings.json");
+    }
+
     #[test]
     fn test_build_prompt() {
         let messages = vec![
@@ -276,4 +353,67 @@ mod tests {
         assert!(prompt.contains("You are a reviewer."));
         assert!(prompt.contains("Review this code."));
     }
+
+    #[test]
+    fn test_build_prompt_skips_empty_system() {
+        let messages = vec![
+            Message {
+                role: MessageRole::System,
+                content: String::new(),
+            },
+            Message {
+                role: MessageRole::User,
+                content: "Review this code.".into(),
+            },
+        ];
+
+        assert_eq!(build_prompt(&messages), "Review this code.");
+    }
+
+    #[test]
+    fn test_new_messages_only_sends_unseen_user_input() {
+        let message = |role, content: &str| Message {
+            role,
+            content: content.into(),
+        };
+        let messages = vec![
+            message(MessageRole::System, "system"),
+            message(MessageRole::User, "Review this code."),
+            message(MessageRole::Assistant, "Not JSON"),
+            message(MessageRole::User, "Reply with JSON."),
+        ];
+
+        assert_eq!(new_messages(&messages, 0).len(), 3);
+        assert_eq!(
+            build_prompt(&new_messages(&messages, 2)),
+            "Reply with JSON."
+        );
+    }
+
+    #[test]
+    fn test_convert_result_output() {
+        let json = r#"{
+            "type": "result",
+            "subtype": "success",
+            "result": "Done",
+            "is_error": false,
+            "total_cost_usd": 0.05,
+            "usage": {"input_tokens": 100, "output_tokens": 20}
+        }"#;
+        let output: ClaudeOutput = serde_json::from_str(json).unwrap();
+        let responses = convert_output(output);
+
+        assert!(matches!(
+            responses[0],
+            ClaudeResponse::Usage {
+                input_tokens: 100,
+                output_tokens: 20
+            }
+        ));
+        assert!(matches!(
+            &responses[1],
+            ClaudeResponse::Result { result: Some(text), is_error: false, cost_usd: Some(_), .. }
+                if text == "Done"
+        ));
+    }
 }
@@ -17,7 +17,7 @@ use serde::Deserialize;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
-use claude_agent_server::FailedItem;
+use claude_agent_server::{FailedItem, JobReport};
 
 const NAMESPACE: &str = "claude-agent";
 
@@ -73,6 +73,14 @@ enum Commands {
         pr: u64,
     },
 
+    /// Write the description of a GitHub PR
+    DescribeGithub {
+        #[arg(long, short)]
+        repo: String,
+        #[arg(long, short)]
+        pr: u64,
+    },
+
     /// Trigger a Sentry fix job
     SentryFix {
         #[arg(long, short)]
@@ -103,6 +111,9 @@ enum Commands {
     /// Retry a failed item
     Retry { id: String },
 
+    /// Show the structured report of a finished job
+    Report { id: String },
+
     /// Show logs from a running or completed review job
     Logs {
         job: Option<String>,
@@ -175,6 +186,13 @@ async fn handle_server_command(command: Commands, server_url: &str, api_key: &st
             println!("Job ID: {id}");
         }
 
+        Commands::DescribeGithub { repo, pr } => {
+            let id =
+                api_queue_github_review(server_url, api_key, &repo, pr, Some("describe")).await?;
+            println!("Queued description for #{} in {}", pr, repo);
+            println!("Job ID: {id}");
+        }
+
         Commands::SentryFix {
             org,
             project,
@@ -190,6 +208,7 @@ async fn handle_server_command(command: Commands, server_url: &str, api_key: &st
         Commands::Stats => api_stats(server_url, api_key).await?,
         Commands::ListFailed { limit } => api_list_failed(server_url, api_key, limit).await?,
         Commands::Retry { id } => api_retry(server_url, api_key, &id).await?,
+        Commands::Report { id } => api_report(server_url, api_key, &id).await?,
         Commands::CheckTokens => api_check_tokens(server_url, api_key).await?,
     }
 
@@ -495,6 +514,90 @@ async fn api_retry(server_url: &str, api_key: &str, id: &str) -> Result<()> {
     Ok(())
 }
 
+async fn api_report(server_url: &str, api_key: &str, id: &str) -> Result<()> {
+    let client = create_api_client(api_key)?;
+    let url = format!("{}/api/reports/{}", server_url.trim_end_matches('/'), id);
+    let resp = client
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to fetch job report")?;
+
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        println!("No report for job: {id}");
+        return Ok(());
+    }
+    if !resp.status().is_success() {
+        bail!("API error: {} - {}", resp.status(), resp.text().await?);
+    }
+
+    let report: JobReport = resp.json().await.context("Failed to parse job report")?;
+
+    println!("  Job:      {}", report.job);
+    println!("  Status:   {:?}", report.status);
+    if let Some(error) = &report.error {
+        println!("  Error:    {error}");
+    }
+    if let Some(decision) = &report.decision {
+        println!("  Decision: {decision:?}");
+    }
+    println!("  Issues:   {}", report.issues);
+    if let Some(score) = report.score {
+        println!("  Score:    {score}/100");
+    }
+    if let Some(effort) = report.effort {
+        println!("  Effort:   {effort}/5");
+    }
+    if let Some(tests) = &report.tests {
+        println!("  Tests:    {tests:?}");
+    }
+    if report.todos > 0 {
+        println!("  TODOs:    {}", report.todos);
+    }
+    if let Some(suggestions) = &report.suggestions {
+        println!(
+            "  Suggestions: {} applied, {} dropped",
+            suggestions.applied, suggestions.failed
+        );
+    }
+    if let Some(fingerprints) = &report.fingerprints {
+        println!(
+            "  Findings: {} new, {} suppressed, {} changed since last review",
+            fingerprints.opened.len(),
+            fingerprints.suppressed_count,
+            fingerprints.changed.len()
+        );
+        for suppressed in &fingerprints.suppressed {
+            let location = match (&suppressed.file, suppressed.line) {
+                (Some(file), Some(line)) => format!("{file}:{line}"),
+                (Some(file), None) => file.clone(),
+                _ => "PR-wide".into(),
+            };
+            println!(
+                "            suppressed {location} (matches {})",
+                suppressed.fingerprint
+            );
+        }
+    }
+    println!("  Tools:    {}", report.metrics.tool_calls);
+    println!("  Tokens:   {}", report.metrics.total_tokens);
+    println!("  Cost:     ${:.4}", report.metrics.cost_usd);
+    if let Some(secs) = report.metrics.duration_secs() {
+        println!("  Duration: {secs:.1}s");
+    }
+    println!("  Worker:   {}", report.worker_version);
+    if let Some(version) = &report.prompt_version {
+        println!("  Prompts:  {version}");
+    }
+    println!("  Finished: {}", report.finished_at);
+    if let Some(summary) = &report.summary {
+        println!();
+        println!("{summary}");
+    }
+
+    Ok(())
+}
+
 async fn api_queue_github_review(
     server_url: &str,
     api_key: &str,
@@ -11,11 +11,21 @@ use crate::stream::EventStream;
 /// Maximum number of iterations before forcing termination.
 const MAX_ITERATIONS: u32 = 100;
 
+/// Maximum number of turns spent asking Claude to fix an invalid review result.
+const MAX_REPAIR_TURNS: u32 = 2;
+
 /// Trait for Claude Code integration.
 #[async_trait]
 pub trait ClaudeBackend: Send + Sync {
     /// Send a prompt and get response events.
     async fn prompt(&mut self, messages: &[Message]) -> Result<Vec<ClaudeResponse>, Error>;
+
+    /// Whether the backend executes tool calls itself (e.g. Claude Code's
+    /// built-in tools). Tool uses are then recorded but not dispatched to the
+    /// executor, and a `Result` response ends the session.
+    fn runs_own_tools(&self) -> bool {
+        false
+    }
 }
 
 /// A message in the conversation.
@@ -47,8 +57,11 @@ pub enum ClaudeResponse {
     Result {
         subtype: String,
         result: Option<String>,
+        is_error: bool,
+        /// Cost of the session so far, not of this turn alone.
+        cost_usd: Option<f64>,
     },
-    /// Token usage info.
+    /// Token usage of the session so far, not of this turn alone.
     Usage {
         input_tokens: u64,
         output_tokens: u64,
@@ -62,6 +75,13 @@ pub trait ActionExecutor: Send + Sync {
     async fn execute(&self, action: &Action) -> Result<Observation, Error>;
 }
 
+#[async_trait]
+impl<T: ActionExecutor + ?Sized> ActionExecutor for &T {
+    async fn execute(&self, action: &Action) -> Result<Observation, Error> {
+        (**self).execute(action).await
+    }
+}
+
 /// The main agent controller.
 pub struct AgentController<C, E> {
     pub state: State,
@@ -69,6 +89,12 @@ pub struct AgentController<C, E> {
     claude: C,
     executor: E,
     system_prompt: String,
+    require_review_result: bool,
+    repair_turns: u32,
+    /// Cumulative cost and tokens last reported by the session, so each
+    /// turn adds only its own usage to the metrics.
+    session_cost_usd: f64,
+    session_tokens: u64,
 }
 
 impl<C, E> AgentController<C, E>
@@ -83,6 +109,10 @@ where
             claude,
             executor,
             system_prompt: system_prompt.into(),
+            require_review_result: false,
+            repair_turns: 0,
+            session_cost_usd: 0.0,
+            session_tokens: 0,
         }
     }
 
@@ -91,6 +121,14 @@ where
         self
     }
 
+    /// Require the final answer to be a `ReviewResult` JSON object.
+    ///
+    /// Invalid answers trigger a repair turn instead of ending the session.
+    pub fn require_review_result(mut self) -> Self {
+        self.require_review_result = true;
+        self
+    }
+
     /// Run the agent loop until completion.
     pub async fn run(&mut self, initial_prompt: &str) -> Result<ReviewResult, Error> {
         info!("Starting agent controller");
@@ -148,26 +186,110 @@ where
                         return Ok(Some(result));
                     }
                 }
-                ClaudeResponse::Result { subtype, result } => {
-                    info!(subtype = %subtype, "Claude returned result");
-                    if let Some(result_str) = result
-                        && let Ok(review_result) = serde_json::from_str::<ReviewResult>(&result_str)
-                    {
-                        self.state.set_finished(review_result.clone());
-                        return Ok(Some(review_result));
+                ClaudeResponse::Result {
+                    subtype,
+                    result,
+                    is_error,
+                    cost_usd,
+                } => {
+                    info!(subtype = %subtype, is_error, "Claude returned result");
+                    if let Some(cost) = cost_usd {
+                        self.state
+                            .record_cost((cost - self.session_cost_usd).max(0.0));
+                        self.session_cost_usd = cost;
+                    }
+                    if let Some(result) = self.handle_result(&subtype, result, is_error).await? {
+                        return Ok(Some(result));
                     }
                 }
                 ClaudeResponse::Usage {
                     input_tokens,
                     output_tokens,
                 } => {
-                    self.state.record_api_call(input_tokens + output_tokens);
+                    let tokens = input_tokens + output_tokens;
+                    self.state
+                        .record_api_call(tokens.saturating_sub(self.session_tokens));
+                    self.session_tokens = tokens;
                 }
             }
         }
         Ok(None)
     }
 
+    /// Handle a final result from Claude, returning a result if the agent finished.
+    async fn handle_result(
+        &mut self,
+        subtype: &str,
+        result: Option<String>,
+        is_error: bool,
+    ) -> Result<Option<ReviewResult>, Error> {
+        if is_error {
+            let message = result
+                .filter(|text| !text.is_empty())
+                .unwrap_or_else(|| format!("Claude session ended with {subtype}"));
+            error!(error = %message, "Claude session failed");
+            self.state.set_error(&message);
+            return Err(Error::SessionFailed(message));
+        }
+
+        let parsed = match &result {
+            Some(text) => ReviewResult::parse(text),
+            None => Err(Error::InvalidReviewResult("empty response".into())),
+        };
+        match parsed {
+            Ok(review_result) => {
+                self.state.set_finished(review_result.clone());
+                return Ok(Some(review_result));
+            }
+            Err(error) if self.require_review_result => {
+                self.request_repair(error).await?;
+                return Ok(None);
+            }
+            Err(_) => {}
+        }
+
+        if !self.claude.runs_own_tools() {
+            return Ok(None);
+        }
+
+        // The session is over: accept the final text as the outcome.
+        match result {
+            Some(text) => {
+                let review_result = ReviewResult::from_summary(text);
+                self.state.set_finished(review_result.clone());
+                Ok(Some(review_result))
+            }
+            None => {
+                self.state
+                    .set_error("Claude session ended without a result");
+                Err(Error::NoResult)
+            }
+        }
+    }
+
+    /// Ask Claude to resend its answer as a valid review result.
+    async fn request_repair(&mut self, error: Error) -> Result<(), Error> {
+        if self.repair_turns >= MAX_REPAIR_TURNS {
+            error!(error = %error, "Review result still invalid after repair turns");
+            self.state.set_error(error.to_string());
+            return Err(error);
+        }
+        self.repair_turns += 1;
+        warn!(error = %error, attempt = self.repair_turns, "Requesting review result repair");
+
+        let event = Event::message(
+            "user",
+            format!(
+                "Your final answer could not be used: {error}\n\n\
+                 Reply with only the review result JSON object described in the \
+                 output format, with no other text."
+            ),
+        );
+        self.state.add_event(event.clone());
+        self.stream.add_event(event).await;
+        Ok(())
+    }
+
     /// Handle a tool use request, returning a result if the agent finished.
     async fn handle_tool_use(
         &mut self,
@@ -175,6 +297,10 @@ where
         input: &serde_json::Value,
     ) -> Result<Option<ReviewResult>, Error> {
         debug!(tool = %name, "Tool use requested");
+        if self.claude.runs_own_tools() {
+            self.record_builtin_tool_use(name, input).await;
+            return Ok(None);
+        }
         self.state.set_waiting();
         self.state.record_tool_call();
 
@@ -210,6 +336,17 @@ where
         Ok(None)
     }
 
+    /// Record a tool call that the backend has already executed itself.
+    async fn record_builtin_tool_use(&mut self, name: &str, input: &serde_json::Value) {
+        self.state.record_tool_call();
+        let event = Event::new(EventPayload::ToolUse {
+            name: name.into(),
+            input: input.clone(),
+        });
+        self.state.add_event(event.clone());
+        self.stream.add_event(event).await;
+    }
+
     async fn handle_invalid_action(
         &mut self,
         name: &str,
@@ -291,6 +428,10 @@ fn event_to_message(event: &Event) -> Option<Message> {
                 content: format!("Tool result: {content}"),
             })
         }
+        EventPayload::ToolUse { name, input } => Some(Message {
+            role: MessageRole::Assistant,
+            content: format!("Tool call: {name} {input}"),
+        }),
     }
 }
 
@@ -316,6 +457,7 @@ mod tests {
     struct MockClaude {
         responses: Vec<Vec<ClaudeResponse>>,
         call_count: usize,
+        own_tools: bool,
     }
 
     #[async_trait]
@@ -329,6 +471,39 @@ mod tests {
                 Ok(vec![])
             }
         }
+
+        fn runs_own_tools(&self) -> bool {
+            self.own_tools
+        }
+    }
+
+    fn session(responses: Vec<ClaudeResponse>) -> AgentController<MockClaude, MockExecutor> {
+        session_turns(vec![responses])
+    }
+
+    fn session_turns(
+        responses: Vec<Vec<ClaudeResponse>>,
+    ) -> AgentController<MockClaude, MockExecutor> {
+        let claude = MockClaude {
+            responses,
+            call_count: 0,
+            own_tools: true,
+        };
+        AgentController::new(claude, MockExecutor, "test")
+    }
+
+    fn final_result(result: Option<&str>, is_error: bool) -> ClaudeResponse {
+        ClaudeResponse::Result {
+            subtype: if is_error {
+                "error_during_execution"
+            } else {
+                "success"
+            }
+            .into(),
+            result: result.map(String::from),
+            is_error,
+            cost_usd: Some(0.25),
+        }
     }
 
     struct MockExecutor;
@@ -341,7 +516,9 @@ mod tests {
                     path: path.clone(),
                     content: "file content".into(),
                 }),
-                Action::Approve => Ok(Observation::Approved),
+                Action::Approve => Ok(Observation::Approved {
+                    review_id: "1".into(),
+                }),
                 _ => Ok(Observation::Error {
                     message: "not implemented".into(),
                 }),
@@ -354,6 +531,7 @@ mod tests {
         let claude = MockClaude {
             responses: vec![],
             call_count: 0,
+            own_tools: false,
         };
         let executor = MockExecutor;
         let controller = AgentController::new(claude, executor, "test");
@@ -362,4 +540,105 @@ mod tests {
         let action = controller.parse_action("read_file", &input).unwrap();
         assert!(matches!(action, Action::ReadFile { path } if path == "src/main.rs"));
     }
+
+    #[tokio::test]
+    async fn test_session_free_text_result() {
+        let mut controller = session(vec![
+            ClaudeResponse::ToolUse {
+                id: "tool_1".into(),
+                name: "Bash".into(),
+                input: serde_json::json!({"command": "ls"}),
+            },
+            ClaudeResponse::Text("Pushed the fix.".into()),
+            final_result(Some("Created PR #12"), false),
+        ]);
+
+        let result = controller.run("fix it").await.unwrap();
+        assert_eq!(result.summary, "Created PR #12");
+        assert_eq!(controller.state.agent_state, AgentState::Finished);
+        assert_eq!(controller.state.metrics.tool_calls, 1);
+        assert_eq!(controller.state.metrics.cost_usd, 0.25);
+        assert!(controller.state.history.iter().any(|event| matches!(
+            &event.payload,
+            EventPayload::ToolUse { name, .. } if name == "Bash"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_session_error_result() {
+        let mut controller = session(vec![final_result(None, true)]);
+
+        let err = controller.run("fix it").await.unwrap_err();
+        assert!(matches!(err, Error::SessionFailed(msg) if msg.contains("error_during_execution")));
+        assert_eq!(controller.state.agent_state, AgentState::Error);
+    }
+
+    #[tokio::test]
+    async fn test_session_structured_result() {
+        let json = r#"{"decision":"approved","summary":"LGTM","issues":[]}"#;
+        let mut controller = session(vec![final_result(Some(json), false)]);
+
+        let result = controller.run("review").await.unwrap();
+        assert_eq!(result.decision, crate::event::ReviewDecision::Approved);
+    }
+
+    #[tokio::test]
+    async fn test_session_repairs_invalid_result() {
+        let json = r#"{"decision":"comment","summary":"One nit","issues":[]}"#;
+        let mut controller = session_turns(vec![
+            vec![final_result(Some("The PR looks fine."), false)],
+            vec![final_result(Some(json), false)],
+        ])
+        .require_review_result();
+
+        let result = controller.run("review").await.unwrap();
+        assert_eq!(result.summary, "One nit");
+        assert!(controller.state.history.iter().any(|event| matches!(
+            &event.payload,
+            EventPayload::Message { role, content }
+                if role == "user" && content.contains("could not be used")
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_session_usage_is_cumulative() {
+        let json = r#"{"decision":"comment","summary":"One nit","issues":[]}"#;
+        let turn = |result: &str, tokens: u64, cost: f64| {
+            vec![
+                ClaudeResponse::Usage {
+                    input_tokens: tokens,
+                    output_tokens: tokens / 10,
+                },
+                ClaudeResponse::Result {
+                    subtype: "success".into(),
+                    result: Some(result.into()),
+                    is_error: false,
+                    cost_usd: Some(cost),
+                },
+            ]
+        };
+        let mut controller = session_turns(vec![
+            turn("The PR looks fine.", 1000, 0.25),
+            turn(json, 1500, 0.40),
+        ])
+        .require_review_result();
+
+        controller.run("review").await.unwrap();
+        let metrics = &controller.state.metrics;
+        assert_eq!(metrics.api_calls, 2);
+        assert_eq!(metrics.total_tokens, 1650);
+        assert!((metrics.cost_usd - 0.40).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_session_repair_gives_up() {
+        let turns = (0..=MAX_REPAIR_TURNS)
+            .map(|_| vec![final_result(Some("not json"), false)])
+            .collect();
+        let mut controller = session_turns(turns).require_review_result();
+
+        let err = controller.run("review").await.unwrap_err();
+        assert!(matches!(err, Error::InvalidReviewResult(_)));
+        assert_eq!(controller.state.agent_state, AgentState::Error);
+    }
 }
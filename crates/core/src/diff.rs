@@ -0,0 +1,906 @@
+//! Unified diff parsing.
+//!
+//! [`Diff::parse`] turns `git diff` output (or a plain `diff -u`) into files,
+//! hunks and lines with their old and new line numbers. Parsing is lenient:
+//! extended headers and binary patches are kept in the file's header, and a
+//! hunk shorter than its header says ends at the next file, so any text gives
+//! a usable, possibly empty, diff. Formatting a parsed diff gives back the
+//! text git wrote.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// A parsed unified diff.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub files: Vec<DiffFile>,
+}
+
+/// How a file changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+}
+
+/// Side of the diff a line number refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// The old version (removed and context lines).
+    Left,
+    /// The new version (added and context lines).
+    Right,
+}
+
+impl Side {
+    /// Name used by the GitHub review comments API.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Left => "LEFT",
+            Self::Right => "RIGHT",
+        }
+    }
+}
+
+/// The changes to one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffFile {
+    /// Path before the change; `None` for added files.
+    pub old_path: Option<String>,
+    /// Path after the change; `None` for deleted files.
+    pub new_path: Option<String>,
+    pub status: FileStatus,
+    /// Similarity of a renamed or copied file, in percent.
+    pub similarity: Option<u8>,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    pub binary: bool,
+    /// Lines before the first hunk (`diff --git`, extended headers, `---` and
+    /// `+++`, binary patch data), as in the diff.
+    pub header: String,
+    pub hunks: Vec<Hunk>,
+}
+
+/// A `@@ -a,b +c,d @@` hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: u32,
+    pub old_count: u32,
+    pub new_start: u32,
+    pub new_count: u32,
+    /// Text after the closing `@@`, usually the enclosing function.
+    pub section: String,
+    pub lines: Vec<DiffLine>,
+}
+
+/// Kind of a hunk line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// A line of a hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    /// Line number in the old version; `None` for added lines.
+    pub old_line: Option<u32>,
+    /// Line number in the new version; `None` for removed lines.
+    pub new_line: Option<u32>,
+    /// The line without its `+`, `-` or space prefix.
+    pub content: String,
+    /// Whether the line is followed by `\ No newline at end of file`.
+    pub no_newline: bool,
+}
+
+impl Diff {
+    /// Parse a unified diff.
+    pub fn parse(text: &str) -> Self {
+        let mut parser = Parser::default();
+        // Not `lines()`: a `\r` ending is part of a CRLF file's content.
+        for line in text.split_inclusive('\n') {
+            parser.line(line.strip_suffix('\n').unwrap_or(line));
+        }
+        parser.finish()
+    }
+
+    /// The file at `path`: its new path, or its old path if it was deleted.
+    pub fn file(&self, path: &str) -> Option<&DiffFile> {
+        self.files.iter().find(|file| file.path() == path)
+    }
+
+    pub fn added(&self) -> usize {
+        self.files.iter().map(DiffFile::added).sum()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.files.iter().map(DiffFile::removed).sum()
+    }
+
+    /// Number of added and removed lines.
+    pub fn changed_lines(&self) -> usize {
+        self.added() + self.removed()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.files.iter().try_for_each(|file| write!(f, "{file}"))
+    }
+}
+
+impl DiffFile {
+    fn new() -> Self {
+        Self {
+            old_path: None,
+            new_path: None,
+            status: FileStatus::Modified,
+            similarity: None,
+            old_mode: None,
+            new_mode: None,
+            binary: false,
+            header: String::new(),
+            hunks: Vec::new(),
+        }
+    }
+
+    /// The new path, or the old path of a deleted file.
+    pub fn path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+
+    pub fn added(&self) -> usize {
+        self.count(LineKind::Added)
+    }
+
+    pub fn removed(&self) -> usize {
+        self.count(LineKind::Removed)
+    }
+
+    fn count(&self, kind: LineKind) -> usize {
+        self.lines().filter(|line| line.kind == kind).count()
+    }
+
+    /// Every hunk line, in order.
+    pub fn lines(&self) -> impl Iterator<Item = &DiffLine> {
+        self.hunks.iter().flat_map(|hunk| &hunk.lines)
+    }
+
+    /// New line numbers of the added lines.
+    pub fn added_lines(&self) -> impl Iterator<Item = u32> + '_ {
+        self.lines()
+            .filter(|line| line.kind == LineKind::Added)
+            .filter_map(|line| line.new_line)
+    }
+
+    /// Whether only the file mode changed.
+    pub fn is_mode_change(&self) -> bool {
+        self.old_mode.is_some() && self.new_mode.is_some() && self.old_mode != self.new_mode
+    }
+
+    /// The hunk whose range on `side` contains `line`.
+    pub fn hunk_at(&self, side: Side, line: u32) -> Option<&Hunk> {
+        self.hunks
+            .iter()
+            .find(|hunk| hunk.range(side).is_some_and(|range| range.contains(&line)))
+    }
+
+    /// Whether a review comment can be placed on `line` of `side`: the line
+    /// must be inside a hunk.
+    pub fn is_commentable(&self, side: Side, line: u32) -> bool {
+        self.find_line(side, line).is_some()
+    }
+
+    /// Position of `line` of `side` in the file's diff, as used by the
+    /// GitHub API: 1 is the line below the first `@@` header, and later hunk
+    /// headers count as lines.
+    pub fn position(&self, side: Side, line: u32) -> Option<u32> {
+        self.find_line(side, line).map(|(position, _)| position)
+    }
+
+    fn find_line(&self, side: Side, line: u32) -> Option<(u32, &DiffLine)> {
+        let mut position = 0;
+        for (index, hunk) in self.hunks.iter().enumerate() {
+            if index > 0 {
+                position += 1;
+            }
+            for diff_line in &hunk.lines {
+                position += 1;
+                let number = match side {
+                    Side::Left => diff_line.old_line,
+                    Side::Right => diff_line.new_line,
+                };
+                if number == Some(line) {
+                    return Some((position, diff_line));
+                }
+                if diff_line.no_newline {
+                    position += 1;
+                }
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for DiffFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.header)?;
+        self.hunks.iter().try_for_each(|hunk| write!(f, "{hunk}"))
+    }
+}
+
+impl Hunk {
+    /// Parse a `@@ -a,b +c,d @@ section` header.
+    fn parse_header(line: &str) -> Option<Self> {
+        let rest = line.strip_prefix("@@ -")?;
+        let (ranges, section) = rest.split_once(" @@")?;
+        let (old, new) = ranges.split_once(" +")?;
+        let (old_start, old_count) = parse_range(old)?;
+        let (new_start, new_count) = parse_range(new)?;
+        Some(Self {
+            old_start,
+            old_count,
+            new_start,
+            new_count,
+            section: section.strip_prefix(' ').unwrap_or(section).to_string(),
+            lines: Vec::new(),
+        })
+    }
+
+    /// Lines covered on `side`; `None` when the hunk has no lines there
+    /// (e.g. the old side of an added file).
+    pub fn range(&self, side: Side) -> Option<RangeInclusive<u32>> {
+        let (start, count) = match side {
+            Side::Left => (self.old_start, self.old_count),
+            Side::Right => (self.new_start, self.new_count),
+        };
+        (count > 0).then(|| start..=start + count - 1)
+    }
+
+    pub fn new_range(&self) -> Option<RangeInclusive<u32>> {
+        self.range(Side::Right)
+    }
+
+    pub fn old_range(&self) -> Option<RangeInclusive<u32>> {
+        self.range(Side::Left)
+    }
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Git leaves out a count of 1.
+        let range = |start: u32, count: u32| match count {
+            1 => format!("{start}"),
+            _ => format!("{start},{count}"),
+        };
+        write!(
+            f,
+            "@@ -{} +{} @@",
+            range(self.old_start, self.old_count),
+            range(self.new_start, self.new_count)
+        )?;
+        if !self.section.is_empty() {
+            write!(f, " {}", self.section)?;
+        }
+        writeln!(f)?;
+        for line in &self.lines {
+            let prefix = match line.kind {
+                LineKind::Context => ' ',
+                LineKind::Added => '+',
+                LineKind::Removed => '-',
+            };
+            writeln!(f, "{prefix}{}", line.content)?;
+            if line.no_newline {
+                writeln!(f, "\\ No newline at end of file")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `start[,count]`, where a missing count means 1.
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    files: Vec<DiffFile>,
+    file: Option<DiffFile>,
+    /// Next (old, new) line numbers and the lines left in the current hunk.
+    old_line: u32,
+    new_line: u32,
+    old_left: u32,
+    new_left: u32,
+}
+
+impl Parser {
+    fn line(&mut self, line: &str) {
+        if self.old_left > 0 || self.new_left > 0 {
+            if self.hunk_line(line) {
+                return;
+            }
+            // The hunk is shorter than its header says.
+            self.old_left = 0;
+            self.new_left = 0;
+        }
+
+        // Headers of a diff saved with CRLF endings.
+        let raw = line;
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.starts_with('\\') {
+            if let Some(last) = self.last_line() {
+                last.no_newline = true;
+                return;
+            }
+        } else if let Some(rest) = line.strip_prefix("diff --git ") {
+            self.finish_file();
+            let mut file = DiffFile::new();
+            if let Some((old, new)) = git_header_paths(rest) {
+                file.old_path = Some(old);
+                file.new_path = Some(new);
+            }
+            self.file = Some(file);
+        } else if let Some(rest) = line.strip_prefix("--- ")
+            && self.file.as_ref().is_none_or(|file| !file.hunks.is_empty())
+        {
+            // A plain `diff -u` file without a `diff --git` line.
+            self.finish_file();
+            let mut file = DiffFile::new();
+            file.old_path = header_path(rest, "a/");
+            file.new_path = file.old_path.clone();
+            self.file = Some(file);
+        } else if line.starts_with("@@ ")
+            && let Some(hunk) = Hunk::parse_header(line)
+        {
+            self.old_line = hunk.old_start;
+            self.new_line = hunk.new_start;
+            self.old_left = hunk.old_count;
+            self.new_left = hunk.new_count;
+            self.file.get_or_insert_with(DiffFile::new).hunks.push(hunk);
+            return;
+        }
+
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        if file.hunks.is_empty() {
+            extended_header(file, line);
+            file.header.push_str(raw);
+            file.header.push('\n');
+        }
+    }
+
+    /// Add a line to the current hunk; false if it is not a hunk line.
+    fn hunk_line(&mut self, line: &str) -> bool {
+        let (kind, content) = match line.chars().next() {
+            Some(' ') | None => (LineKind::Context, line.get(1..).unwrap_or("")),
+            Some('+') if self.new_left > 0 => (LineKind::Added, &line[1..]),
+            Some('-') if self.old_left > 0 => (LineKind::Removed, &line[1..]),
+            Some('\\') => {
+                if let Some(last) = self.last_line() {
+                    last.no_newline = true;
+                }
+                return true;
+            }
+            _ => return false,
+        };
+        if kind == LineKind::Context && (self.old_left == 0 || self.new_left == 0) {
+            return false;
+        }
+        let old_line = (kind != LineKind::Added).then_some(self.old_line);
+        let new_line = (kind != LineKind::Removed).then_some(self.new_line);
+        if old_line.is_some() {
+            self.old_line += 1;
+            self.old_left -= 1;
+        }
+        if new_line.is_some() {
+            self.new_line += 1;
+            self.new_left -= 1;
+        }
+        let Some(hunk) = self.file.as_mut().and_then(|file| file.hunks.last_mut()) else {
+            return false;
+        };
+        hunk.lines.push(DiffLine {
+            kind,
+            old_line,
+            new_line,
+            content: content.to_string(),
+            no_newline: false,
+        });
+        true
+    }
+
+    fn last_line(&mut self) -> Option<&mut DiffLine> {
+        self.file.as_mut()?.hunks.last_mut()?.lines.last_mut()
+    }
+
+    fn finish_file(&mut self) {
+        let Some(mut file) = self.file.take() else {
+            return;
+        };
+        if file.old_path.is_none() {
+            file.status = FileStatus::Added;
+        } else if file.new_path.is_none() {
+            file.status = FileStatus::Deleted;
+        }
+        self.files.push(file);
+    }
+
+    fn finish(mut self) -> Diff {
+        self.finish_file();
+        Diff { files: self.files }
+    }
+}
+
+/// Apply an extended header line (`new file mode`, `rename from`, …).
+fn extended_header(file: &mut DiffFile, line: &str) {
+    if let Some(mode) = line.strip_prefix("new file mode ") {
+        file.old_path = None;
+        file.new_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+        file.new_path = None;
+        file.old_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("old mode ") {
+        file.old_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("new mode ") {
+        file.new_mode = Some(mode.to_string());
+    } else if let Some(percent) = line
+        .strip_prefix("similarity index ")
+        .and_then(|p| p.strip_suffix('%'))
+    {
+        file.similarity = percent.parse().ok();
+    } else if let Some(path) = line.strip_prefix("rename from ") {
+        file.status = FileStatus::Renamed;
+        file.old_path = Some(unquote(path));
+    } else if let Some(path) = line.strip_prefix("rename to ") {
+        file.status = FileStatus::Renamed;
+        file.new_path = Some(unquote(path));
+    } else if let Some(path) = line.strip_prefix("copy from ") {
+        file.status = FileStatus::Copied;
+        file.old_path = Some(unquote(path));
+    } else if let Some(path) = line.strip_prefix("copy to ") {
+        file.status = FileStatus::Copied;
+        file.new_path = Some(unquote(path));
+    } else if let Some(rest) = line.strip_prefix("--- ") {
+        file.old_path = header_path(rest, "a/");
+    } else if let Some(rest) = line.strip_prefix("+++ ") {
+        file.new_path = header_path(rest, "b/");
+    } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+        file.binary = true;
+    }
+}
+
+/// Path of a `---`/`+++` line: `/dev/null` is no file, and `diff -u` puts a
+/// tab and a timestamp after the name (git a tab after names with spaces).
+fn header_path(rest: &str, prefix: &str) -> Option<String> {
+    let path = if rest.starts_with('"') {
+        unquote(rest)
+    } else {
+        rest.split('\t').next().unwrap_or(rest).to_string()
+    };
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).map(String::from).unwrap_or(path))
+}
+
+/// Old and new paths of a `diff --git a/x b/y` line.
+fn git_header_paths(rest: &str) -> Option<(String, String)> {
+    let (old, new) = if rest.starts_with('"') {
+        let end = quoted_end(rest)?;
+        let new = rest[end..].trim_start();
+        let new = if new.starts_with('"') {
+            unquote(new)
+        } else {
+            new.to_string()
+        };
+        (unquote(&rest[..end]), new)
+    } else if let Some(end) = rest.find(" \"") {
+        (rest[..end].to_string(), unquote(&rest[end + 1..]))
+    } else if rest.len() % 2 == 1 && rest.as_bytes()[rest.len() / 2] == b' ' {
+        // Unquoted names may contain spaces; the same name on both sides
+        // splits in the middle.
+        let (old, new) = rest.split_at(rest.len() / 2);
+        (old.to_string(), new[1..].to_string())
+    } else {
+        let (old, new) = rest.split_once(" b/")?;
+        (old.to_string(), format!("b/{new}"))
+    };
+    let strip = |path: String, prefix: &str| path.strip_prefix(prefix).map(String::from);
+    Some((strip(old, "a/")?, strip(new, "b/")?))
+}
+
+/// End (exclusive) of the quoted string at the start of `text`.
+fn quoted_end(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i + 1),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// Decode a C-style quoted path, as git writes names with special
+/// characters (`"tab\tname"`, `"\303\251.txt"`). Unquoted text is returned
+/// as is.
+fn unquote(text: &str) -> String {
+    let Some(inner) = text
+        .strip_prefix('"')
+        .and_then(|t| quoted_end(text).map(|end| &t[..end - 2]))
+    else {
+        return text.to_string();
+    };
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes().peekable();
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match chars.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(b'r') => bytes.push(b'\r'),
+            Some(b'a') => bytes.push(0x07),
+            Some(b'b') => bytes.push(0x08),
+            Some(b'f') => bytes.push(0x0c),
+            Some(b'v') => bytes.push(0x0b),
+            Some(d @ b'0'..=b'7') => {
+                let mut value = u32::from(d - b'0');
+                for _ in 0..2 {
+                    if let Some(&d @ b'0'..=b'7') = chars.peek() {
+                        value = value * 8 + u32::from(d - b'0');
+                        chars.next();
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENAME_BINARY_MODE: &str = include_str!("../fixtures/diff/rename_binary_mode.diff");
+    const NEW_FILES: &str = include_str!("../fixtures/diff/new_files.diff");
+    const BINARY_PATCH: &str = include_str!("../fixtures/diff/binary_patch.diff");
+    const MULTI_HUNK: &str = include_str!("../fixtures/diff/multi_hunk.diff");
+    const PLAIN: &str = include_str!("../fixtures/diff/plain.diff");
+    const COPY: &str = include_str!("../fixtures/diff/copy.diff");
+    const CRLF: &str = include_str!("../fixtures/diff/crlf.diff");
+    const EMPTY_FILES: &str = include_str!("../fixtures/diff/empty_files.diff");
+    const SPACES_AND_QUOTES: &str = include_str!("../fixtures/diff/spaces_and_quotes.diff");
+
+    /// (old line, new line, content) of every hunk line.
+    fn numbered(file: &DiffFile) -> Vec<(Option<u32>, Option<u32>, &str)> {
+        file.lines()
+            .map(|l| (l.old_line, l.new_line, l.content.as_str()))
+            .collect()
+    }
+
+    fn summary(diff: &Diff) -> Vec<String> {
+        diff.files
+            .iter()
+            .map(|f| {
+                format!(
+                    "{:?} {:?} -> {:?} +{} -{}{}",
+                    f.status,
+                    f.old_path.as_deref().unwrap_or("-"),
+                    f.new_path.as_deref().unwrap_or("-"),
+                    f.added(),
+                    f.removed(),
+                    if f.binary { " binary" } else { "" }
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_hunk_header() {
+        let hunk = Hunk::parse_header("@@ -10,4 +10,6 @@ fn main() {").unwrap();
+        assert_eq!(
+            (
+                hunk.old_start,
+                hunk.old_count,
+                hunk.new_start,
+                hunk.new_count
+            ),
+            (10, 4, 10, 6)
+        );
+        assert_eq!(hunk.section, "fn main() {");
+        assert_eq!(hunk.new_range(), Some(10..=15));
+
+        let hunk = Hunk::parse_header("@@ -1 +1 @@").unwrap();
+        assert_eq!(hunk.new_range(), Some(1..=1));
+        assert_eq!(hunk.section, "");
+
+        let hunk = Hunk::parse_header("@@ -3,2 +2,0 @@").unwrap();
+        assert_eq!(hunk.new_range(), None);
+        assert_eq!(hunk.old_range(), Some(3..=4));
+
+        assert!(Hunk::parse_header("@@ -a +1 @@").is_none());
+        assert!(Hunk::parse_header("@@@ -1,2 -1,2 +1,3 @@@").is_none());
+    }
+
+    #[test]
+    fn test_rename_binary_and_mode_changes() {
+        let diff = Diff::parse(RENAME_BINARY_MODE);
+        assert_eq!(
+            summary(&diff),
+            vec![
+                "Modified \"bin.dat\" -> \"bin.dat\" +0 -0 binary",
+                "Renamed \"old.txt\" -> \"new.txt\" +1 -1",
+                "Modified \"nonl.txt\" -> \"nonl.txt\" +1 -1",
+                "Modified \"script.sh\" -> \"script.sh\" +0 -0",
+                "Deleted \"é.txt\" -> \"-\" +0 -1",
+            ]
+        );
+
+        let renamed = diff.file("new.txt").unwrap();
+        assert_eq!(renamed.similarity, Some(85));
+        assert_eq!(renamed.hunks[0].old_start, 7);
+        let changed: Vec<(Option<u32>, Option<u32>, &str)> = renamed
+            .lines()
+            .filter(|l| l.kind != LineKind::Context)
+            .map(|l| (l.old_line, l.new_line, l.content.as_str()))
+            .collect();
+        assert_eq!(
+            changed,
+            vec![(Some(10), None, "10"), (None, Some(10), "11")]
+        );
+
+        let no_newline = diff.file("nonl.txt").unwrap();
+        assert!(no_newline.lines().all(|l| l.no_newline));
+
+        let mode = diff.file("script.sh").unwrap();
+        assert!(mode.is_mode_change());
+        assert_eq!(mode.old_mode.as_deref(), Some("100644"));
+        assert_eq!(mode.new_mode.as_deref(), Some("100755"));
+        assert!(mode.hunks.is_empty());
+
+        assert!(diff.file("é.txt").unwrap().old_mode.is_some());
+        assert_eq!(diff.changed_lines(), 5);
+        assert_eq!(diff.to_string(), RENAME_BINARY_MODE);
+    }
+
+    #[test]
+    fn test_new_files_with_special_names() {
+        let diff = Diff::parse(NEW_FILES);
+        let paths: Vec<&str> = diff.files.iter().map(DiffFile::path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "bin.dat",
+                "nonl.txt",
+                "old.txt",
+                "script.sh",
+                "sp ace.txt",
+                "tab\tname.txt",
+                "é.txt",
+            ]
+        );
+        assert!(
+            diff.files
+                .iter()
+                .all(|f| f.status == FileStatus::Added && f.old_path.is_none())
+        );
+        assert!(diff.files[0].binary);
+        assert_eq!(diff.file("old.txt").unwrap().added_lines().count(), 10);
+        let script = diff.file("script.sh").unwrap();
+        assert_eq!(script.new_mode.as_deref(), Some("100644"));
+        assert_eq!(script.hunks[0].old_range(), None);
+        assert_eq!(diff.to_string(), NEW_FILES);
+    }
+
+    #[test]
+    fn test_binary_patch() {
+        let diff = Diff::parse(BINARY_PATCH);
+        assert_eq!(diff.files.len(), 1);
+        assert!(diff.files[0].binary);
+        assert!(diff.files[0].hunks.is_empty());
+        assert_eq!(diff.to_string(), BINARY_PATCH);
+    }
+
+    #[test]
+    fn test_positions() {
+        let diff = Diff::parse(MULTI_HUNK);
+        let file = diff.file("long.txt").unwrap();
+        assert_eq!(file.hunks.len(), 2);
+        assert_eq!(file.added_lines().collect::<Vec<_>>(), vec![3, 24, 25]);
+
+        // Line 1 is the first line below the first header.
+        assert_eq!(file.position(Side::Right, 1), Some(1));
+        assert_eq!(file.position(Side::Left, 3), Some(3));
+        assert_eq!(file.position(Side::Right, 3), Some(4));
+        assert_eq!(file.position(Side::Left, 5), Some(6));
+        // The second hunk header takes position 9.
+        assert_eq!(file.position(Side::Right, 22), Some(10));
+        assert_eq!(file.position(Side::Left, 25), Some(12));
+        assert_eq!(file.position(Side::Right, 25), Some(14));
+        assert_eq!(file.position(Side::Right, 10), None);
+
+        assert!(file.is_commentable(Side::Right, 6));
+        assert!(!file.is_commentable(Side::Right, 7));
+        assert!(!file.is_commentable(Side::Left, 25 + 10));
+        assert_eq!(file.hunk_at(Side::Right, 27).unwrap().new_start, 22);
+        assert!(file.hunk_at(Side::Right, 28).is_none());
+        assert_eq!(diff.to_string(), MULTI_HUNK);
+    }
+
+    #[test]
+    fn test_plain_unified_diff() {
+        let diff = Diff::parse(PLAIN);
+        assert_eq!(
+            summary(&diff),
+            vec!["Modified \"a.txt\" -> \"b.txt\" +2 -1"]
+        );
+        assert_eq!(diff.to_string(), PLAIN);
+    }
+
+    #[test]
+    fn test_truncated_hunk_and_garbage() {
+        let text = "\
+commit message line
+diff --git a/src/a.rs b/src/a.rs
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -1,5 +1,5 @@
+ one
+-two
++deux
+diff --git a/src/b.rs b/src/b.rs
+--- a/src/b.rs
++++ b/src/b.rs
+@@ -1 +1,2 @@
+ --- not a header
++++ not a header either
+";
+        let diff = Diff::parse(text);
+        assert_eq!(
+            summary(&diff),
+            vec![
+                "Modified \"src/a.rs\" -> \"src/a.rs\" +1 -1",
+                "Modified \"src/b.rs\" -> \"src/b.rs\" +1 -0",
+            ]
+        );
+        let b = diff.file("src/b.rs").unwrap();
+        assert_eq!(b.lines().next().unwrap().content, "--- not a header");
+        assert_eq!(b.added_lines().collect::<Vec<_>>(), vec![2]);
+        assert!(Diff::parse("").is_empty());
+        assert!(Diff::parse("not a diff\n").is_empty());
+    }
+
+    #[test]
+    fn test_copy() {
+        let diff = Diff::parse(COPY);
+        assert_eq!(
+            summary(&diff),
+            vec!["Copied \"base.txt\" -> \"copy.txt\" +1 -1"]
+        );
+        let copy = diff.file("copy.txt").unwrap();
+        assert_eq!(copy.similarity, Some(85));
+        assert_eq!(
+            numbered(copy)[2..5],
+            [
+                (Some(14), Some(14), "14"),
+                (Some(15), None, "15"),
+                (None, Some(15), "fifteen"),
+            ]
+        );
+        assert_eq!(copy.position(Side::Left, 15), Some(4));
+        assert_eq!(copy.position(Side::Right, 15), Some(5));
+        assert_eq!(copy.position(Side::Right, 18), Some(8));
+        assert!(!copy.is_commentable(Side::Right, 11));
+        assert_eq!(diff.to_string(), COPY);
+    }
+
+    #[test]
+    fn test_crlf() {
+        let diff = Diff::parse(CRLF);
+        let file = diff.file("crlf.txt").unwrap();
+        assert_eq!(
+            numbered(file),
+            vec![
+                (Some(1), Some(1), "one\r"),
+                (Some(2), None, "two\r"),
+                (None, Some(2), "TWO\r"),
+                (Some(3), Some(3), "three\r"),
+                (None, Some(4), "four\r"),
+            ]
+        );
+        assert_eq!(file.added_lines().collect::<Vec<_>>(), vec![2, 4]);
+        assert_eq!(file.position(Side::Right, 4), Some(5));
+        assert_eq!(file.position(Side::Left, 2), Some(2));
+        assert_eq!(diff.to_string(), CRLF);
+
+        let saved_with_crlf = CRLF.replace("\r\n", "\n").replace('\n', "\r\n");
+        let diff = Diff::parse(&saved_with_crlf);
+        let file = diff.file("crlf.txt").unwrap();
+        assert_eq!(file.hunks[0].new_range(), Some(1..=4));
+        assert_eq!(file.added_lines().collect::<Vec<_>>(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_empty_files() {
+        let diff = Diff::parse(EMPTY_FILES);
+        assert_eq!(
+            summary(&diff),
+            vec![
+                "Deleted \"empty_gone.txt\" -> \"-\" +0 -0",
+                "Added \"-\" -> \"empty_new.txt\" +0 -0",
+                "Renamed \"empty_gone.txt\" -> \"empty_new.txt\" +0 -0",
+            ]
+        );
+        for file in &diff.files {
+            assert!(file.hunks.is_empty());
+            assert!(!file.binary);
+            assert!(!file.is_commentable(Side::Right, 1));
+            assert_eq!(file.position(Side::Left, 1), None);
+        }
+        assert_eq!(diff.files[0].old_mode.as_deref(), Some("100644"));
+        assert_eq!(diff.files[1].new_mode.as_deref(), Some("100644"));
+        assert_eq!(diff.files[2].similarity, Some(100));
+        assert_eq!(diff.to_string(), EMPTY_FILES);
+    }
+
+    #[test]
+    fn test_spaces_and_quotes() {
+        let diff = Diff::parse(SPACES_AND_QUOTES);
+        assert_eq!(
+            summary(&diff),
+            vec![
+                "Renamed \"old name.txt\" -> \"new name.txt\" +1 -1",
+                "Modified \"say \\\"hi\\\".txt\" -> \"say \\\"hi\\\".txt\" +1 -0",
+            ]
+        );
+
+        let renamed = diff.file("new name.txt").unwrap();
+        assert_eq!(renamed.similarity, Some(77));
+        assert_eq!(
+            numbered(renamed)[3..],
+            [(Some(12), None, "12"), (None, Some(12), "twelve")]
+        );
+        assert_eq!(renamed.position(Side::Right, 12), Some(5));
+        assert_eq!(renamed.position(Side::Left, 12), Some(4));
+
+        let quoted = diff.file("say \"hi\".txt").unwrap();
+        assert_eq!(
+            numbered(quoted),
+            vec![(Some(1), Some(1), "hi"), (None, Some(2), "hello")]
+        );
+        assert_eq!(quoted.position(Side::Right, 2), Some(2));
+        assert_eq!(diff.to_string(), SPACES_AND_QUOTES);
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote("\"tab\\tname.txt\""), "tab\tname.txt");
+        assert_eq!(unquote("\"\\303\\251.txt\""), "é.txt");
+        assert_eq!(unquote("\"say \\\"hi\\\"\""), "say \"hi\"");
+        assert_eq!(unquote("plain name"), "plain name");
+    }
+}
@@ -59,6 +59,145 @@ pub struct ReviewResult {
     pub decision: ReviewDecision,
     pub summary: String,
     pub issues: Vec<ReviewIssue>,
+    /// Overall quality score (0–100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u8>,
+    /// Estimated review effort (1–5).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<u8>,
+    /// Whether the changed logic comes with tests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<TestsVerdict>,
+    /// TODO markers on added lines, filled in from the diff rather than by the model.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub todos: Vec<TodoMarker>,
+    /// How well the PR implements its linked ticket.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket_compliance: Option<TicketCompliance>,
+    /// Earlier review threads that the new changes address (update reviews).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolved_threads: Vec<ResolvedThread>,
+}
+
+impl ReviewResult {
+    /// Build a result from free-form final text (no structured review).
+    pub fn from_summary(summary: impl Into<String>) -> Self {
+        Self {
+            decision: ReviewDecision::Comment,
+            summary: summary.into(),
+            issues: Vec::new(),
+            score: None,
+            effort: None,
+            tests: None,
+            todos: Vec::new(),
+            ticket_compliance: None,
+            resolved_threads: Vec::new(),
+        }
+    }
+
+    /// Parse and validate a result from the agent's final text.
+    ///
+    /// Accepts a bare JSON object, or one wrapped in a code fence or
+    /// surrounded by prose.
+    pub fn parse(text: &str) -> Result<Self, crate::Error> {
+        let json = extract_json_object(text).ok_or_else(|| {
+            crate::Error::InvalidReviewResult("no JSON object found in the response".into())
+        })?;
+        let result: Self = serde_json::from_str(json)
+            .map_err(|e| crate::Error::InvalidReviewResult(e.to_string()))?;
+        result.validate()?;
+        Ok(result)
+    }
+
+    fn validate(&self) -> Result<(), crate::Error> {
+        let invalid = |message: String| Err(crate::Error::InvalidReviewResult(message));
+        if let Some(score) = self.score
+            && score > 100
+        {
+            return invalid(format!("score {score} is not between 0 and 100"));
+        }
+        if let Some(effort) = self.effort
+            && !(1..=5).contains(&effort)
+        {
+            return invalid(format!("effort {effort} is not between 1 and 5"));
+        }
+        for (index, issue) in self.issues.iter().enumerate() {
+            issue
+                .validate()
+                .map_err(|e| crate::Error::InvalidReviewResult(format!("issues[{index}]: {e}")))?;
+        }
+        for (index, thread) in self.resolved_threads.iter().enumerate() {
+            if thread.reply.trim().is_empty() {
+                return invalid(format!("resolved_threads[{index}]: reply is empty"));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn extract_json_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    (start < end).then(|| &text[start..=end])
+}
+
+/// Test coverage verdict for the changed logic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestsVerdict {
+    /// Tests for the changed logic are included.
+    Included,
+    /// Logic changed but no tests cover it.
+    Missing,
+    /// No testable logic changed (docs, config, renames).
+    NotNeeded,
+}
+
+/// Compliance of a PR with its linked ticket.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TicketCompliance {
+    /// Ticket key; `None` when the PR references no ticket.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub requirements: Vec<RequirementCheck>,
+}
+
+/// Assessment of one ticket requirement.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementCheck {
+    pub requirement: String,
+    pub status: ComplianceStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplianceStatus {
+    Met,
+    NotMet,
+    /// Cannot be confirmed from the code (UI, copy, behaviour in production).
+    NeedsVerification,
+}
+
+/// A review thread the new changes address, to be resolved with a reply.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedThread {
+    /// ID of the first comment of the thread.
+    pub comment_id: u64,
+    /// Short reply explaining how the concern was addressed.
+    pub reply: String,
+}
+
+/// A TODO/FIXME marker on an added line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoMarker {
+    pub file: String,
+    pub line: u32,
+    pub text: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -72,9 +211,55 @@ pub enum ReviewDecision {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewIssue {
     pub severity: IssueSeverity,
+    #[serde(default)]
+    pub category: IssueCategory,
     pub file: Option<String>,
+    /// First line of the issue in the new version of the file.
     pub line: Option<u32>,
+    /// Last line of the issue, for multi-line ranges.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
     pub message: String,
+    /// Concrete fix, in prose or code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggested_fix: Option<String>,
+    /// Exact replacement for lines `line..=end_line`, posted as a GitHub
+    /// suggestion once validated against the checkout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
+
+impl ReviewIssue {
+    fn validate(&self) -> Result<(), String> {
+        if self.message.trim().is_empty() {
+            return Err("message is empty".into());
+        }
+        if self.line.is_some() && self.file.is_none() {
+            return Err("line given without file".into());
+        }
+        if self.line == Some(0) {
+            return Err("line numbers start at 1".into());
+        }
+        if self.replacement.is_some() && self.line.is_none() {
+            return Err("replacement given without line".into());
+        }
+        match (self.line, self.end_line) {
+            (None, Some(_)) => Err("end_line given without line".into()),
+            (Some(start), Some(end)) if end < start => {
+                Err(format!("end_line {end} is before line {start}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Outcome of validating suggested changes before they are posted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuggestionStats {
+    /// Suggestions that applied cleanly and passed the repository's check.
+    pub applied: usize,
+    /// Suggestions dropped because they did not apply or failed the check.
+    pub failed: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -85,6 +270,29 @@ pub enum IssueSeverity {
     Info,
 }
 
+impl IssueSeverity {
+    /// Rank for comparisons; higher is more severe.
+    pub fn rank(self) -> u8 {
+        match self {
+            IssueSeverity::Info => 0,
+            IssueSeverity::Warning => 1,
+            IssueSeverity::Error => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueCategory {
+    Bug,
+    Security,
+    Performance,
+    Maintainability,
+    Testing,
+    #[default]
+    Other,
+}
+
 /// Observations from the environment in response to actions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -106,10 +314,10 @@ pub enum Observation {
     CommentPosted { comment_id: String },
 
     /// MR was approved.
-    Approved,
+    Approved { review_id: String },
 
     /// Changes were requested on MR.
-    ChangesRequested,
+    ChangesRequested { review_id: String },
 
     /// An error occurred.
     Error { message: String },
@@ -160,6 +368,12 @@ pub enum EventPayload {
 
     /// A message (user or assistant).
     Message { role: String, content: String },
+
+    /// A tool call executed by the backend itself (e.g. Claude Code's Bash tool).
+    ToolUse {
+        name: String,
+        input: serde_json::Value,
+    },
 }
 
 #[cfg(test)]
@@ -186,12 +400,73 @@ mod tests {
             summary: "Found issues".into(),
             issues: vec![ReviewIssue {
                 severity: IssueSeverity::Error,
+                category: IssueCategory::Bug,
                 file: Some("src/lib.rs".into()),
                 line: Some(42),
+                end_line: None,
                 message: "Unused variable".into(),
+                suggested_fix: None,
+                replacement: None,
             }],
+            score: Some(70),
+            effort: Some(2),
+            tests: Some(TestsVerdict::Missing),
+            todos: Vec::new(),
+            ticket_compliance: None,
+            resolved_threads: Vec::new(),
         };
         let json = serde_json::to_string(&result).unwrap();
         assert!(json.contains("changes_requested"));
+        assert!(json.contains(r#""tests":"missing""#));
+        assert!(!json.contains("todos"));
+    }
+
+    #[test]
+    fn test_review_result_parse_fenced() {
+        let text = r#"Here is my review:
+
+```json
+{
+  "decision": "comment",
+  "summary": "One bug",
+  "issues": [{
+    "severity": "error",
+    "category": "bug",
+    "file": "src/lib.rs",
+    "line": 10,
+    "end_line": 12,
+    "message": "Off by one",
+    "suggested_fix": "Use `..=`"
+  }],
+  "ticket_compliance": {
+    "requirements": [
+      {"requirement": "Reject empty input", "status": "not_met", "note": "No check added"}
+    ]
+  }
+}
+```"#;
+        let result = ReviewResult::parse(text).unwrap();
+        let compliance = result.ticket_compliance.unwrap();
+        assert_eq!(compliance.requirements[0].status, ComplianceStatus::NotMet);
+        let issue = &result.issues[0];
+        assert_eq!(issue.category, IssueCategory::Bug);
+        assert_eq!(issue.end_line, Some(12));
+        assert_eq!(issue.suggested_fix.as_deref(), Some("Use `..=`"));
+    }
+
+    #[test]
+    fn test_review_result_parse_rejects_invalid() {
+        assert!(ReviewResult::parse("Looks good to me!").is_err());
+        assert!(ReviewResult::parse(r#"{"decision":"maybe","summary":"","issues":[]}"#).is_err());
+
+        let score = r#"{"decision":"comment","summary":"s","issues":[],"score":120}"#;
+        assert!(ReviewResult::parse(score).is_err());
+        let effort = r#"{"decision":"comment","summary":"s","issues":[],"effort":0}"#;
+        assert!(ReviewResult::parse(effort).is_err());
+
+        let inverted = r#"{"decision":"comment","summary":"s","issues":[
+            {"severity":"info","file":"a.rs","line":5,"end_line":3,"message":"m"}]}"#;
+        let err = ReviewResult::parse(inverted).unwrap_err();
+        assert!(err.to_string().contains("issues[0]"));
     }
 }
@@ -0,0 +1,211 @@
+//! Stable fingerprints for review issues.
+//!
+//! A fingerprint identifies a finding by its file, category and the code it
+//! points at (whitespace-normalized), so the same concern raised again on a
+//! later push can be recognized even when the model words it differently or
+//! the code moved to another line.
+
+use serde::{Deserialize, Serialize};
+
+use crate::event::{IssueCategory, ReviewIssue};
+
+/// Most lines of code taken into a fingerprint.
+const MAX_SPAN: u32 = 10;
+
+/// Fingerprint of a posted review issue, persisted per PR.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueFingerprint {
+    /// Hash of file, category and normalized code context.
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub category: IssueCategory,
+    /// Line the issue was reported on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Number of code lines hashed; 0 when the fingerprint is based on the
+    /// message because the issue has no code context.
+    #[serde(default)]
+    pub span: u32,
+}
+
+impl IssueFingerprint {
+    /// Fingerprint an issue, given the current content of its file.
+    pub fn of(issue: &ReviewIssue, source: Option<&str>) -> Self {
+        let file = issue.file.clone();
+        if let (Some(line), Some(source)) = (issue.line, source) {
+            let end = issue.end_line.unwrap_or(line).max(line);
+            let span = (end - line + 1).min(MAX_SPAN);
+            let lines: Vec<&str> = source.lines().collect();
+            let start = (line as usize).saturating_sub(1);
+            if let Some(window) = lines.get(start..start + span as usize) {
+                let context = normalize_code(window);
+                if !context.is_empty() {
+                    return Self {
+                        id: hash(file.as_deref(), issue.category, &context),
+                        file,
+                        category: issue.category,
+                        line: Some(line),
+                        span,
+                    };
+                }
+            }
+        }
+
+        let context = format!("message:{}", normalize_words(&issue.message));
+        Self {
+            id: hash(file.as_deref(), issue.category, &context),
+            file,
+            category: issue.category,
+            line: issue.line,
+            span: 0,
+        }
+    }
+
+    /// Whether the flagged code still exists in `source`, the current content
+    /// of the file (`None` when the file was deleted).
+    ///
+    /// The code may have moved; it only counts as changed when no run of
+    /// lines of the same length hashes to this fingerprint any more.
+    pub fn is_present(&self, source: Option<&str>) -> bool {
+        if self.file.is_none() {
+            return true;
+        }
+        let Some(source) = source else {
+            return false;
+        };
+        if self.span == 0 {
+            return true;
+        }
+        let lines: Vec<&str> = source.lines().collect();
+        lines.windows(self.span as usize).any(|window| {
+            let context = normalize_code(window);
+            !context.is_empty() && hash(self.file.as_deref(), self.category, &context) == self.id
+        })
+    }
+}
+
+/// A finding left out of a review because it was already posted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuppressedIssue {
+    /// ID of the open fingerprint it matched.
+    pub fingerprint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+}
+
+/// Fingerprint decisions of one review.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FingerprintReport {
+    /// Fingerprints of the issues posted by this review.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub opened: Vec<IssueFingerprint>,
+    /// Issues not posted because they match an open fingerprint.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suppressed: Vec<SuppressedIssue>,
+    /// Number of suppressed issues, including any dropped from `suppressed`
+    /// to keep the report small.
+    #[serde(default)]
+    pub suppressed_count: usize,
+    /// IDs of previously flagged fingerprints whose code changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<String>,
+}
+
+impl FingerprintReport {
+    pub fn is_empty(&self) -> bool {
+        self.opened.is_empty() && self.suppressed_count == 0 && self.changed.is_empty()
+    }
+}
+
+/// Trim every line, collapse inner whitespace and drop blank lines.
+fn normalize_code(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn normalize_words(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across Rust releases.
+fn hash(file: Option<&str>, category: IssueCategory, context: &str) -> String {
+    let category = serde_json::to_string(&category).unwrap_or_default();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in [file.unwrap_or(""), &category, context] {
+        for byte in part.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::IssueSeverity;
+
+    fn issue(line: Option<u32>, message: &str) -> ReviewIssue {
+        ReviewIssue {
+            severity: IssueSeverity::Warning,
+            category: IssueCategory::Bug,
+            file: Some("src/lib.rs".into()),
+            line,
+            end_line: None,
+            message: message.into(),
+            suggested_fix: None,
+            replacement: None,
+        }
+    }
+
+    const SOURCE: &str = "fn main() {\n    let x = parse(input).unwrap();\n}\n";
+
+    #[test]
+    fn test_fingerprint_ignores_wording_and_whitespace() {
+        let first = IssueFingerprint::of(&issue(Some(2), "unwrap may panic"), Some(SOURCE));
+        let reworded = IssueFingerprint::of(
+            &issue(Some(3), "Calling unwrap() here can panic"),
+            Some("// moved\nfn main() {\n  let x  =  parse(input).unwrap();\n}\n"),
+        );
+
+        assert_eq!(first.id, reworded.id);
+        assert_eq!(first.span, 1);
+        assert_eq!(first.id.len(), 16);
+
+        let mut security = issue(Some(2), "unwrap may panic");
+        security.category = IssueCategory::Security;
+        assert_ne!(IssueFingerprint::of(&security, Some(SOURCE)).id, first.id);
+    }
+
+    #[test]
+    fn test_fingerprint_without_code_uses_message() {
+        let fingerprint = IssueFingerprint::of(&issue(None, "Missing tests!"), None);
+        assert_eq!(fingerprint.span, 0);
+        assert_eq!(
+            fingerprint.id,
+            IssueFingerprint::of(&issue(None, "missing tests"), Some(SOURCE)).id
+        );
+        assert!(fingerprint.is_present(Some("")));
+        assert!(!fingerprint.is_present(None));
+    }
+
+    #[test]
+    fn test_is_present_detects_changed_region() {
+        let fingerprint = IssueFingerprint::of(&issue(Some(2), "unwrap"), Some(SOURCE));
+
+        assert!(fingerprint.is_present(Some(SOURCE)));
+        assert!(fingerprint.is_present(Some(&format!("use std::io;\n\n{SOURCE}"))));
+        assert!(!fingerprint.is_present(Some("fn main() {\n    let x = parse(input)?;\n}\n")));
+    }
+}
@@ -1,14 +1,22 @@
 //! Core agent loop and types for Claude Code agentic system.
 
 pub mod controller;
+pub mod diff;
 pub mod event;
+pub mod fingerprint;
 pub mod state;
 pub mod stream;
 
 pub use controller::{
     ActionExecutor, AgentController, ClaudeBackend, ClaudeResponse, Message, MessageRole,
 };
-pub use event::{Action, Event, EventId, EventPayload, Observation, ReviewDecision, ReviewResult};
+pub use diff::{Diff, DiffFile, DiffLine, FileStatus, Hunk, LineKind, Side};
+pub use event::{
+    Action, ComplianceStatus, Event, EventId, EventPayload, IssueCategory, IssueSeverity,
+    Observation, RequirementCheck, ResolvedThread, ReviewDecision, ReviewIssue, ReviewResult,
+    SuggestionStats, TestsVerdict, TicketCompliance, TodoMarker,
+};
+pub use fingerprint::{FingerprintReport, IssueFingerprint, SuppressedIssue};
 pub use state::{AgentState, Metrics, ReviewContext, State};
 pub use stream::EventStream;
 
@@ -30,6 +38,12 @@ pub enum Error {
     #[error("Agent finished without result")]
     NoResult,
 
+    #[error("Invalid review result: {0}")]
+    InvalidReviewResult(String),
+
+    #[error("Agent session failed: {0}")]
+    SessionFailed(String),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
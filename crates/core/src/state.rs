@@ -70,6 +70,9 @@ pub struct Metrics {
     pub tool_calls: u32,
     /// Number of errors encountered.
     pub errors: u32,
+    /// Total cost reported by Claude Code, in USD.
+    #[serde(default)]
+    pub cost_usd: f64,
 }
 
 impl Metrics {
@@ -81,6 +84,23 @@ impl Metrics {
         self.finished_at = Some(Utc::now());
     }
 
+    /// Add the usage of another session, e.g. one part of a chunked review.
+    pub fn merge(&mut self, other: &Metrics) {
+        self.started_at = match (self.started_at, other.started_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.finished_at = match (self.finished_at, other.finished_at) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.api_calls += other.api_calls;
+        self.total_tokens += other.total_tokens;
+        self.tool_calls += other.tool_calls;
+        self.errors += other.errors;
+        self.cost_usd += other.cost_usd;
+    }
+
     pub fn duration_secs(&self) -> Option<f64> {
         match (self.started_at, self.finished_at) {
             (Some(start), Some(end)) => Some((end - start).num_milliseconds() as f64 / 1000.0),
@@ -176,6 +196,10 @@ impl State {
     pub fn record_tool_call(&mut self) {
         self.metrics.tool_calls += 1;
     }
+
+    pub fn record_cost(&mut self, cost_usd: f64) {
+        self.metrics.cost_usd += cost_usd;
+    }
 }
 
 #[cfg(test)]
@@ -213,4 +237,22 @@ mod tests {
         let duration = metrics.duration_secs().unwrap();
         assert!(duration >= 0.01);
     }
+
+    #[test]
+    fn test_metrics_merge() {
+        let mut first = Metrics::default();
+        first.start();
+        first.finish();
+        first.api_calls = 2;
+        first.cost_usd = 0.5;
+
+        let mut total = Metrics::default();
+        total.merge(&first);
+        total.merge(&first);
+
+        assert_eq!(total.api_calls, 4);
+        assert_eq!(total.cost_usd, 1.0);
+        assert_eq!(total.started_at, first.started_at);
+        assert_eq!(total.finished_at, first.finished_at);
+    }
 }
@@ -0,0 +1,302 @@
+//! Logs of failing CI checks, for lint-fix jobs.
+//!
+//! GitHub Actions job logs are downloaded and trimmed to the failing step;
+//! other check runs only have their output text. The check's annotations,
+//! which carry file and line, are appended in the `::error` workflow
+//! command format the worker parses.
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::github::CheckRunEvent;
+use crate::payload::FailedCheck;
+
+const GITHUB_API: &str = "https://api.github.com";
+
+/// Subject prefix of the commits pushed by lint-fix jobs (see the lint-fix
+/// system prompt), used to avoid fixing our own fix in a loop.
+pub const LINT_FIX_COMMIT_PREFIX: &str = "fix: resolve linter errors";
+
+/// Maximum lines of the failing step kept in the log.
+const MAX_LOG_LINES: usize = 200;
+/// Maximum characters of log sent to the worker.
+const MAX_LOG_CHARS: usize = 16_000;
+/// Lines at the start of the failing step (its command) kept when the step's
+/// output is cut.
+const HEADER_LINES: usize = 10;
+/// Maximum number of annotations appended to the log.
+const MAX_ANNOTATIONS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+struct Annotation {
+    path: String,
+    start_line: Option<u32>,
+    annotation_level: String,
+    message: String,
+}
+
+fn client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent("claude-agent")
+        .build()
+}
+
+/// Fetch the log of a failed check run.
+pub async fn fetch_failed_check(event: &CheckRunEvent, token: &str) -> anyhow::Result<FailedCheck> {
+    let client = client()?;
+    let repo = &event.repository.full_name;
+    let run = &event.check_run;
+
+    let mut log = if event.is_actions() {
+        let raw = client
+            .get(format!(
+                "{GITHUB_API}/repos/{repo}/actions/jobs/{}/logs",
+                run.id
+            ))
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await?
+            .error_for_status()
+            .context("Failed to download job log")?
+            .text()
+            .await?;
+        trim_log(&raw)
+    } else {
+        [&run.output.title, &run.output.summary, &run.output.text]
+            .into_iter()
+            .flatten()
+            .map(|text| strip_ansi(text))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+
+    let annotations: Vec<Annotation> = client
+        .get(format!(
+            "{GITHUB_API}/repos/{repo}/check-runs/{}/annotations",
+            run.id
+        ))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()
+        .context("Failed to fetch check annotations")?
+        .json()
+        .await?;
+    let annotations = format_annotations(&annotations);
+    if !annotations.is_empty() {
+        log.push_str("\n\n");
+        log.push_str(&annotations);
+    }
+
+    let log = cap_chars(&log);
+    info!(
+        repo = %repo,
+        check = %run.name,
+        log_len = log.len(),
+        "Fetched failed check log"
+    );
+    Ok(FailedCheck {
+        name: run.name.clone(),
+        url: run.html_url.clone(),
+        log,
+    })
+}
+
+/// Whether a commit was pushed by a lint-fix job.
+pub async fn is_lint_fix_commit(repo: &str, sha: &str, token: &str) -> anyhow::Result<bool> {
+    let commit: serde_json::Value = client()?
+        .get(format!("{GITHUB_API}/repos/{repo}/commits/{sha}"))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let message = commit["commit"]["message"].as_str().unwrap_or("");
+    Ok(message.starts_with(LINT_FIX_COMMIT_PREFIX))
+}
+
+/// Trim an Actions job log to the failing step.
+///
+/// Timestamps and colour codes are removed. The excerpt starts at the
+/// `##[group]Run` line of the step with the first `##[error]` and ends at
+/// that step's last error; long output keeps the step's command and the
+/// lines before the error. Logs without error markers keep their tail.
+pub fn trim_log(log: &str) -> String {
+    let lines: Vec<String> = log
+        .lines()
+        .map(|line| strip_ansi(strip_timestamp(line)))
+        .collect();
+    let Some(first_error) = lines.iter().position(|l| l.starts_with("##[error]")) else {
+        let start = lines.len().saturating_sub(MAX_LOG_LINES);
+        return lines[start..].join("\n");
+    };
+
+    let is_step = |line: &String| line.starts_with("##[group]Run ");
+    let start = lines[..first_error].iter().rposition(is_step).unwrap_or(0);
+    let step_end = lines[first_error..]
+        .iter()
+        .position(is_step)
+        .map_or(lines.len(), |offset| first_error + offset);
+    let end = lines[..step_end]
+        .iter()
+        .rposition(|l| l.starts_with("##[error]"))
+        .unwrap_or(first_error)
+        + 1;
+
+    let step = &lines[start..end];
+    if step.len() <= MAX_LOG_LINES {
+        return step.join("\n");
+    }
+    let header = step.len().min(HEADER_LINES);
+    let tail = MAX_LOG_LINES - header;
+    let omitted = step.len() - header - tail;
+    let mut out = step[..header].to_vec();
+    out.push(format!("… {omitted} lines omitted …"));
+    out.extend_from_slice(&step[step.len() - tail..]);
+    out.join("\n")
+}
+
+/// Drop the ISO timestamp GitHub prefixes to every log line.
+fn strip_timestamp(line: &str) -> &str {
+    let line = line.trim_start_matches('\u{feff}');
+    match line.split_once(' ') {
+        Some((stamp, rest))
+            if stamp.len() >= 20
+                && stamp.ends_with('Z')
+                && stamp.as_bytes().get(10) == Some(&b'T') =>
+        {
+            rest
+        }
+        _ => line,
+    }
+}
+
+/// Remove ANSI escape sequences (colours) from a log line.
+fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            if chars.next() == Some('[') {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Annotations on source files, as `::error file=…,line=…::message` lines.
+fn format_annotations(annotations: &[Annotation]) -> String {
+    annotations
+        .iter()
+        // Actions adds "Process completed with exit code 1" on `.github`.
+        .filter(|a| !a.path.is_empty() && a.path != ".github")
+        .take(MAX_ANNOTATIONS)
+        .map(|a| {
+            let level = match a.annotation_level.as_str() {
+                "failure" => "error",
+                "warning" => "warning",
+                _ => "notice",
+            };
+            let line = a
+                .start_line
+                .map(|line| format!(",line={line}"))
+                .unwrap_or_default();
+            let message = a.message.lines().next().unwrap_or("");
+            format!("::{level} file={}{line}::{message}", a.path)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Keep the end of a log longer than [`MAX_LOG_CHARS`].
+fn cap_chars(log: &str) -> String {
+    if log.len() <= MAX_LOG_CHARS {
+        return log.to_string();
+    }
+    let mut start = log.len() - MAX_LOG_CHARS;
+    while !log.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("…\n{}", &log[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_log() {
+        let log = "\
+2024-05-01T10:00:00.0000000Z ##[group]Run actions/checkout@v4
+2024-05-01T10:00:01.0000000Z Checked out
+2024-05-01T10:00:02.0000000Z ##[endgroup]
+2024-05-01T10:00:03.0000000Z ##[group]Run vendor/bin/phpstan analyse
+2024-05-01T10:00:03.1000000Z vendor/bin/phpstan analyse
+2024-05-01T10:00:03.2000000Z ##[endgroup]
+2024-05-01T10:00:04.0000000Z \u{1b}[31msrc/User.php:12: Undefined variable $name\u{1b}[0m
+2024-05-01T10:00:05.0000000Z ##[error]Process completed with exit code 1.
+2024-05-01T10:00:06.0000000Z ##[group]Run actions/cache/save@v4
+2024-05-01T10:00:07.0000000Z Post job cleanup.";
+        assert_eq!(
+            trim_log(log),
+            "##[group]Run vendor/bin/phpstan analyse\n\
+             vendor/bin/phpstan analyse\n\
+             ##[endgroup]\n\
+             src/User.php:12: Undefined variable $name\n\
+             ##[error]Process completed with exit code 1."
+        );
+    }
+
+    #[test]
+    fn test_trim_long_step() {
+        let mut log = String::from("##[group]Run cargo clippy -- -D warnings\n##[endgroup]\n");
+        for i in 0..500 {
+            log.push_str(&format!("line {i}\n"));
+        }
+        log.push_str("##[error]Process completed with exit code 101.\n");
+
+        let trimmed = trim_log(&log);
+        let lines: Vec<&str> = trimmed.lines().collect();
+        assert_eq!(lines.len(), MAX_LOG_LINES + 1);
+        assert_eq!(lines[0], "##[group]Run cargo clippy -- -D warnings");
+        assert!(trimmed.contains("lines omitted"));
+        assert!(trimmed.ends_with("line 499\n##[error]Process completed with exit code 101."));
+    }
+
+    #[test]
+    fn test_trim_log_without_errors() {
+        let log: String = (0..300).map(|i| format!("line {i}\n")).collect();
+        let trimmed = trim_log(&log);
+        assert_eq!(trimmed.lines().count(), MAX_LOG_LINES);
+        assert!(trimmed.ends_with("line 299"));
+    }
+
+    #[test]
+    fn test_format_annotations() {
+        let annotations: Vec<Annotation> = serde_json::from_value(serde_json::json!([
+            { "path": ".github", "start_line": 1, "annotation_level": "failure",
+              "message": "Process completed with exit code 1." },
+            { "path": "src/app.js", "start_line": 7, "annotation_level": "failure",
+              "message": "'x' is not defined.\nno-undef" },
+            { "path": "src/app.js", "start_line": null, "annotation_level": "warning",
+              "message": "File ignored" }
+        ]))
+        .unwrap();
+        assert_eq!(
+            format_annotations(&annotations),
+            "::error file=src/app.js,line=7::'x' is not defined.\n::warning file=src/app.js::File ignored"
+        );
+    }
+}
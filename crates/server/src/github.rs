@@ -32,6 +32,9 @@ pub struct PullRequest {
     pub head: GitRef,
     pub base: GitRef,
     pub html_url: String,
+    /// Set on `closed` events when the PR was merged.
+    #[serde(default)]
+    pub merged: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -80,6 +83,11 @@ impl PullRequestEvent {
         true
     }
 
+    /// Check if this event is a PR being merged.
+    pub fn is_merged(&self) -> bool {
+        self.action == "closed" && self.pull_request.merged
+    }
+
     /// Map GitHub action to our internal action name.
     fn review_action(&self) -> &str {
         match self.action.as_str() {
@@ -105,10 +113,80 @@ impl From<&PullRequestEvent> for ReviewPayload {
             action: event.review_action().to_string(),
             platform: "github".into(),
             trigger_comment: None,
+            ticket: None,
+            known_issues: Vec::new(),
+            last_reviewed_sha: None,
+            failed_check: None,
         }
     }
 }
 
+/// GitHub check_run webhook event.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckRunEvent {
+    pub action: String,
+    pub check_run: CheckRun,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckRun {
+    pub id: u64,
+    pub name: String,
+    pub head_sha: String,
+    pub conclusion: Option<String>,
+    pub html_url: Option<String>,
+    pub app: Option<CheckApp>,
+    #[serde(default)]
+    pub output: CheckOutput,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckPullRequest>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckApp {
+    pub slug: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CheckOutput {
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CheckPullRequest {
+    pub number: u64,
+}
+
+impl CheckRunEvent {
+    /// PR to fix when this is a failed run of one of the `lint_checks`
+    /// (matched by name, case-insensitively).
+    pub fn lint_fix_pr(&self, lint_checks: &[String]) -> Option<u64> {
+        let run = &self.check_run;
+        if self.action != "completed" || run.conclusion.as_deref() != Some("failure") {
+            return None;
+        }
+        if !lint_checks
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(&run.name))
+        {
+            return None;
+        }
+        run.pull_requests.first().map(|pr| pr.number)
+    }
+
+    /// Whether the check ran on GitHub Actions, whose job logs can be
+    /// downloaded.
+    pub fn is_actions(&self) -> bool {
+        self.check_run
+            .app
+            .as_ref()
+            .is_some_and(|app| app.slug == "github-actions")
+    }
+}
+
 /// Check if a branch exists in a GitHub repository.
 pub async fn branch_exists(repo: &str, branch: &str, token: &str) -> Result<bool, anyhow::Error> {
     let client = reqwest::Client::builder()
@@ -126,6 +204,35 @@ pub async fn branch_exists(repo: &str, branch: &str, token: &str) -> Result<bool
     Ok(resp.status().is_success())
 }
 
+/// Find the most recent PR opened from a branch of a GitHub repository.
+pub async fn find_pull_request(
+    repo: &str,
+    branch: &str,
+    token: &str,
+) -> Result<Option<serde_json::Value>, anyhow::Error> {
+    let client = reqwest::Client::builder()
+        .user_agent("claude-agent")
+        .build()?;
+
+    let owner = repo.split('/').next().unwrap_or(repo);
+    let url = format!("https://api.github.com/repos/{repo}/pulls");
+    let pulls: Vec<serde_json::Value> = client
+        .get(&url)
+        .query(&[
+            ("head", format!("{owner}:{branch}").as_str()),
+            ("state", "all"),
+        ])
+        .header("Authorization", format!("Bearer {token}"))
+        .header("Accept", "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(pulls.into_iter().next())
+}
+
 /// Verify GitHub HMAC-SHA256 webhook signature.
 pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
     let sig_hex = match signature.strip_prefix("sha256=") {
@@ -174,6 +281,7 @@ mod tests {
                 repo: None,
             },
             html_url: "https://github.com/owner/repo/pull/42".into(),
+            merged: false,
         }
     }
 
@@ -226,6 +334,16 @@ mod tests {
         assert!(!make_event("opened", true).should_review());
     }
 
+    #[test]
+    fn test_is_merged() {
+        let mut event = make_event("closed", false);
+        assert!(!event.is_merged());
+        event.pull_request.merged = true;
+        assert!(event.is_merged());
+        event.action = "edited".into();
+        assert!(!event.is_merged());
+    }
+
     #[test]
     fn test_payload_from_event() {
         let event = make_event("opened", false);
@@ -245,6 +363,45 @@ mod tests {
         assert_eq!(payload.action, "update");
     }
 
+    fn make_check_run_event(name: &str, conclusion: &str) -> CheckRunEvent {
+        serde_json::from_value(serde_json::json!({
+            "action": "completed",
+            "check_run": {
+                "id": 99,
+                "name": name,
+                "head_sha": "abc123",
+                "conclusion": conclusion,
+                "app": { "slug": "github-actions" },
+                "pull_requests": [{ "number": 42 }]
+            },
+            "repository": {
+                "id": 1,
+                "name": "repo",
+                "full_name": "owner/repo",
+                "clone_url": "https://github.com/owner/repo.git",
+                "html_url": "https://github.com/owner/repo"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lint_fix_pr() {
+        let checks = vec!["Lint".to_string()];
+        let event = make_check_run_event("lint", "failure");
+        assert_eq!(event.lint_fix_pr(&checks), Some(42));
+        assert!(event.is_actions());
+        assert_eq!(
+            make_check_run_event("lint", "success").lint_fix_pr(&checks),
+            None
+        );
+        assert_eq!(
+            make_check_run_event("tests", "failure").lint_fix_pr(&checks),
+            None
+        );
+        assert_eq!(event.lint_fix_pr(&[]), None);
+    }
+
     #[test]
     fn test_verify_signature_valid() {
         let secret = "test-secret";
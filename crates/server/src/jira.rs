@@ -2,6 +2,8 @@
 //!
 //! Handles Jira Cloud webhooks for comment events where @claude-agent is mentioned.
 
+use std::time::Duration;
+
 use hmac::{Hmac, Mac};
 use serde::Deserialize;
 use sha2::Sha256;
@@ -9,11 +11,19 @@ use sha2::Sha256;
 /// Bot mention trigger - looks for this text in comments.
 pub const BOT_MENTION: &str = "@claude-agent";
 
+/// Jira site used when no other URL is known.
+pub const DEFAULT_JIRA_URL: &str = "https://globalcomix.atlassian.net";
+
 /// Jira Cloud account ID for the claude-agent bot user.
 /// When mentioned via @, Jira stores the account ID in ADF mention nodes
 /// instead of the display name.
 pub const BOT_ACCOUNT_ID: &str = "712020:8218f147-a7bd-4843-b5d3-0b2b01212bb2";
 
+/// Jira is queried inline from webhook handlers, so a slow instance must not
+/// hold up the webhook response.
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+const REQUEST_TIMEOUT_SECS: u64 = 15;
+
 /// Jira webhook event from Jira Cloud.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -133,10 +143,7 @@ impl JiraWebhookEvent {
         if let Some(base) = self.jira_base_url() {
             format!("{}/browse/{}", base, self.issue.key)
         } else {
-            format!(
-                "https://globalcomix.atlassian.net/browse/{}",
-                self.issue.key
-            )
+            format!("{}/browse/{}", DEFAULT_JIRA_URL, self.issue.key)
         }
     }
 }
@@ -235,6 +242,61 @@ pub struct JiraProjectMapping {
     pub vcs_project: String,
     /// Target branch for fixes (e.g., "master")
     pub target_branch: String,
+    /// Jira site URL (defaults to DEFAULT_JIRA_URL)
+    #[serde(default = "default_jira_url")]
+    pub jira_url: String,
+}
+
+fn default_jira_url() -> String {
+    DEFAULT_JIRA_URL.into()
+}
+
+/// Find Jira issue keys (e.g. "GC-123") in free text such as a PR title or
+/// branch name, limited to the given project keys. Matching is
+/// case-insensitive so `feature/gc-123-login` yields `GC-123`.
+pub fn find_issue_keys(text: &str, projects: &[&str]) -> Vec<String> {
+    let mut keys = Vec::new();
+    let words = text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'));
+    for word in words {
+        let parts: Vec<&str> = word.split('-').collect();
+        for pair in parts.windows(2) {
+            let (project, number) = (pair[0], pair[1]);
+            let is_number = !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+            if !is_number || !projects.iter().any(|p| p.eq_ignore_ascii_case(project)) {
+                continue;
+            }
+            let key = format!("{}-{}", project.to_ascii_uppercase(), number);
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    keys
+}
+
+/// Fetch an issue from the Jira REST API.
+pub async fn fetch_issue(
+    base_url: &str,
+    issue_key: &str,
+    token: &str,
+) -> Result<serde_json::Value, reqwest::Error> {
+    let url = format!(
+        "{}/rest/api/3/issue/{}",
+        base_url.trim_end_matches('/'),
+        issue_key
+    );
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()?
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Accept", "application/json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
 }
 
 /// Parse Jira project mappings from JSON string.
@@ -486,4 +548,30 @@ mod tests {
         assert_eq!(mappings[0].jira_project, "GC");
         assert_eq!(mappings[0].vcs_platform, "github");
     }
+
+    #[test]
+    fn test_find_issue_keys() {
+        let projects = ["GC", "WEB"];
+        assert_eq!(
+            find_issue_keys("GC-123: Fix login", &projects),
+            vec!["GC-123"]
+        );
+        assert_eq!(
+            find_issue_keys("feature/web-42-new-header", &projects),
+            vec!["WEB-42"]
+        );
+        assert_eq!(
+            find_issue_keys("Fixes GC-1 and GC-2, see GC-1", &projects),
+            vec!["GC-1", "GC-2"]
+        );
+        assert!(find_issue_keys("Bump utf-8 handling for OPS-7", &projects).is_empty());
+        assert!(find_issue_keys("GC-abc", &projects).is_empty());
+    }
+
+    #[test]
+    fn test_mapping_default_jira_url() {
+        let json = r#"[{"jira_project":"GC","clone_url":"u","vcs_platform":"github","vcs_project":"o/r","target_branch":"master"}]"#;
+        let mappings = parse_project_mappings(json).unwrap();
+        assert_eq!(mappings[0].jira_url, DEFAULT_JIRA_URL);
+    }
 }
@@ -1,19 +1,29 @@
 //! Server components for Claude Agent.
 
+pub mod checks;
 pub mod github;
 pub mod jira;
 pub mod jira_token;
 pub mod payload;
 pub mod queue;
+pub mod report;
 pub mod scheduler;
 pub mod sentry;
 pub mod sentry_api;
+pub mod sentry_events;
+pub mod sentry_followup;
+pub mod sentry_impact;
+pub mod sentry_paths;
+pub mod sentry_suspects;
 pub mod webhook;
 
 pub use jira::{JiraProjectMapping, JiraWebhookEvent};
 pub use jira_token::JiraTokenManager;
-pub use payload::{JiraTicketPayload, JobPayload, ReviewPayload, SentryFixPayload};
+pub use payload::{
+    FailedCheck, JiraTicketPayload, JobPayload, LinkedTicket, ReviewPayload, SentryFixPayload,
+};
 pub use queue::{FailedItem, Queue, QueueItem};
+pub use report::{JobReport, JobStatus};
 pub use scheduler::Scheduler;
 pub use sentry::{SentryProjectMapping, SentryWebhookEvent};
 pub use sentry_api::SentryClient;
@@ -5,6 +5,7 @@
 use std::env;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use tokio::net::TcpListener;
@@ -13,22 +14,14 @@ use tower_http::trace::TraceLayer;
 use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
-mod github;
-mod jira;
-mod jira_token;
-mod payload;
-mod queue;
-mod scheduler;
-mod sentry;
-mod sentry_api;
-mod webhook;
-
-use jira::JiraProjectMapping;
-use jira_token::JiraTokenManager;
-use queue::Queue;
-use scheduler::Scheduler;
-use sentry::SentryProjectMapping as SentryMapping;
-use webhook::{AppState, router};
+use claude_agent_server::sentry::SentryProjectMapping as SentryMapping;
+use claude_agent_server::webhook::reevaluate_parked_issues;
+use claude_agent_server::{
+    AppState, JiraProjectMapping, JiraTokenManager, Queue, Scheduler, jira, router, sentry,
+};
+
+/// How often Sentry issues parked below the impact thresholds are checked.
+const PARKED_SENTRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -42,6 +35,11 @@ async fn main() -> Result<()> {
     let jira_token_manager = state.jira_token_manager.clone();
     let listen_addr = env::var("LISTEN_ADDR").unwrap_or_else(|_| "0.0.0.0:8443".into());
 
+    tokio::spawn(reevaluate_parked_issues(
+        Arc::new(state.clone()),
+        PARKED_SENTRY_INTERVAL,
+    ));
+
     let app = router(state).layer(TraceLayer::new_for_http());
 
     let scheduler = Arc::new(
@@ -110,6 +108,7 @@ async fn build_app_state() -> Result<AppState> {
         jira_project_mappings: parse_jira_mappings(),
         allowed_authors,
         ignored_repos: parse_ignored_repos(),
+        lint_fix_checks: parse_lint_fix_checks(),
     })
 }
 
@@ -164,6 +163,16 @@ fn parse_ignored_repos() -> Vec<String> {
         .collect()
 }
 
+/// Parse comma-separated LINT_FIX_CHECKS env var.
+fn parse_lint_fix_checks() -> Vec<String> {
+    env::var("LINT_FIX_CHECKS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
 async fn shutdown_signal(scheduler: Arc<Scheduler>) {
     let ctrl_c = async {
         signal::ctrl_c()
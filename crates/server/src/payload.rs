@@ -2,6 +2,10 @@
 
 use serde::{Deserialize, Deserializer, Serialize};
 
+use claude_agent_core::IssueFingerprint;
+
+use crate::sentry_paths::PathRewrite;
+
 /// Payload for MR/PR review jobs (GitHub only).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewPayload {
@@ -13,7 +17,8 @@ pub struct ReviewPayload {
     pub title: String,
     pub description: Option<String>,
     pub author: String,
-    /// Webhook action: "open", "reopen", "update", "comment", "lint_fix", etc.
+    /// Webhook action: "open", "reopen", "update", "comment", "lint_fix",
+    /// "describe", etc.
     #[serde(default = "default_action")]
     pub action: String,
     /// Platform: "github"
@@ -22,6 +27,47 @@ pub struct ReviewPayload {
     /// Comment that triggered the job (for action == "comment").
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub trigger_comment: Option<String>,
+    /// Jira ticket referenced in the title, branch or description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticket: Option<LinkedTicket>,
+    /// Fingerprints of issues already posted on the PR, attached by the
+    /// scheduler when the job is spawned.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub known_issues: Vec<IssueFingerprint>,
+    /// Head commit of the PR at its last review, attached by the scheduler
+    /// so update reviews only look at newer changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reviewed_sha: Option<String>,
+    /// Failing CI check that triggered a lint-fix job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_check: Option<FailedCheck>,
+}
+
+/// Failing check run of a PR, for lint-fix jobs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedCheck {
+    /// Check run name (the workflow job name for GitHub Actions).
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Log of the check, trimmed to the failing step.
+    pub log: String,
+}
+
+/// Jira ticket linked to a PR.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedTicket {
+    pub key: String,
+    pub web_url: String,
+    /// Ticket details; `None` when the ticket could not be fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
 }
 
 fn default_action() -> String {
@@ -157,6 +203,13 @@ pub struct SentryFixPayload {
     pub vcs_platform: String,
     /// VCS project path (e.g., "Globalcomix/gc")
     pub vcs_project: String,
+    /// Rules mapping stacktrace paths to repository paths
+    #[serde(default)]
+    pub path_rewrites: Vec<PathRewrite>,
+    /// Sentry integration installation that sent the webhook, used to link
+    /// the fix PR to the issue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentry_installation: Option<String>,
 }
 
 /// Payload for Jira ticket fix jobs.
@@ -233,6 +286,10 @@ mod tests {
             action: "open".into(),
             platform: "github".into(),
             trigger_comment: None,
+            ticket: None,
+            known_issues: Vec::new(),
+            last_reviewed_sha: None,
+            failed_check: None,
         });
 
         let json = serde_json::to_string(&payload).unwrap();
@@ -259,6 +316,8 @@ mod tests {
             target_branch: "master".into(),
             vcs_platform: "github".into(),
             vcs_project: "Globalcomix/gc".into(),
+            path_rewrites: Vec::new(),
+            sentry_installation: None,
         });
 
         let json = serde_json::to_string(&payload).unwrap();
@@ -305,6 +364,10 @@ mod tests {
             action: String::new(),
             platform: String::new(),
             trigger_comment: None,
+            ticket: None,
+            known_issues: Vec::new(),
+            last_reviewed_sha: None,
+            failed_check: None,
         });
         assert_eq!(review.description(), "review group/repo!42");
 
@@ -323,6 +386,8 @@ mod tests {
             target_branch: String::new(),
             vcs_platform: String::new(),
             vcs_project: String::new(),
+            path_rewrites: Vec::new(),
+            sentry_installation: None,
         });
         assert_eq!(sentry.description(), "sentry-fix WEB-123");
     }
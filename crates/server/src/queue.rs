@@ -2,13 +2,31 @@
 
 use redis::AsyncCommands;
 use redis::aio::ConnectionManager;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+use claude_agent_core::IssueFingerprint;
 
 use crate::payload::JobPayload;
+use crate::report::JobReport;
+use crate::sentry::Issue;
 
 const QUEUE_KEY: &str = "claude-agent:review-queue";
 const PROCESSING_KEY: &str = "claude-agent:processing";
 const FAILED_KEY: &str = "claude-agent:failed";
+const REPORTS_KEY_PREFIX: &str = "claude-agent:reports";
+const FINGERPRINTS_KEY_PREFIX: &str = "claude-agent:fingerprints";
+const REVIEWED_HEAD_KEY_PREFIX: &str = "claude-agent:reviewed-head";
+const PARKED_SENTRY_KEY_PREFIX: &str = "claude-agent:parked-sentry";
+
+/// How long the issue fingerprints and reviewed head of an inactive PR are
+/// kept.
+const PR_STATE_TTL_SECS: i64 = 90 * 24 * 60 * 60;
+
+/// How long the report of a job is kept.
+const REPORT_TTL_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How long a Sentry issue below the impact thresholds stays parked.
+const PARKED_SENTRY_TTL_SECS: u64 = 30 * 24 * 60 * 60;
 
 /// Queue item with metadata.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -177,6 +195,204 @@ impl Queue {
 
         Ok(false)
     }
+
+    /// Store the worker report for a job, kept for `REPORT_TTL_SECS`.
+    pub async fn store_report(
+        &self,
+        id: &str,
+        report: &JobReport,
+    ) -> Result<(), redis::RedisError> {
+        let mut conn = self.conn.clone();
+        let json = serde_json::to_string(report).unwrap();
+        conn.set_ex::<_, _, ()>(report_key(id), &json, REPORT_TTL_SECS)
+            .await?;
+        debug!(id = %id, "Stored job report");
+        Ok(())
+    }
+
+    /// Get the worker report for a job.
+    pub async fn get_report(&self, id: &str) -> Result<Option<JobReport>, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        let json: Option<String> = conn.get(report_key(id)).await?;
+        Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+
+    /// Get the fingerprints of issues posted on a PR that are still open.
+    pub async fn get_fingerprints(
+        &self,
+        project: &str,
+        pr: &str,
+    ) -> Result<Vec<IssueFingerprint>, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        let values: Vec<String> = conn.hvals(fingerprints_key(project, pr)).await?;
+        Ok(values
+            .into_iter()
+            .filter_map(|json| serde_json::from_str(&json).ok())
+            .collect())
+    }
+
+    /// Record newly posted issues of a PR and forget those whose code changed.
+    pub async fn update_fingerprints(
+        &self,
+        project: &str,
+        pr: &str,
+        opened: &[IssueFingerprint],
+        changed: &[String],
+    ) -> Result<(), redis::RedisError> {
+        if opened.is_empty() && changed.is_empty() {
+            return Ok(());
+        }
+        let key = fingerprints_key(project, pr);
+        let mut pipe = redis::pipe();
+        if !changed.is_empty() {
+            pipe.hdel(&key, changed).ignore();
+        }
+        for fingerprint in opened {
+            let json = serde_json::to_string(fingerprint).unwrap();
+            pipe.hset(&key, &fingerprint.id, json).ignore();
+        }
+        pipe.expire(&key, PR_STATE_TTL_SECS).ignore();
+
+        let mut conn = self.conn.clone();
+        pipe.query_async::<()>(&mut conn).await?;
+        debug!(
+            key = %key,
+            opened = opened.len(),
+            changed = changed.len(),
+            "Updated issue fingerprints"
+        );
+        Ok(())
+    }
+
+    /// Get the head commit of a PR at its last completed review.
+    pub async fn get_reviewed_head(
+        &self,
+        project: &str,
+        pr: &str,
+    ) -> Result<Option<String>, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        conn.get(reviewed_head_key(project, pr)).await
+    }
+
+    /// Remember the head commit a PR was last reviewed at, for
+    /// `PR_STATE_TTL_SECS` after its last review.
+    pub async fn set_reviewed_head(
+        &self,
+        project: &str,
+        pr: &str,
+        sha: &str,
+    ) -> Result<(), redis::RedisError> {
+        let mut conn = self.conn.clone();
+        conn.set_ex::<_, _, ()>(
+            reviewed_head_key(project, pr),
+            sha,
+            PR_STATE_TTL_SECS as u64,
+        )
+        .await?;
+        debug!(project = %project, pr = %pr, sha = %sha, "Stored reviewed head");
+        Ok(())
+    }
+
+    /// Park a Sentry issue below the impact thresholds, with the reasons, so
+    /// later webhooks and the periodic re-evaluation look at it again. An
+    /// issue stays parked at most `PARKED_SENTRY_TTL_SECS` after it was first
+    /// parked, however often it is re-evaluated.
+    pub async fn park_sentry_issue(
+        &self,
+        issue: &Issue,
+        installation: &str,
+        reasons: &[String],
+    ) -> Result<(), redis::RedisError> {
+        let key = parked_sentry_key(&issue.id);
+        let mut conn = self.conn.clone();
+        let existing: Option<String> = conn.get(&key).await?;
+        let now = chrono::Utc::now();
+        let parked_at = existing
+            .and_then(|json| serde_json::from_str::<ParkedSentryIssue>(&json).ok())
+            .map_or(now, |parked| parked.parked_at);
+        let parked = ParkedSentryIssue {
+            issue: issue.clone(),
+            installation: installation.to_string(),
+            reasons: reasons.to_vec(),
+            parked_at,
+        };
+        let age = (now - parked_at).num_seconds().max(0) as u64;
+        let ttl = PARKED_SENTRY_TTL_SECS.saturating_sub(age).max(1);
+        let json = serde_json::to_string(&parked).unwrap();
+        conn.set_ex::<_, _, ()>(&key, &json, ttl).await?;
+        debug!(issue_id = %issue.id, ttl, "Parked Sentry issue");
+        Ok(())
+    }
+
+    /// Check if a Sentry issue is parked below the impact thresholds.
+    pub async fn is_sentry_issue_parked(&self, issue_id: &str) -> Result<bool, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        conn.exists(parked_sentry_key(issue_id)).await
+    }
+
+    /// All parked Sentry issues. Entries that do not parse, such as those
+    /// parked before the issue was stored with them, are left to expire.
+    pub async fn parked_sentry_issues(&self) -> Result<Vec<ParkedSentryIssue>, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        let pattern = format!("{PARKED_SENTRY_KEY_PREFIX}:*");
+        let mut keys = Vec::new();
+        {
+            let mut iter = conn.scan_match::<_, String>(&pattern).await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key);
+            }
+        }
+
+        let mut parked = Vec::new();
+        for key in keys {
+            let Some(json) = conn.get::<_, Option<String>>(&key).await? else {
+                continue;
+            };
+            match serde_json::from_str(&json) {
+                Ok(issue) => parked.push(issue),
+                Err(e) => warn!(key = %key, error = %e, "Skipping unreadable parked Sentry issue"),
+            }
+        }
+        Ok(parked)
+    }
+
+    /// Forget a parked Sentry issue, returning whether it was parked.
+    pub async fn unpark_sentry_issue(&self, issue_id: &str) -> Result<bool, redis::RedisError> {
+        let mut conn = self.conn.clone();
+        let removed: u32 = conn.del(parked_sentry_key(issue_id)).await?;
+        Ok(removed > 0)
+    }
+}
+
+fn pr_field(project: &str, pr: &str) -> String {
+    format!("{project}#{pr}")
+}
+
+fn reviewed_head_key(project: &str, pr: &str) -> String {
+    format!("{REVIEWED_HEAD_KEY_PREFIX}:{}", pr_field(project, pr))
+}
+
+fn report_key(id: &str) -> String {
+    format!("{REPORTS_KEY_PREFIX}:{id}")
+}
+
+fn parked_sentry_key(issue_id: &str) -> String {
+    format!("{PARKED_SENTRY_KEY_PREFIX}:{issue_id}")
+}
+
+fn fingerprints_key(project: &str, pr: &str) -> String {
+    format!("{FINGERPRINTS_KEY_PREFIX}:{}", pr_field(project, pr))
+}
+
+/// A Sentry issue parked below the impact thresholds of its project.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ParkedSentryIssue {
+    pub issue: Issue,
+    /// Sentry installation of the webhook that parked the issue.
+    pub installation: String,
+    /// Why the issue is below the thresholds.
+    pub reasons: Vec<String>,
+    pub parked_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -0,0 +1,356 @@
+//! Structured job reports written by workers.
+//!
+//! The worker writes a compact JSON report to its termination message so the
+//! scheduler can pick it up from the pod status and store it with the job.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use claude_agent_core::{
+    FingerprintReport, Metrics, ReviewDecision, ReviewResult, SuggestionStats, TestsVerdict,
+};
+
+/// Kubernetes truncates termination messages at 4096 bytes.
+const MAX_SUMMARY_CHARS: usize = 1500;
+
+/// Budget for the serialized report, leaving some room under the limit.
+const MAX_REPORT_BYTES: usize = 3800;
+
+/// Message of the worker log line carrying the fingerprints of a review
+/// whose report would not fit the termination message with them.
+pub const FINGERPRINTS_LOG_MESSAGE: &str = "Issue fingerprints";
+
+/// Default location of the report inside the worker container.
+pub const DEFAULT_REPORT_PATH: &str = "/dev/termination-log";
+
+/// Final status of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Succeeded,
+    Failed,
+}
+
+/// Structured outcome of a worker job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobReport {
+    /// Short job description (e.g. "review owner/repo!42").
+    pub job: String,
+    pub status: JobStatus,
+    /// Error message when the job failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Review decision (structured review jobs only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<ReviewDecision>,
+    /// Final agent output, truncated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Number of issues reported by the agent.
+    #[serde(default)]
+    pub issues: usize,
+    /// Review quality score (0–100).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u8>,
+    /// Review effort estimate (1–5), used to route heavy PRs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tests: Option<TestsVerdict>,
+    /// Head commit that was reviewed (review jobs only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_sha: Option<String>,
+    /// Number of TODO markers added by the PR.
+    #[serde(default)]
+    pub todos: usize,
+    /// Issue fingerprints posted, suppressed and changed by a review.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprints: Option<FingerprintReport>,
+    /// The fingerprints were too large for the termination message and were
+    /// written to the worker log instead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fingerprints_in_log: bool,
+    /// Suggested changes validated against the checkout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestions: Option<SuggestionStats>,
+    pub metrics: Metrics,
+    pub worker_version: String,
+    /// Version of the prompt templates the job ran with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl JobReport {
+    /// Report for a job whose agent finished with a result.
+    pub fn succeeded(job: impl Into<String>, result: &ReviewResult, metrics: Metrics) -> Self {
+        Self {
+            job: job.into(),
+            status: JobStatus::Succeeded,
+            error: None,
+            decision: Some(result.decision),
+            summary: Some(truncate(&result.summary, MAX_SUMMARY_CHARS)),
+            issues: result.issues.len(),
+            score: result.score,
+            effort: result.effort,
+            tests: result.tests,
+            head_sha: None,
+            todos: result.todos.len(),
+            fingerprints: None,
+            fingerprints_in_log: false,
+            suggestions: None,
+            metrics,
+            worker_version: String::new(),
+            prompt_version: None,
+            finished_at: Utc::now(),
+        }
+    }
+
+    /// Report for a job that failed before or during the agent run.
+    pub fn failed(job: impl Into<String>, error: impl Into<String>, metrics: Metrics) -> Self {
+        Self {
+            job: job.into(),
+            status: JobStatus::Failed,
+            error: Some(truncate(&error.into(), MAX_SUMMARY_CHARS)),
+            decision: None,
+            summary: None,
+            issues: 0,
+            score: None,
+            effort: None,
+            tests: None,
+            head_sha: None,
+            todos: 0,
+            fingerprints: None,
+            fingerprints_in_log: false,
+            suggestions: None,
+            metrics,
+            worker_version: String::new(),
+            prompt_version: None,
+            finished_at: Utc::now(),
+        }
+    }
+
+    pub fn with_worker_version(mut self, version: &str) -> Self {
+        self.worker_version = version.to_string();
+        self
+    }
+
+    /// Record the version of the prompt templates used.
+    pub fn with_prompt_version(mut self, version: &str) -> Self {
+        self.prompt_version = Some(version.to_string());
+        self
+    }
+
+    /// Record the head commit the review was done at.
+    pub fn with_head_sha(mut self, sha: Option<String>) -> Self {
+        self.head_sha = sha;
+        self
+    }
+
+    /// Attach the outcome of suggestion validation.
+    pub fn with_suggestions(mut self, stats: SuggestionStats) -> Self {
+        if stats != SuggestionStats::default() {
+            self.suggestions = Some(stats);
+        }
+        self
+    }
+
+    /// Attach the fingerprint decisions of a review.
+    pub fn with_fingerprints(mut self, fingerprints: FingerprintReport) -> Self {
+        if !fingerprints.is_empty() {
+            self.fingerprints = Some(fingerprints);
+        }
+        self
+    }
+
+    /// Shrink the report to fit the termination message. Call once the
+    /// report is complete.
+    ///
+    /// Drops the suppressed issue details first (their count is kept), then
+    /// shortens the summary. If the fingerprints still do not fit, they are
+    /// moved out of the report and returned, for the worker to log where the
+    /// scheduler picks them up: opened and changed fingerprints are never
+    /// dropped, as the next review depends on them.
+    pub fn fit(&mut self) -> Option<FingerprintReport> {
+        while self.serialized_len() > MAX_REPORT_BYTES {
+            if let Some(fingerprints) = self.fingerprints.as_mut()
+                && fingerprints.suppressed.pop().is_some()
+            {
+                continue;
+            }
+            if let Some(summary) = self.summary.as_mut()
+                && summary.chars().count() > 100
+            {
+                *summary = truncate(summary, summary.chars().count() / 2);
+                continue;
+            }
+            let fingerprints = self.fingerprints.take()?;
+            self.fingerprints_in_log = true;
+            return Some(fingerprints);
+        }
+        None
+    }
+
+    fn serialized_len(&self) -> usize {
+        serde_json::to_string(self).map_or(0, |json| json.len())
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.status == JobStatus::Succeeded
+    }
+}
+
+/// Fingerprints logged by a worker whose report could not hold them, from
+/// the worker's JSON log.
+pub fn fingerprints_from_log(log: &str) -> Option<FingerprintReport> {
+    log.lines().rev().find_map(|line| {
+        let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+        let fields = &entry["fields"];
+        if fields["message"] != FINGERPRINTS_LOG_MESSAGE {
+            return None;
+        }
+        serde_json::from_str(fields["fingerprints"].as_str()?).ok()
+    })
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_agent_core::{IssueCategory, IssueFingerprint, SuppressedIssue};
+
+    #[test]
+    fn test_report_roundtrip() {
+        let result = ReviewResult::from_summary("Opened PR #7");
+        let report = JobReport::succeeded("sentry-fix WEB-1", &result, Metrics::default())
+            .with_worker_version("2026.02.12.1");
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains(r#""status":"succeeded""#));
+        assert!(!json.contains(r#""error":"#));
+
+        let parsed: JobReport = serde_json::from_str(&json).unwrap();
+        assert!(parsed.is_success());
+        assert_eq!(parsed.summary.as_deref(), Some("Opened PR #7"));
+    }
+
+    #[test]
+    fn test_report_review_signals() {
+        let mut result = ReviewResult::from_summary("Looks fine");
+        result.score = Some(90);
+        result.effort = Some(4);
+        result.tests = Some(TestsVerdict::Included);
+        let report = JobReport::succeeded("review a/b!1", &result, Metrics::default())
+            .with_suggestions(SuggestionStats {
+                applied: 2,
+                failed: 1,
+            });
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains(r#""suggestions":{"applied":2,"failed":1}"#));
+        assert!(json.contains(r#""score":90"#));
+        assert!(json.contains(r#""effort":4"#));
+        assert!(json.contains(r#""tests":"included""#));
+    }
+
+    fn fingerprint(i: usize) -> IssueFingerprint {
+        IssueFingerprint {
+            id: format!("{i:016x}"),
+            file: Some(format!("src/module_{i}/handlers.rs")),
+            category: IssueCategory::Bug,
+            line: Some(100),
+            span: 1,
+        }
+    }
+
+    fn fingerprint_report(opened: usize, changed: usize) -> FingerprintReport {
+        FingerprintReport {
+            opened: (0..opened).map(fingerprint).collect(),
+            suppressed: (0..30)
+                .map(|i| SuppressedIssue {
+                    fingerprint: format!("{i:016x}"),
+                    file: Some("src/lib.rs".into()),
+                    line: Some(7),
+                })
+                .collect(),
+            suppressed_count: 30,
+            changed: (0..changed).map(|i| format!("{:016x}", i + 1000)).collect(),
+        }
+    }
+
+    fn review_result() -> ReviewResult {
+        let mut result = ReviewResult::from_summary("y".repeat(1500));
+        result.decision = ReviewDecision::Comment;
+        result
+    }
+
+    #[test]
+    fn test_report_fingerprints_fit_termination_message() {
+        let mut report = JobReport::succeeded("review a/b!1", &review_result(), Metrics::default())
+            .with_fingerprints(fingerprint_report(20, 1))
+            .with_worker_version("2026.02.12.1")
+            .with_prompt_version("builtin+0123456789abcdef");
+        assert_eq!(report.fit(), None);
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.len() <= MAX_REPORT_BYTES);
+        assert!(!json.contains("fingerprints_in_log"));
+
+        let kept = report.fingerprints.unwrap();
+        assert!(kept.suppressed.is_empty());
+        assert_eq!(kept.suppressed_count, 30);
+        assert_eq!(kept.opened.len(), 20);
+        assert_eq!(kept.changed.len(), 1);
+        assert!(report.summary.unwrap().len() < 1500);
+    }
+
+    #[test]
+    fn test_report_moves_large_fingerprints_to_log() {
+        let fingerprints = fingerprint_report(60, 80);
+        let mut report = JobReport::succeeded("review a/b!1", &review_result(), Metrics::default())
+            .with_fingerprints(fingerprints.clone())
+            .with_worker_version("2026.02.12.1")
+            .with_prompt_version("builtin");
+
+        let moved = report.fit().unwrap();
+        assert_eq!(moved.opened, fingerprints.opened);
+        assert_eq!(moved.changed, fingerprints.changed);
+        assert_eq!(moved.suppressed_count, 30);
+        assert!(report.fingerprints.is_none());
+        assert!(report.fingerprints_in_log);
+        assert!(serde_json::to_string(&report).unwrap().len() <= MAX_REPORT_BYTES);
+
+        let log = format!(
+            "{}\n{}\n{}\n",
+            r#"{"level":"INFO","fields":{"message":"Agent finished"}}"#,
+            serde_json::json!({
+                "level": "INFO",
+                "fields": {
+                    "message": FINGERPRINTS_LOG_MESSAGE,
+                    "fingerprints": serde_json::to_string(&moved).unwrap(),
+                }
+            }),
+            "not json",
+        );
+        let logged = fingerprints_from_log(&log).unwrap();
+        assert_eq!(logged.opened.len(), 60);
+        assert_eq!(logged.changed.len(), 80);
+        assert_eq!(fingerprints_from_log("{}\n"), None);
+    }
+
+    #[test]
+    fn test_report_fits_termination_message() {
+        let long = "x".repeat(10_000);
+        let mut report = JobReport::failed("review a/b!1", long, Metrics::default());
+        assert_eq!(report.fit(), None);
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.len() < 4096);
+    }
+}
@@ -9,17 +9,21 @@ use std::time::Duration;
 use base64::Engine;
 use k8s_openapi::api::batch::v1::{Job, JobSpec};
 use k8s_openapi::api::core::v1::{
-    Container, EmptyDirVolumeSource, EnvVar, EnvVarSource, PodSpec, PodTemplateSpec,
+    Container, EmptyDirVolumeSource, EnvVar, EnvVarSource, Pod, PodSpec, PodTemplateSpec,
     ResourceRequirements, SecretKeySelector, Volume, VolumeMount,
 };
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
 use kube::Client;
-use kube::api::{Api, DeleteParams, ListParams, PostParams};
+use kube::api::{Api, DeleteParams, ListParams, LogParams, PostParams};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
+use claude_agent_core::FingerprintReport;
+
 use crate::jira_token::JiraTokenManager;
+use crate::payload::JobPayload;
 use crate::queue::{Queue, QueueItem};
+use crate::report::{JobReport, fingerprints_from_log};
 
 const NAMESPACE: &str = "claude-agent";
 /// Worker image, configurable via WORKER_IMAGE env var (defaults to :latest)
@@ -74,6 +78,7 @@ pub struct Scheduler {
     queue: Queue,
     _k8s_client: Client,
     jobs_api: Api<Job>,
+    pods_api: Api<Pod>,
     running: Arc<Mutex<bool>>,
     jira_token_manager: Option<Arc<JiraTokenManager>>,
 }
@@ -85,11 +90,13 @@ impl Scheduler {
     ) -> Result<Self, kube::Error> {
         let k8s_client = Client::try_default().await?;
         let jobs_api = Api::namespaced(k8s_client.clone(), NAMESPACE);
+        let pods_api = Api::namespaced(k8s_client.clone(), NAMESPACE);
 
         Ok(Self {
             queue,
             _k8s_client: k8s_client,
             jobs_api,
+            pods_api,
             running: Arc::new(Mutex::new(false)),
             jira_token_manager,
         })
@@ -144,7 +151,10 @@ impl Scheduler {
     }
 
     async fn await_job_completion(&self, job_name: &str, item: QueueItem) {
-        match self.wait_for_job(job_name).await {
+        let outcome = self.wait_for_job(job_name).await;
+        self.collect_report(job_name, &item).await;
+
+        match outcome {
             Ok(true) => {
                 let _ = self.queue.mark_completed(&item.id).await;
             }
@@ -161,6 +171,133 @@ impl Scheduler {
         }
     }
 
+    /// Read the worker's report from the pod termination message and store it,
+    /// along with the issue fingerprints of a review.
+    async fn collect_report(&self, job_name: &str, item: &QueueItem) {
+        let Some(mut report) = self.fetch_job_report(job_name).await else {
+            debug!(job = %job_name, "No job report available");
+            return;
+        };
+        if report.fingerprints_in_log {
+            report.fingerprints = self.fetch_logged_fingerprints(job_name).await;
+        }
+        info!(
+            job = %job_name,
+            status = ?report.status,
+            cost_usd = report.metrics.cost_usd,
+            "Collected job report"
+        );
+        if let Err(e) = self.queue.store_report(&item.id, &report).await {
+            warn!(error = %e, job = %job_name, "Failed to store job report");
+        }
+
+        let JobPayload::Review(payload) = &item.payload else {
+            return;
+        };
+        if report.is_success()
+            && let Some(sha) = &report.head_sha
+            && let Err(e) = self
+                .queue
+                .set_reviewed_head(&payload.project, &payload.mr_iid, sha)
+                .await
+        {
+            warn!(error = %e, job = %job_name, "Failed to store reviewed head");
+        }
+        if let Some(fingerprints) = &report.fingerprints {
+            info!(
+                job = %job_name,
+                opened = fingerprints.opened.len(),
+                suppressed = fingerprints.suppressed_count,
+                changed = fingerprints.changed.len(),
+                "Updating issue fingerprints"
+            );
+            let updated = self
+                .queue
+                .update_fingerprints(
+                    &payload.project,
+                    &payload.mr_iid,
+                    &fingerprints.opened,
+                    &fingerprints.changed,
+                )
+                .await;
+            if let Err(e) = updated {
+                warn!(error = %e, job = %job_name, "Failed to update issue fingerprints");
+            }
+        }
+    }
+
+    /// The payload passed to the worker: reviews get the fingerprints of the
+    /// issues already posted on the PR and the last reviewed head commit.
+    async fn job_payload(&self, item: &QueueItem) -> JobPayload {
+        let mut payload = item.payload.clone();
+        if let JobPayload::Review(review) = &mut payload {
+            match self
+                .queue
+                .get_fingerprints(&review.project, &review.mr_iid)
+                .await
+            {
+                Ok(known) => review.known_issues = known,
+                Err(e) => warn!(error = %e, "Failed to load issue fingerprints"),
+            }
+            match self
+                .queue
+                .get_reviewed_head(&review.project, &review.mr_iid)
+                .await
+            {
+                Ok(sha) => review.last_reviewed_sha = sha,
+                Err(e) => warn!(error = %e, "Failed to load last reviewed head"),
+            }
+        }
+        payload
+    }
+
+    async fn fetch_job_report(&self, job_name: &str) -> Option<JobReport> {
+        let lp = ListParams::default().labels(&format!("job-name={job_name}"));
+        let pods = match self.pods_api.list(&lp).await {
+            Ok(pods) => pods,
+            Err(e) => {
+                warn!(error = %e, job = %job_name, "Failed to list job pods");
+                return None;
+            }
+        };
+        let message = pods.items.iter().find_map(termination_message)?;
+        match serde_json::from_str(&message) {
+            Ok(report) => Some(report),
+            Err(e) => {
+                warn!(error = %e, job = %job_name, "Failed to parse job report");
+                None
+            }
+        }
+    }
+
+    /// Read the fingerprints a worker logged because they did not fit its
+    /// report.
+    async fn fetch_logged_fingerprints(&self, job_name: &str) -> Option<FingerprintReport> {
+        let lp = ListParams::default().labels(&format!("job-name={job_name}"));
+        let pods = match self.pods_api.list(&lp).await {
+            Ok(pods) => pods,
+            Err(e) => {
+                warn!(error = %e, job = %job_name, "Failed to list job pods");
+                return None;
+            }
+        };
+        for pod in &pods.items {
+            let Some(name) = pod.metadata.name.as_deref() else {
+                continue;
+            };
+            match self.pods_api.logs(name, &LogParams::default()).await {
+                Ok(log) => {
+                    if let Some(fingerprints) = fingerprints_from_log(&log) {
+                        return Some(fingerprints);
+                    }
+                }
+                Err(e) => warn!(error = %e, pod = %name, "Failed to read worker log"),
+            }
+        }
+        warn!(job = %job_name, "Logged issue fingerprints not found");
+        None
+    }
+
     /// Stop the scheduler.
     pub async fn stop(&self) {
         info!("Stopping scheduler");
@@ -238,7 +375,7 @@ impl Scheduler {
             &item.id[..8]
         );
 
-        let payload_json = serde_json::to_string(&item.payload).unwrap();
+        let payload_json = serde_json::to_string(&self.job_payload(item).await).unwrap();
         let payload_b64 = base64::engine::general_purpose::STANDARD.encode(&payload_json);
         let jira_access_token = self.get_jira_access_token().await;
         let env_vars = build_env_vars(payload_b64, jira_access_token);
@@ -309,6 +446,17 @@ impl Scheduler {
     }
 }
 
+/// Extract the termination message of the first terminated container.
+fn termination_message(pod: &Pod) -> Option<String> {
+    pod.status
+        .as_ref()?
+        .container_statuses
+        .as_ref()?
+        .iter()
+        .find_map(|status| status.state.as_ref()?.terminated.as_ref()?.message.clone())
+        .filter(|message| !message.is_empty())
+}
+
 fn build_worker_container(env_vars: Vec<EnvVar>) -> Container {
     Container {
         name: "worker".into(),
@@ -359,6 +507,11 @@ fn build_pod_spec(env_vars: Vec<EnvVar>) -> PodSpec {
 #[cfg(test)]
 mod tests {
     use k8s_openapi::api::batch::v1::JobStatus;
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateTerminated, ContainerStatus, Pod, PodStatus,
+    };
+
+    use super::termination_message;
 
     /// Helper to evaluate job status for testing
     fn evaluate_job_status(status: Option<&JobStatus>) -> Option<bool> {
@@ -421,4 +574,31 @@ mod tests {
             "Should fail after 3 not-founds"
         );
     }
+
+    #[test]
+    fn test_termination_message() {
+        let pod = Pod {
+            status: Some(PodStatus {
+                container_statuses: Some(vec![ContainerStatus {
+                    name: "worker".into(),
+                    state: Some(ContainerState {
+                        terminated: Some(ContainerStateTerminated {
+                            exit_code: 1,
+                            message: Some(r#"{"status":"failed"}"#.into()),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            termination_message(&pod).as_deref(),
+            Some(r#"{"status":"failed"}"#)
+        );
+        assert_eq!(termination_message(&Pod::default()), None);
+    }
 }
@@ -3,9 +3,12 @@
 #![allow(dead_code)] // Deserialization structs have unused fields
 
 use hmac::{Hmac, Mac};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serialize};
 use sha2::Sha256;
 
+use crate::sentry_impact::ImpactThresholds;
+use crate::sentry_paths::PathRewrite;
+
 type HmacSha256 = Hmac<Sha256>;
 
 /// Sentry webhook event (for issue alerts).
@@ -31,7 +34,7 @@ pub struct IssueData {
     pub issue: Issue,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Issue {
     /// Numeric issue ID
     pub id: String,
@@ -69,7 +72,7 @@ pub struct Issue {
     pub project: SentryProject,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SentryProject {
     /// Project ID
     pub id: String,
@@ -140,7 +143,21 @@ impl SentryWebhookEvent {
             "created" | "unresolved" => {}
             _ => return false,
         }
+        self.is_fixable()
+    }
+
+    /// Check if this event should re-evaluate an issue parked below the impact
+    /// thresholds: any later update that leaves the issue open.
+    pub fn should_reevaluate(&self) -> bool {
+        match self.action.as_str() {
+            "created" | "unresolved" | "assigned" => {}
+            _ => return false,
+        }
+        self.is_fixable()
+    }
 
+    /// Check if the issue is of a kind a fix job can handle.
+    fn is_fixable(&self) -> bool {
         // Skip certain issue categories we can't fix
         if let Some(category) = &self.data.issue.issue_category {
             match category.as_str() {
@@ -201,6 +218,12 @@ pub struct SentryProjectMapping {
     pub vcs_project: String,
     /// Target branch to base fixes on
     pub target_branch: String,
+    /// Rules mapping stacktrace paths to repository paths, applied in order
+    #[serde(default)]
+    pub path_rewrites: Vec<PathRewrite>,
+    /// Impact an issue must have before a fix job is queued for it
+    #[serde(default)]
+    pub impact: ImpactThresholds,
 }
 
 /// Parse project mappings from environment variable.
@@ -282,6 +305,14 @@ mod tests {
         assert!(!event.should_fix());
     }
 
+    #[test]
+    fn test_should_reevaluate() {
+        assert!(make_event("assigned", Some("error")).should_reevaluate());
+        assert!(make_event("unresolved", Some("error")).should_reevaluate());
+        assert!(!make_event("resolved", Some("error")).should_reevaluate());
+        assert!(!make_event("assigned", Some("performance")).should_reevaluate());
+    }
+
     #[test]
     fn test_should_not_fix_performance() {
         let event = make_event("created", Some("performance"));
@@ -371,6 +402,48 @@ mod tests {
         assert_eq!(mappings.len(), 1);
         assert_eq!(mappings[0].sentry_project, "globalcomix-web");
         assert_eq!(mappings[0].vcs_platform, "github");
+        assert!(mappings[0].path_rewrites.is_empty());
+        assert_eq!(mappings[0].impact, ImpactThresholds::default());
+    }
+
+    #[test]
+    fn test_parse_project_mappings_with_impact() {
+        let json = r#"[
+            {
+                "sentry_project": "web",
+                "clone_url": "https://github.com/acme/web.git",
+                "vcs_platform": "github",
+                "vcs_project": "acme/web",
+                "target_branch": "main",
+                "impact": {"min_events": 10, "environments": ["production"]}
+            }
+        ]"#;
+        let mappings = parse_project_mappings(json).unwrap();
+        assert_eq!(mappings[0].impact.min_events, 10);
+        assert_eq!(mappings[0].impact.min_users, 0);
+        assert_eq!(mappings[0].impact.environments, ["production"]);
+    }
+
+    #[test]
+    fn test_parse_project_mappings_with_rewrites() {
+        let json = r#"[
+            {
+                "sentry_project": "web",
+                "clone_url": "https://github.com/acme/web.git",
+                "vcs_platform": "github",
+                "vcs_project": "acme/web",
+                "target_branch": "main",
+                "path_rewrites": [
+                    {"regex": "^/var/www/releases/\\d+/"},
+                    {"prefix": "webpack://web/./", "replace": "assets/"}
+                ]
+            }
+        ]"#;
+        let mappings = parse_project_mappings(json).unwrap();
+        assert_eq!(mappings[0].path_rewrites.len(), 2);
+
+        let invalid = json.replace("\\\\d+", "(");
+        assert!(parse_project_mappings(&invalid).is_err());
     }
 
     #[test]
@@ -1,12 +1,16 @@
-//! Sentry API client for fetching issue details.
+//! Sentry API client for fetching issue details, and formatting of
+//! events for the fix prompt.
 
 #![allow(dead_code)] // Used by worker crate
 
 use anyhow::{Context, Result};
-use serde_json::Value;
+use reqwest::Method;
+use serde_json::{Value, json};
 use std::time::Duration;
 use tracing::debug;
 
+use crate::sentry_paths::{FrameLocation, FrameResolver};
+
 const MAX_RETRIES: u32 = 3;
 const INITIAL_BACKOFF_SECS: u64 = 1;
 
@@ -32,6 +36,12 @@ impl SentryClient {
         })
     }
 
+    /// Use another API root, such as a self-hosted Sentry's.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
     /// Get issue details by ID (numeric or short ID like "WEB-123").
     pub async fn get_issue(&self, issue_id: &str) -> Result<Value> {
         self.get(&format!(
@@ -47,10 +57,13 @@ impl SentryClient {
             .await
     }
 
-    /// Get events for an issue.
+    /// Get the most recent events of an issue, with their entries.
     pub async fn get_issue_events(&self, issue_id: &str, limit: u32) -> Result<Value> {
-        self.get(&format!("/issues/{}/events/?per_page={}", issue_id, limit))
-            .await
+        self.get(&format!(
+            "/issues/{}/events/?full=true&per_page={}",
+            issue_id, limit
+        ))
+        .await
     }
 
     /// Get a specific event by ID.
@@ -59,17 +72,81 @@ impl SentryClient {
             .await
     }
 
+    /// Add a note to the activity of an issue.
+    pub async fn add_note(&self, issue_id: &str, text: &str) -> Result<Value> {
+        let body = json!({ "text": text });
+        self.send(
+            Method::POST,
+            &format!("/issues/{}/comments/", issue_id),
+            Some(&body),
+        )
+        .await
+    }
+
+    /// Link an issue to a pull request (or any external issue) through the
+    /// Sentry integration installed as `installation`, so it shows on the
+    /// issue's sidebar.
+    pub async fn link_external_issue(
+        &self,
+        installation: &str,
+        issue_id: &str,
+        web_url: &str,
+        project: &str,
+        identifier: &str,
+    ) -> Result<Value> {
+        let issue_id = issue_id
+            .parse::<u64>()
+            .map_or_else(|_| json!(issue_id), |id| json!(id));
+        let body = json!({
+            "issueId": issue_id,
+            "webUrl": web_url,
+            "project": project,
+            "identifier": identifier,
+        });
+        self.send(
+            Method::POST,
+            &format!(
+                "/sentry-app-installations/{}/external-issues/",
+                installation
+            ),
+            Some(&body),
+        )
+        .await
+    }
+
+    /// Mark an issue as resolved in the next release.
+    pub async fn resolve_in_next_release(&self, issue_id: &str) -> Result<Value> {
+        let body = json!({
+            "status": "resolved",
+            "statusDetails": { "inNextRelease": true },
+        });
+        self.send(
+            Method::PUT,
+            &format!("/organizations/{}/issues/{}/", self.organization, issue_id),
+            Some(&body),
+        )
+        .await
+    }
+
     async fn get(&self, endpoint: &str) -> Result<Value> {
+        self.send(Method::GET, endpoint, None).await
+    }
+
+    async fn send(&self, method: Method, endpoint: &str, body: Option<&Value>) -> Result<Value> {
         let url = format!("{}{}", self.base_url, endpoint);
-        debug!(url = %url, "Sentry API request");
+        debug!(method = %method, url = %url, "Sentry API request");
 
         let resp = self
             .send_with_retry(|| {
-                self.http
-                    .get(&url)
+                let request = self
+                    .http
+                    .request(method.clone(), &url)
                     .header("Authorization", format!("Bearer {}", self.auth_token))
-                    .header("Content-Type", "application/json")
-                    .send()
+                    .header("Content-Type", "application/json");
+                match body {
+                    Some(body) => request.json(body).send(),
+                    None => request.send(),
+                }
             })
             .await
             .context("Failed to send Sentry API request")?;
@@ -129,65 +206,228 @@ fn is_retryable(err: &reqwest::Error) -> bool {
     }
 }
 
+/// Most breadcrumbs rendered, counting back from the error.
+const MAX_BREADCRUMBS: usize = 20;
+/// Most characters of a breadcrumb message or exception value.
+const MAX_MESSAGE_CHARS: usize = 300;
+/// Most query parameters or body fields listed for the request.
+const MAX_REQUEST_FIELDS: usize = 20;
+/// Most characters of a formatted event sent to the agent.
+const MAX_EVENT_CHARS: usize = 24_000;
+
+/// Request headers rendered; everything else (cookies, authorization, API
+/// keys, forwarded addresses) may identify the user and is left out.
+const SAFE_HEADERS: [&str; 8] = [
+    "accept",
+    "accept-language",
+    "content-type",
+    "content-length",
+    "host",
+    "origin",
+    "referer",
+    "user-agent",
+];
+
+/// Context fields rendered; contexts are reduced to what identifies the
+/// environment (name, version, device family).
+const SAFE_CONTEXT_FIELDS: [&str; 4] = ["name", "version", "family", "model"];
+
+/// Format a Sentry event for the fix prompt: the exceptions with their
+/// chained causes, then breadcrumbs, the request, contexts and release.
+///
+/// Only fields that describe the environment are kept: the user's email,
+/// IP and username, cookies, credentials, query and body values are never
+/// rendered. With a resolver, in-app frames are annotated with their path
+/// in the checkout and the current text of their line, and frames whose file
+/// is not in the repository are flagged. The result is capped at
+/// [`MAX_EVENT_CHARS`].
+pub fn format_event(event: &Value, resolver: Option<&FrameResolver>) -> String {
+    let mut output = format_exception_entries(event, resolver);
+    if output.is_empty() {
+        append_event_fallback(event, &mut output);
+    }
+    append_breadcrumbs(event, &mut output);
+    append_request(event, &mut output);
+    append_contexts(event, &mut output);
+    append_release(event, &mut output);
+    truncate_chars(&output, MAX_EVENT_CHARS)
+}
+
 /// Extract a formatted stacktrace from a Sentry event.
 pub fn format_stacktrace(event: &Value) -> String {
-    let mut output = format_exception_entries(event);
+    let mut output = format_exception_entries(event, None);
     if output.is_empty() {
         append_event_fallback(event, &mut output);
     }
     output
 }
 
-fn format_exception_entries(event: &Value) -> String {
+fn format_exception_entries(event: &Value, resolver: Option<&FrameResolver>) -> String {
     let mut output = String::new();
-    let Some(entries) = event["entries"].as_array() else {
-        return output;
-    };
-    for entry in entries {
-        append_exception_entry(entry, &mut output);
+    for entry in entries(event, "exception") {
+        append_exception_entry(entry, resolver, &mut output);
     }
     output
 }
 
-fn append_exception_entry(entry: &Value, output: &mut String) {
-    if entry["type"].as_str() != Some("exception") {
-        return;
-    }
-    let Some(values) = entry["data"]["values"].as_array() else {
+/// The `data` of the event's entries of type `kind`.
+pub(crate) fn entries<'a>(event: &'a Value, kind: &'a str) -> impl Iterator<Item = &'a Value> {
+    event["entries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(move |entry| entry["type"].as_str() == Some(kind))
+        .map(|entry| &entry["data"])
+}
+
+/// Sentry lists chained exceptions oldest first: the last value is the one
+/// that was raised, earlier ones are its causes.
+fn append_exception_entry(data: &Value, resolver: Option<&FrameResolver>, output: &mut String) {
+    let Some(values) = data["values"].as_array() else {
         return;
     };
-    for exception in values {
-        append_exception_details(exception, output);
+    for (index, exception) in values.iter().rev().enumerate() {
+        append_exception_details(exception, index > 0, resolver, output);
     }
 }
 
-fn append_exception_details(exception: &Value, output: &mut String) {
+fn append_exception_details(
+    exception: &Value,
+    is_cause: bool,
+    resolver: Option<&FrameResolver>,
+    output: &mut String,
+) {
     let exception_type = exception["type"].as_str().unwrap_or("Exception");
-    let exception_value = exception["value"].as_str().unwrap_or("");
-    output.push_str(&format!("## {} : {}\n\n", exception_type, exception_value));
-    append_stacktrace_frames(exception, output);
+    let exception_value =
+        truncate_chars(exception["value"].as_str().unwrap_or(""), MAX_MESSAGE_CHARS);
+    let caused_by = if is_cause { "Caused by " } else { "" };
+    output.push_str(&format!(
+        "## {caused_by}{} : {}\n\n",
+        exception_type, exception_value
+    ));
+    append_mechanism(&exception["mechanism"], output);
+    append_stacktrace_frames(exception, resolver, output);
 }
 
-fn append_stacktrace_frames(exception: &Value, output: &mut String) {
+fn append_mechanism(mechanism: &Value, output: &mut String) {
+    let Some(kind) = mechanism["type"].as_str() else {
+        return;
+    };
+    let handled = match mechanism["handled"].as_bool() {
+        Some(true) => ", handled",
+        Some(false) => ", unhandled",
+        None => "",
+    };
+    output.push_str(&format!("Mechanism: {kind}{handled}\n\n"));
+}
+
+/// Frames are listed most recent last. When the SDK marks in-app frames,
+/// runs of library frames are collapsed to one line, except the frame that
+/// raised the error.
+fn append_stacktrace_frames(
+    exception: &Value,
+    resolver: Option<&FrameResolver>,
+    output: &mut String,
+) {
     let Some(frames) = exception["stacktrace"]["frames"].as_array() else {
         return;
     };
     output.push_str("### Stacktrace (most recent last)\n\n");
-    for frame in frames {
-        append_frame(frame, output);
+    let marks_in_app = frames.iter().any(|f| f["inApp"].as_bool() == Some(true));
+    let mut library: Vec<&Value> = Vec::new();
+    for (index, frame) in frames.iter().enumerate() {
+        let in_app = !marks_in_app || frame["inApp"].as_bool() == Some(true);
+        let is_last = index + 1 == frames.len();
+        if in_app {
+            append_library_frames(&library, output);
+            library.clear();
+            append_frame(frame, resolver, output);
+        } else if is_last {
+            append_library_frames(&library, output);
+            library.clear();
+            output.push_str("  [library] ");
+            append_frame_location(frame, output);
+            output.push('\n');
+        } else {
+            library.push(frame);
+        }
     }
+    append_library_frames(&library, output);
 }
 
-fn append_frame(frame: &Value, output: &mut String) {
+fn append_library_frames(frames: &[&Value], output: &mut String) {
+    let (Some(first), Some(last)) = (frames.first(), frames.last()) else {
+        return;
+    };
+    let file = |frame: &Value| frame["filename"].as_str().unwrap_or("?").to_string();
+    let files = if frames.len() == 1 || file(first) == file(last) {
+        file(first)
+    } else {
+        format!("{} … {}", file(first), file(last))
+    };
+    let plural = if frames.len() == 1 { "" } else { "s" };
+    output.push_str(&format!(
+        "  … {} library frame{plural} ({files})\n\n",
+        frames.len()
+    ));
+}
+
+fn append_frame(frame: &Value, resolver: Option<&FrameResolver>, output: &mut String) {
+    output.push_str("  ");
+    append_frame_location(frame, output);
+    output.push('\n');
+    if let Some(resolver) = resolver {
+        append_repo_location(frame, resolver, output);
+    }
+    append_frame_context(frame, output);
+    output.push('\n');
+}
+
+fn append_frame_location(frame: &Value, output: &mut String) {
     let filename = frame["filename"].as_str().unwrap_or("?");
     let function = frame["function"].as_str().unwrap_or("?");
     let lineno = frame["lineNo"]
         .as_u64()
         .map(|line| line.to_string())
         .unwrap_or_else(|| "?".into());
-    output.push_str(&format!("  {} in {}:{}\n", function, filename, lineno));
-    append_frame_context(frame, output);
-    output.push('\n');
+    output.push_str(&format!("{} in {}:{}", function, filename, lineno));
+    if let Some(column) = frame["colNo"].as_u64() {
+        output.push_str(&format!(":{column}"));
+    }
+}
+
+/// Where the frame's file is in the checkout and what its line reads now,
+/// noting when it no longer matches the line of the event.
+fn append_repo_location(frame: &Value, resolver: &FrameResolver, output: &mut String) {
+    match resolver.resolve(frame) {
+        FrameLocation::Resolved { path, line } => {
+            let number = frame["lineNo"].as_u64();
+            let location = number.map_or(path.clone(), |number| format!("{path}:{number}"));
+            output.push_str(&format!("    repo: {location}"));
+            if let Some(line) = line {
+                let event_line = frame["context"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|context| context[0].as_u64() == number)
+                    .and_then(|context| context[1].as_str());
+                let changed = event_line.is_some_and(|event_line| event_line != line);
+                output.push_str(&format!(
+                    " | {}{}",
+                    truncate_chars(line.trim(), MAX_MESSAGE_CHARS),
+                    if changed {
+                        " (changed since the event)"
+                    } else {
+                        ""
+                    }
+                ));
+            }
+            output.push('\n');
+        }
+        FrameLocation::Unresolved { tried } => {
+            output.push_str(&format!("    repo: not found (tried `{tried}`)\n"));
+        }
+    }
 }
 
 fn append_frame_context(frame: &Value, output: &mut String) {
@@ -220,6 +460,242 @@ fn append_event_fallback(event: &Value, output: &mut String) {
     }
 }
 
+/// The last [`MAX_BREADCRUMBS`] breadcrumbs before the error, oldest first.
+fn append_breadcrumbs(event: &Value, output: &mut String) {
+    let crumbs: Vec<&Value> = entries(event, "breadcrumbs")
+        .filter_map(|data| data["values"].as_array())
+        .flatten()
+        .collect();
+    if crumbs.is_empty() {
+        return;
+    }
+    let skipped = crumbs.len().saturating_sub(MAX_BREADCRUMBS);
+    output.push_str("\n## Breadcrumbs (oldest first)\n\n");
+    if skipped > 0 {
+        output.push_str(&format!("… {skipped} earlier breadcrumbs omitted\n"));
+    }
+    for crumb in &crumbs[skipped..] {
+        output.push_str(&format!("- {}\n", format_breadcrumb(crumb)));
+    }
+}
+
+fn format_breadcrumb(crumb: &Value) -> String {
+    let mut line = String::new();
+    if let Some(time) = breadcrumb_time(&crumb["timestamp"]) {
+        line.push_str(&time);
+        line.push(' ');
+    }
+    if let Some(level) = crumb["level"].as_str()
+        && level != "info"
+    {
+        line.push_str(level);
+        line.push(' ');
+    }
+    let category = crumb["category"]
+        .as_str()
+        .or(crumb["type"].as_str())
+        .unwrap_or("default");
+    line.push_str(&format!("[{category}]"));
+
+    let data = &crumb["data"];
+    let text = if let Some(url) = data["url"].as_str() {
+        // HTTP requests: method, URL without query, status.
+        let method = data["method"].as_str().unwrap_or("");
+        let status = data["status_code"]
+            .as_u64()
+            .map(|status| format!(" → {status}"))
+            .unwrap_or_default();
+        format!("{method} {}{status}", strip_query(url))
+            .trim()
+            .to_string()
+    } else if let (Some(from), Some(to)) = (data["from"].as_str(), data["to"].as_str()) {
+        format!("{} → {}", strip_query(from), strip_query(to))
+    } else {
+        redact_emails(crumb["message"].as_str().unwrap_or(""))
+    };
+    if !text.is_empty() {
+        line.push(' ');
+        line.push_str(&truncate_chars(&text, MAX_MESSAGE_CHARS));
+    }
+    line
+}
+
+/// `HH:MM:SS` of an ISO 8601 or Unix timestamp.
+fn breadcrumb_time(timestamp: &Value) -> Option<String> {
+    if let Some(text) = timestamp.as_str() {
+        return chrono::DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|time| time.format("%H:%M:%S").to_string());
+    }
+    let seconds = timestamp.as_f64()?;
+    chrono::DateTime::from_timestamp_millis((seconds * 1000.0) as i64)
+        .map(|time| time.format("%H:%M:%S%.3f").to_string())
+}
+
+/// The HTTP request: method and URL without the query string, the names
+/// of query parameters and body fields, and [`SAFE_HEADERS`].
+fn append_request(event: &Value, output: &mut String) {
+    let Some(request) = entries(event, "request").next() else {
+        return;
+    };
+    let Some(url) = request["url"].as_str() else {
+        return;
+    };
+    output.push_str("\n## Request\n\n");
+    let method = request["method"].as_str().unwrap_or("");
+    output.push_str(format!("{method} {}\n", strip_query(url)).trim_start());
+
+    let mut query = pair_names(&request["query"]);
+    if query.is_empty()
+        && let Some((_, query_string)) = url.split_once('?')
+    {
+        query = query_string
+            .split('&')
+            .filter_map(|pair| pair.split('=').next())
+            .filter(|name| !name.is_empty())
+            .map(String::from)
+            .collect();
+    }
+    append_names("Query parameters", &query, output);
+
+    match &request["data"] {
+        Value::Object(fields) => {
+            let names: Vec<String> = fields.keys().cloned().collect();
+            append_names("Body fields", &names, output);
+        }
+        Value::String(body) if !body.is_empty() => {
+            output.push_str(&format!("Body: {} bytes\n", body.len()));
+        }
+        _ => {}
+    }
+
+    let headers: Vec<String> = request["headers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|pair| Some((pair[0].as_str()?, pair[1].as_str()?)))
+        .filter(|(name, _)| SAFE_HEADERS.contains(&name.to_ascii_lowercase().as_str()))
+        .map(|(name, value)| {
+            let value = if name.eq_ignore_ascii_case("referer") {
+                strip_query(value)
+            } else {
+                value.to_string()
+            };
+            format!("{name}: {}", truncate_chars(&value, MAX_MESSAGE_CHARS))
+        })
+        .collect();
+    if !headers.is_empty() {
+        output.push_str("Headers:\n");
+        for header in headers {
+            output.push_str(&format!("- {header}\n"));
+        }
+    }
+}
+
+/// Names of a `[[name, value], …]` list.
+fn pair_names(pairs: &Value) -> Vec<String> {
+    pairs
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|pair| pair[0].as_str().map(String::from))
+        .collect()
+}
+
+fn append_names(label: &str, names: &[String], output: &mut String) {
+    if names.is_empty() {
+        return;
+    }
+    let mut listed = names
+        .iter()
+        .take(MAX_REQUEST_FIELDS)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if names.len() > MAX_REQUEST_FIELDS {
+        listed.push_str(&format!(" (+{} more)", names.len() - MAX_REQUEST_FIELDS));
+    }
+    output.push_str(&format!("{label}: {listed}\n"));
+}
+
+/// Runtime, OS, browser and device contexts, and whether a user was
+/// identified.
+fn append_contexts(event: &Value, output: &mut String) {
+    let mut lines = Vec::new();
+    if let Some(contexts) = event["contexts"].as_object() {
+        let mut names: Vec<&String> = contexts.keys().collect();
+        names.sort();
+        for name in names {
+            let context = &contexts[name];
+            let kind = context["type"].as_str().unwrap_or(name);
+            if kind == "trace" {
+                continue;
+            }
+            let values: Vec<&str> = SAFE_CONTEXT_FIELDS
+                .iter()
+                .filter_map(|field| context[*field].as_str())
+                .filter(|value| !value.is_empty())
+                .collect();
+            if !values.is_empty() {
+                lines.push(format!("{kind}: {}", values.join(" ")));
+            }
+        }
+    }
+    if event["user"]["id"].is_string() || event["user"]["id"].is_number() {
+        lines.push("user: identified".to_string());
+    }
+    if lines.is_empty() {
+        return;
+    }
+    output.push_str("\n## Contexts\n\n");
+    for line in lines {
+        output.push_str(&format!("- {line}\n"));
+    }
+}
+
+fn append_release(event: &Value, output: &mut String) {
+    let release = event["release"]["version"]
+        .as_str()
+        .or(event["release"].as_str());
+    if let Some(release) = release {
+        output.push_str(&format!("\n**Release**: {release}\n"));
+    }
+}
+
+/// A URL without its query string, fragment and credentials.
+pub(crate) fn strip_query(url: &str) -> String {
+    let end = url.find(['?', '#']).unwrap_or(url.len());
+    let url = &url[..end];
+    if let Some(scheme_end) = url.find("://") {
+        let rest = &url[scheme_end + 3..];
+        let authority_end = rest.find('/').unwrap_or(rest.len());
+        if let Some(at) = rest[..authority_end].rfind('@') {
+            return format!("{}{}", &url[..scheme_end + 3], &rest[at + 1..]);
+        }
+    }
+    url.to_string()
+}
+
+/// Replace email addresses in free text with `[email]`.
+pub(crate) fn redact_emails(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            let is_email = word.split_once('@').is_some_and(|(user, domain)| {
+                !user.is_empty() && domain.contains('.') && !domain.starts_with('.')
+            });
+            if is_email { "[email]" } else { word }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
 /// Extract tags from a Sentry event.
 pub fn extract_tags(event: &Value) -> Vec<(String, String)> {
     let mut tags = Vec::new();
@@ -238,6 +714,7 @@ pub fn extract_tags(event: &Value) -> Vec<(String, String)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sentry_paths::PathRewrite;
 
     #[test]
     fn test_format_stacktrace_exception() {
@@ -284,6 +761,200 @@ mod tests {
         assert!(output.contains("Something went wrong"));
     }
 
+    fn fixture(name: &str) -> Value {
+        let text = match name {
+            "php" => include_str!("../fixtures/sentry/php_event.json"),
+            "python" => include_str!("../fixtures/sentry/python_event.json"),
+            _ => include_str!("../fixtures/sentry/javascript_event.json"),
+        };
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_format_php_event() {
+        let output = format_event(&fixture("php"), None);
+        assert!(output.starts_with("## ErrorException : Attempt to read property \"id\" on null"));
+        assert!(output.contains("Mechanism: generic, unhandled"));
+        assert!(output.contains(
+            "  … 3 library frames (vendor/laravel/framework/src/Illuminate/Foundation/Http/Kernel.php … vendor/laravel/framework/src/Illuminate/Routing/Controller.php)"
+        ));
+        assert!(
+            output.contains("    >   71 |         $order->customer_name = $order->customer->id;")
+        );
+        assert!(output.contains(
+            "  [library] Illuminate\\Foundation\\Bootstrap\\HandleExceptions::handleError in vendor/laravel/framework/src/Illuminate/Foundation/Bootstrap/HandleExceptions.php:255"
+        ));
+        assert!(
+            output
+                .contains("- 10:00:04 warning [log] Customer lookup for [email] returned nothing")
+        );
+        assert!(
+            output.contains(
+                "GET https://shop.example.com/orders/9001\nQuery parameters: token, ref\n"
+            )
+        );
+        assert!(output.contains("- User-Agent: Mozilla/5.0 (X11; Linux x86_64)"));
+        assert!(output.contains("- runtime: php 8.2.18"));
+        assert!(output.contains("- user: identified"));
+        assert!(output.contains("**Release**: web@2024.05.01-3f2a1bc"));
+
+        for secret in [
+            "jane.doe@example.com",
+            "203.0.113.7",
+            "janedoe",
+            "s3cr3t",
+            "Bearer",
+            "session=xyz",
+        ] {
+            assert!(!output.contains(secret), "{secret} leaked");
+        }
+        assert!(!output.contains("trace_id"));
+    }
+
+    #[test]
+    fn test_format_python_chained_event() {
+        let output = format_event(&fixture("python"), None);
+        let raised = output
+            .find("## InvoiceImportError : Row 12: bad quantity")
+            .unwrap();
+        let cause = output
+            .find("## Caused by ValueError : invalid literal for int() with base 10: 'abc'")
+            .unwrap();
+        assert!(raised < cause);
+        assert!(output.contains("Mechanism: django, unhandled"));
+        assert!(output.contains("  … 2 library frames (django/core/handlers/exception.py … django/core/handlers/base.py)"));
+        assert!(
+            output.contains("- 08:30:00 [httplib] GET https://files.example.com/export.csv → 200")
+        );
+        assert!(output.contains("POST https://api.example.com/billing/import/"));
+        assert!(output.contains("Body fields: dry_run, file_id, password"));
+        assert!(output.contains("- Referer: https://app.example.com/billing\n"));
+        assert!(output.contains("- runtime: CPython 3.11.8"));
+        for secret in [
+            "ops@example.com",
+            "198.51.100.23",
+            "k_live_abc",
+            "signature=",
+            "f_123",
+        ] {
+            assert!(!output.contains(secret), "{secret} leaked");
+        }
+    }
+
+    #[test]
+    fn test_format_javascript_event() {
+        let output = format_event(&fixture("javascript"), None);
+        assert!(output.contains("Mechanism: onunhandledrejection, unhandled"));
+        assert!(output.contains(
+            "  … 2 library frames (./node_modules/react-dom/cjs/react-dom.production.min.js)"
+        ));
+        assert!(output.contains("  CartTotal in ./src/cart/CartTotal.tsx:14:32"));
+        assert!(output.contains("- 10:00:00.000 [navigation] /products/42 → /cart"));
+        assert!(output.contains("- 10:00:01.500 error [fetch] GET /api/cart → 500"));
+        assert!(output.contains("error [console] Cart items missing product for user [email]"));
+        assert!(output.contains("https://shop.example.com/cart\nQuery parameters: coupon\n"));
+        assert!(output.contains("- browser: Chrome 124.0.0"));
+        assert!(output.contains("- device: Desktop"));
+        assert!(!output.contains("user: identified"));
+        assert!(!output.contains("Release"));
+        for secret in [
+            "192.0.2.44",
+            "bob@example.org",
+            "SPRING",
+            "session=abc",
+            "ref=mail",
+        ] {
+            assert!(!output.contains(secret), "{secret} leaked");
+        }
+    }
+
+    #[test]
+    fn test_format_event_with_repo_paths() {
+        let dir = std::env::temp_dir().join(format!("sentry-frames-{}", std::process::id()));
+        let repository = dir.join("app/Repositories/OrderRepository.php");
+        std::fs::create_dir_all(repository.parent().unwrap()).unwrap();
+        let mut lines = vec![String::new(); 70];
+        lines.push("        $order->customer_name = $order->customer?->id;".into());
+        std::fs::write(&repository, lines.join("\n")).unwrap();
+        std::fs::create_dir_all(dir.join("public")).unwrap();
+        std::fs::write(dir.join("public/index.php"), "<?php\n".repeat(60)).unwrap();
+
+        let resolver = FrameResolver::new(&[], &dir);
+        let output = format_event(&fixture("php"), Some(&resolver));
+        assert!(output.contains(
+            "OrderRepository.php:71\n    repo: app/Repositories/OrderRepository.php:71 | $order->customer_name = $order->customer?->id; (changed since the event)\n"
+        ));
+        assert!(
+            output.contains("    repo: public/index.php:52 | <?php (changed since the event)\n")
+        );
+        assert!(output.contains(
+            "    repo: not found (tried `/var/www/app/Http/Controllers/OrderController.php`)\n"
+        ));
+        // Collapsed library frames are not looked up.
+        assert_eq!(output.matches("repo: ").count(), 3);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_javascript_event_with_repo_paths() {
+        let dir = std::env::temp_dir().join(format!("sentry-frames-js-{}", std::process::id()));
+        let cart = dir.join("frontend/src/cart/CartTotal.tsx");
+        std::fs::create_dir_all(cart.parent().unwrap()).unwrap();
+        std::fs::write(&cart, "export function CartTotal() {}\n").unwrap();
+
+        let unmapped = FrameResolver::new(&[], &dir);
+        let output = format_event(&fixture("javascript"), Some(&unmapped));
+        assert!(
+            output.contains(
+                "    repo: not found (tried `webpack://shop/./src/cart/CartTotal.tsx`)\n"
+            )
+        );
+
+        let rewrites = vec![PathRewrite::Prefix {
+            prefix: "webpack://shop/./".into(),
+            replace: "frontend/".into(),
+        }];
+        let mapped = FrameResolver::new(&rewrites, &dir);
+        let output = format_event(&fixture("javascript"), Some(&mapped));
+        assert!(output.contains("    repo: frontend/src/cart/CartTotal.tsx:14\n"));
+        // The frame without `absPath` matches no rule and is flagged.
+        assert!(output.contains("    repo: not found (tried `./src/cart/CartTotal.tsx`)\n"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_event_caps() {
+        let crumbs: Vec<Value> = (0..50)
+            .map(|i| serde_json::json!({"category": "log", "message": format!("step {i} {}", "x".repeat(1000))}))
+            .collect();
+        let event = serde_json::json!({
+            "title": "Boom",
+            "entries": [{"type": "breadcrumbs", "data": {"values": crumbs}}]
+        });
+        let output = format_event(&event, None);
+        assert!(output.contains("… 30 earlier breadcrumbs omitted"));
+        assert!(!output.contains("step 29 "));
+        assert!(output.contains("step 30 "));
+        assert!(
+            output
+                .lines()
+                .all(|line| line.chars().count() < MAX_MESSAGE_CHARS + 20)
+        );
+        assert!(output.chars().count() <= MAX_EVENT_CHARS + 1);
+    }
+
+    #[test]
+    fn test_strip_query() {
+        assert_eq!(
+            strip_query("https://user:pw@host/a?b=1#c"),
+            "https://host/a"
+        );
+        assert_eq!(strip_query("/api/cart?session=abc"), "/api/cart");
+        assert_eq!(strip_query("https://host/a@b"), "https://host/a@b");
+    }
+
     #[test]
     fn test_extract_tags() {
         let event = serde_json::json!({
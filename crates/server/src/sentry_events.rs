@@ -0,0 +1,350 @@
+//! What the recent events of a Sentry issue have in common.
+//!
+//! The latest event alone can be unrepresentative: one odd browser or
+//! release sends the fixer after the wrong cause. [`summarize_occurrences`]
+//! compares a sample of events and reports the tags shared by all of them,
+//! the tags that vary, and the distinct stacktraces within the issue.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use crate::sentry_api::{entries, redact_emails, strip_query};
+
+/// Number of recent events sampled.
+pub const SAMPLE_EVENTS: u32 = 25;
+
+/// Most values listed for a varying tag.
+const MAX_TAG_VALUES: usize = 3;
+/// Most characters of a tag value or message.
+const MAX_VALUE_CHARS: usize = 100;
+/// Most stacktrace variants listed.
+const MAX_VARIANTS: usize = 5;
+/// In-app frames (most recent first) that identify a stacktrace variant.
+const SIGNATURE_FRAMES: usize = 5;
+/// Tags whose values identify a person; only the number of distinct values
+/// is reported.
+const PERSONAL_TAGS: [&str; 3] = ["user", "user.email", "client_ip"];
+
+/// Summarize a sample of events of one issue as a prompt section; empty
+/// when there are fewer than two events to compare.
+pub fn summarize_occurrences(events: &[Value]) -> String {
+    if events.len() < 2 {
+        return String::new();
+    }
+    let total = events.len();
+    let mut common = Vec::new();
+    let mut varying = Vec::new();
+
+    let types = count(events.iter().map(|e| exception(e).map(exception_type)));
+    describe_field("Exception", &types, total, &mut common, &mut varying);
+    let messages = count(events.iter().map(|e| {
+        exception(e)
+            .and_then(|ex| ex["value"].as_str())
+            .map(|value| truncate(&redact_emails(value)))
+    }));
+    describe_field("Message", &messages, total, &mut common, &mut varying);
+
+    let shared = shared_frames(events);
+    if !shared.is_empty() {
+        common.push(format!("In-app frames: {}", shared.join(", ")));
+    }
+
+    for (key, values) in tag_values(events) {
+        if PERSONAL_TAGS.contains(&key.as_str()) {
+            let distinct = values.keys().filter(|v| v.is_some()).count();
+            if distinct == 1 && !values.contains_key(&None) {
+                common.push(format!("{key}: the same in every event"));
+            } else {
+                varying.push(format!("{key}: {distinct} distinct"));
+            }
+            continue;
+        }
+        describe_field(&key, &values, total, &mut common, &mut varying);
+    }
+
+    let mut output = format!("## Occurrences\n\nSampled the {total} most recent events.\n");
+    append_list("Common to all occurrences", &common, &mut output);
+    append_list("Varying between occurrences", &varying, &mut output);
+    append_variants(events, &mut output);
+    output
+}
+
+/// The exception that was raised: the last value of the exception entry.
+fn exception(event: &Value) -> Option<&Value> {
+    entries(event, "exception")
+        .filter_map(|data| data["values"].as_array()?.last())
+        .last()
+}
+
+fn exception_type(exception: &Value) -> String {
+    exception["type"]
+        .as_str()
+        .unwrap_or("Exception")
+        .to_string()
+}
+
+/// Frames of the raised exception, most recent first, keeping in-app frames
+/// when the SDK marks them.
+fn app_frames(event: &Value) -> Vec<&Value> {
+    let frames: Vec<&Value> = exception(event)
+        .and_then(|ex| ex["stacktrace"]["frames"].as_array())
+        .into_iter()
+        .flatten()
+        .rev()
+        .collect();
+    if frames.iter().any(|f| f["inApp"].as_bool() == Some(true)) {
+        frames
+            .into_iter()
+            .filter(|f| f["inApp"].as_bool() == Some(true))
+            .collect()
+    } else {
+        frames
+    }
+}
+
+/// `function (file)`: line numbers are left out as they move between
+/// releases.
+fn frame_key(frame: &Value) -> String {
+    format!(
+        "{} ({})",
+        frame["function"].as_str().unwrap_or("?"),
+        frame["filename"].as_str().unwrap_or("?")
+    )
+}
+
+/// In-app frames found in the stacktrace of every event, most recent first.
+fn shared_frames(events: &[Value]) -> Vec<String> {
+    let mut frames = events
+        .iter()
+        .map(|e| app_frames(e).into_iter().map(frame_key).collect::<Vec<_>>());
+    let Some(mut shared) = frames.next() else {
+        return Vec::new();
+    };
+    for keys in frames {
+        shared.retain(|key| keys.contains(key));
+    }
+    shared.dedup();
+    shared
+}
+
+/// Values of every tag across the events; `None` counts events without it.
+fn tag_values(events: &[Value]) -> BTreeMap<String, HashMap<Option<String>, usize>> {
+    let mut tags: BTreeMap<String, HashMap<Option<String>, usize>> = BTreeMap::new();
+    for (index, event) in events.iter().enumerate() {
+        for tag in event["tags"].as_array().into_iter().flatten() {
+            let (Some(key), Some(value)) = (tag["key"].as_str(), tag["value"].as_str()) else {
+                continue;
+            };
+            let values = tags.entry(key.to_string()).or_default();
+            // Events before this one did not have the tag.
+            let seen: usize = values.values().sum();
+            if seen < index {
+                *values.entry(None).or_default() += index - seen;
+            }
+            let value = if key == "url" {
+                strip_query(value)
+            } else {
+                value.to_string()
+            };
+            *values.entry(Some(truncate(&value))).or_default() += 1;
+        }
+    }
+    for values in tags.values_mut() {
+        let seen: usize = values.values().sum();
+        if seen < events.len() {
+            *values.entry(None).or_default() += events.len() - seen;
+        }
+    }
+    tags
+}
+
+fn count(values: impl Iterator<Item = Option<String>>) -> HashMap<Option<String>, usize> {
+    let mut counts = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    counts
+}
+
+/// A field with one value in every event is common; otherwise its most
+/// frequent values are listed with their counts.
+fn describe_field(
+    name: &str,
+    values: &HashMap<Option<String>, usize>,
+    total: usize,
+    common: &mut Vec<String>,
+    varying: &mut Vec<String>,
+) {
+    if values.len() == 1 {
+        if let Some((Some(value), _)) = values.iter().next() {
+            common.push(format!("{name}: {value}"));
+        }
+        return;
+    }
+    let mut sorted: Vec<(&Option<String>, &usize)> = values.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.1.cmp(a.1)
+            .then_with(|| a.0.is_none().cmp(&b.0.is_none()))
+            .then_with(|| a.0.cmp(b.0))
+    });
+    let mut listed: Vec<String> = sorted
+        .iter()
+        .take(MAX_TAG_VALUES)
+        .map(|(value, count)| format!("{} ({count})", value.as_deref().unwrap_or("not set")))
+        .collect();
+    if sorted.len() > MAX_TAG_VALUES {
+        listed.push(format!("{} other values", sorted.len() - MAX_TAG_VALUES));
+    }
+    varying.push(format!(
+        "{name}: {} distinct in {total} events: {}",
+        sorted.len(),
+        listed.join(", ")
+    ));
+}
+
+fn append_list(title: &str, lines: &[String], output: &mut String) {
+    if lines.is_empty() {
+        return;
+    }
+    output.push_str(&format!("\n### {title}\n\n"));
+    for line in lines {
+        output.push_str(&format!("- {line}\n"));
+    }
+}
+
+/// Events grouped by exception type and their most recent in-app frames,
+/// listed when the issue has more than one stacktrace.
+fn append_variants(events: &[Value], output: &mut String) {
+    let mut variants: Vec<(Vec<String>, &Value, usize)> = Vec::new();
+    for event in events {
+        let mut signature: Vec<String> = app_frames(event)
+            .into_iter()
+            .take(SIGNATURE_FRAMES)
+            .map(frame_key)
+            .collect();
+        signature.insert(0, exception(event).map(exception_type).unwrap_or_default());
+        match variants.iter_mut().find(|(s, _, _)| *s == signature) {
+            Some((_, _, count)) => *count += 1,
+            None => variants.push((signature, event, 1)),
+        }
+    }
+    if variants.len() < 2 {
+        return;
+    }
+    variants.sort_by_key(|(_, _, count)| std::cmp::Reverse(*count));
+    output.push_str(&format!(
+        "\n### Stacktrace variants\n\n{} distinct stacktraces:\n\n",
+        variants.len()
+    ));
+    for (index, (signature, event, count)) in variants.iter().take(MAX_VARIANTS).enumerate() {
+        let frames: Vec<String> = app_frames(event)
+            .into_iter()
+            .take(3)
+            .map(|frame| {
+                let line = frame["lineNo"]
+                    .as_u64()
+                    .map(|line| format!(":{line}"))
+                    .unwrap_or_default();
+                format!(
+                    "{} in {}{line}",
+                    frame["function"].as_str().unwrap_or("?"),
+                    frame["filename"].as_str().unwrap_or("?")
+                )
+            })
+            .collect();
+        output.push_str(&format!(
+            "{}. {count} events: {} at {}\n",
+            index + 1,
+            signature[0],
+            frames.join(" ← ")
+        ));
+    }
+    if variants.len() > MAX_VARIANTS {
+        output.push_str(&format!(
+            "… {} more variants\n",
+            variants.len() - MAX_VARIANTS
+        ));
+    }
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_VALUE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(release: &str, browser: Option<&str>, frames: &[(&str, u64)]) -> Value {
+        let mut tags = vec![
+            serde_json::json!({"key": "environment", "value": "production"}),
+            serde_json::json!({"key": "release", "value": release}),
+            serde_json::json!({"key": "user", "value": format!("email:{release}@example.com")}),
+            serde_json::json!({"key": "url", "value": "https://shop.example.com/cart?coupon=X"}),
+        ];
+        if let Some(browser) = browser {
+            tags.push(serde_json::json!({"key": "browser", "value": browser}));
+        }
+        let mut stack = vec![serde_json::json!({
+            "filename": "./node_modules/react-dom/index.js", "function": "Rb", "lineNo": 1, "inApp": false
+        })];
+        stack.extend(frames.iter().map(|(function, line)| {
+            serde_json::json!({
+                "filename": "./src/cart/CartTotal.tsx", "function": function, "lineNo": line, "inApp": true
+            })
+        }));
+        serde_json::json!({
+            "tags": tags,
+            "entries": [{"type": "exception", "data": {"values": [{
+                "type": "TypeError",
+                "value": format!("Cannot read properties of undefined (reading 'price') for {release}"),
+                "stacktrace": {"frames": stack}
+            }]}}]
+        })
+    }
+
+    #[test]
+    fn test_summarize_occurrences() {
+        let main = [("CartTotal", 14), ("reduce", 13)];
+        let events = vec![
+            event("web@2", Some("Chrome 124"), &main),
+            event("web@2", Some("Firefox 125"), &main),
+            event("web@1", None, &[("CartTotal", 12), ("MiniCart", 30)]),
+        ];
+        let summary = summarize_occurrences(&events);
+
+        assert!(summary.starts_with("## Occurrences\n\nSampled the 3 most recent events.\n"));
+        assert!(summary.contains("### Common to all occurrences\n\n- Exception: TypeError\n"));
+        assert!(summary.contains("- In-app frames: CartTotal (./src/cart/CartTotal.tsx)\n"));
+        assert!(summary.contains("- environment: production\n"));
+        assert!(summary.contains("- url: https://shop.example.com/cart\n"));
+        assert!(summary.contains("- release: 2 distinct in 3 events: web@2 (2), web@1 (1)\n"));
+        assert!(summary.contains(
+            "- browser: 3 distinct in 3 events: Chrome 124 (1), Firefox 125 (1), not set (1)\n"
+        ));
+        assert!(summary.contains("- Message: 2 distinct in 3 events"));
+        assert!(summary.contains("- user: 2 distinct\n"));
+        assert!(!summary.contains("example.com\n- user"));
+        assert!(!summary.contains("@example.com"));
+
+        assert!(summary.contains("### Stacktrace variants\n\n2 distinct stacktraces:\n\n"));
+        assert!(summary.contains(
+            "1. 2 events: TypeError at reduce in ./src/cart/CartTotal.tsx:13 ← CartTotal in ./src/cart/CartTotal.tsx:14\n"
+        ));
+        assert!(summary.contains("2. 1 events: TypeError at MiniCart in"));
+    }
+
+    #[test]
+    fn test_single_event_has_no_summary() {
+        assert!(summarize_occurrences(&[event("web@1", None, &[("a", 1)])]).is_empty());
+        let same = event("web@1", None, &[("a", 1)]);
+        let summary = summarize_occurrences(&[same.clone(), same]);
+        assert!(!summary.contains("Varying"));
+        assert!(summary.contains("- user: the same in every event\n"));
+        assert!(!summary.contains("Stacktrace variants"));
+    }
+}
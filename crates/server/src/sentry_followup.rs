@@ -0,0 +1,326 @@
+//! Follow-up on a Sentry issue once its fix pull request exists.
+//!
+//! When the fixer opens a pull request, the issue gets a note with the pull
+//! request and its root cause, and a link to the pull request. When the pull
+//! request merges, the issue is resolved in the next release, so Sentry
+//! reopens it if the fix does not hold.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use tracing::info;
+
+use crate::sentry_api::SentryClient;
+
+/// Prefix of the branches fix jobs push to, followed by the lowercased
+/// short ID of the issue.
+pub const FIX_BRANCH_PREFIX: &str = "sentry-fix/";
+
+/// Most characters of the root cause quoted in the note.
+const MAX_ROOT_CAUSE_CHARS: usize = 1500;
+
+/// Branch the fix of an issue is pushed to.
+pub fn fix_branch(short_id: &str) -> String {
+    format!("{FIX_BRANCH_PREFIX}{}", short_id.to_lowercase())
+}
+
+/// Short ID of the issue a fix branch is for (`sentry-fix/web-12` → `WEB-12`).
+pub fn short_id_from_branch(branch: &str) -> Option<String> {
+    branch
+        .strip_prefix(FIX_BRANCH_PREFIX)
+        .filter(|short_id| !short_id.is_empty())
+        .map(str::to_uppercase)
+}
+
+/// A pull request opened for a Sentry issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixPullRequest {
+    /// Repository path (e.g., "acme/web").
+    pub repo: String,
+    pub number: u64,
+    pub url: String,
+    pub body: String,
+}
+
+impl FixPullRequest {
+    /// Build from a GitHub pull request API object.
+    pub fn from_github(pull_request: &Value) -> Option<Self> {
+        Some(Self {
+            repo: pull_request["base"]["repo"]["full_name"]
+                .as_str()?
+                .to_string(),
+            number: pull_request["number"].as_u64()?,
+            url: pull_request["html_url"].as_str()?.to_string(),
+            body: pull_request["body"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    /// The "Root Cause" section the fixer writes in the description.
+    pub fn root_cause(&self) -> Option<String> {
+        let is_heading = |line: &str| {
+            line.starts_with('#')
+                && line
+                    .trim_start_matches('#')
+                    .trim()
+                    .eq_ignore_ascii_case("root cause")
+        };
+        let mut lines = self.body.lines().skip_while(|line| !is_heading(line));
+        lines.next()?;
+        let section: Vec<&str> = lines.take_while(|line| !line.starts_with('#')).collect();
+        let section = section.join("\n").trim().to_string();
+        (!section.is_empty()).then_some(section)
+    }
+
+    /// Note added to the issue when the pull request is opened.
+    pub fn note(&self) -> String {
+        let mut note = format!(
+            "Fix proposed in {}#{}: {}",
+            self.repo, self.number, self.url
+        );
+        if let Some(root_cause) = self.root_cause() {
+            let root_cause: String = root_cause.chars().take(MAX_ROOT_CAUSE_CHARS).collect();
+            note.push_str(&format!("\n\nRoot cause:\n{root_cause}"));
+        }
+        note
+    }
+}
+
+/// Record a fix pull request on its issue: add a note, and link the pull
+/// request through the Sentry integration when its installation is known.
+pub async fn record_fix(
+    client: &SentryClient,
+    issue_id: &str,
+    installation: Option<&str>,
+    pull_request: &FixPullRequest,
+) -> Result<()> {
+    client
+        .add_note(issue_id, &pull_request.note())
+        .await
+        .context("Failed to add note to Sentry issue")?;
+
+    if let Some(installation) = installation {
+        client
+            .link_external_issue(
+                installation,
+                issue_id,
+                &pull_request.url,
+                &pull_request.repo,
+                &format!("#{}", pull_request.number),
+            )
+            .await
+            .context("Failed to link pull request to Sentry issue")?;
+    }
+    info!(
+        issue_id,
+        pr = %pull_request.url,
+        linked = installation.is_some(),
+        "Recorded fix on Sentry issue"
+    );
+    Ok(())
+}
+
+/// Resolve the issue of a merged fix in the next release. Returns the
+/// numeric issue ID, or `None` when the issue was already resolved.
+pub async fn resolve_merged_fix(client: &SentryClient, short_id: &str) -> Result<Option<String>> {
+    let issue = client
+        .get_issue(short_id)
+        .await
+        .context("Failed to fetch Sentry issue")?;
+    let issue_id = issue["id"]
+        .as_str()
+        .context("Sentry issue has no ID")?
+        .to_string();
+    if issue["status"].as_str() == Some("resolved") {
+        info!(short_id, "Sentry issue already resolved");
+        return Ok(None);
+    }
+
+    client
+        .resolve_in_next_release(&issue_id)
+        .await
+        .context("Failed to resolve Sentry issue")?;
+    info!(short_id, issue_id = %issue_id, "Resolved Sentry issue in next release");
+    Ok(Some(issue_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::{Method, Uri};
+    use serde_json::json;
+
+    /// Requests received by the fake Sentry API.
+    type Requests = Arc<Mutex<Vec<(Method, String, Value)>>>;
+
+    /// Serve a fake Sentry API on a local port, answering issue lookups with
+    /// `issue` and recording every request.
+    async fn fake_sentry(issue: Value) -> (String, Requests) {
+        let requests: Requests = Arc::default();
+        let state = (requests.clone(), issue);
+        let app = Router::new().fallback(
+            |State((requests, issue)): State<(Requests, Value)>,
+             method: Method,
+             uri: Uri,
+             body: String| async move {
+                let body = serde_json::from_str(&body).unwrap_or(Value::Null);
+                let response = if method == Method::GET {
+                    issue
+                } else {
+                    json!({ "id": "1" })
+                };
+                requests
+                    .lock()
+                    .unwrap()
+                    .push((method, uri.path().to_string(), body));
+                axum::Json(response)
+            },
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app.with_state(state)).await });
+        (url, requests)
+    }
+
+    fn pull_request() -> FixPullRequest {
+        FixPullRequest::from_github(&json!({
+            "number": 12,
+            "html_url": "https://github.com/acme/web/pull/12",
+            "body": "## Summary\n\nFixes Sentry issue WEB-7\n\n## Root Cause\n\n`$order->customer` is null for guest orders.\n\n## Fix\n\nGuard the lookup.",
+            "base": { "repo": { "full_name": "acme/web" } }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_fix_branch() {
+        assert_eq!(fix_branch("WEB-7"), "sentry-fix/web-7");
+        assert_eq!(
+            short_id_from_branch("sentry-fix/web-7").as_deref(),
+            Some("WEB-7")
+        );
+        assert_eq!(short_id_from_branch("feature/web-7"), None);
+        assert_eq!(short_id_from_branch("sentry-fix/"), None);
+    }
+
+    #[test]
+    fn test_note() {
+        let pr = pull_request();
+        assert_eq!(
+            pr.root_cause().as_deref(),
+            Some("`$order->customer` is null for guest orders.")
+        );
+        assert_eq!(
+            pr.note(),
+            "Fix proposed in acme/web#12: https://github.com/acme/web/pull/12\n\n\
+             Root cause:\n`$order->customer` is null for guest orders."
+        );
+
+        let bare = FixPullRequest {
+            body: "Guard the lookup.".into(),
+            ..pr
+        };
+        assert_eq!(bare.root_cause(), None);
+        assert!(!bare.note().contains("Root cause"));
+    }
+
+    #[tokio::test]
+    async fn test_record_fix() {
+        let (url, requests) = fake_sentry(Value::Null).await;
+        let client = SentryClient::new("acme", "token")
+            .unwrap()
+            .with_base_url(&url);
+
+        record_fix(&client, "4242", Some("install-1"), &pull_request())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let (method, path, body) = &requests[0];
+        assert_eq!(
+            (method, path.as_str()),
+            (&Method::POST, "/issues/4242/comments/")
+        );
+        assert!(
+            body["text"]
+                .as_str()
+                .unwrap()
+                .contains("https://github.com/acme/web/pull/12")
+        );
+        let (method, path, body) = &requests[1];
+        assert_eq!(
+            (method, path.as_str()),
+            (
+                &Method::POST,
+                "/sentry-app-installations/install-1/external-issues/"
+            )
+        );
+        assert_eq!(
+            body,
+            &json!({
+                "issueId": 4242,
+                "webUrl": "https://github.com/acme/web/pull/12",
+                "project": "acme/web",
+                "identifier": "#12",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_fix_without_installation() {
+        let (url, requests) = fake_sentry(Value::Null).await;
+        let client = SentryClient::new("acme", "token")
+            .unwrap()
+            .with_base_url(&url);
+
+        record_fix(&client, "4242", None, &pull_request())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1, "/issues/4242/comments/");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_merged_fix() {
+        let issue = json!({ "id": "4242", "shortId": "WEB-7", "status": "unresolved" });
+        let (url, requests) = fake_sentry(issue).await;
+        let client = SentryClient::new("acme", "token")
+            .unwrap()
+            .with_base_url(&url);
+
+        let resolved = resolve_merged_fix(&client, "WEB-7").await.unwrap();
+        assert_eq!(resolved.as_deref(), Some("4242"));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].1, "/organizations/acme/issues/WEB-7/");
+        let (method, path, body) = &requests[1];
+        assert_eq!(
+            (method, path.as_str()),
+            (&Method::PUT, "/organizations/acme/issues/4242/")
+        );
+        assert_eq!(
+            body,
+            &json!({ "status": "resolved", "statusDetails": { "inNextRelease": true } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_merged_fix_already_resolved() {
+        let issue = json!({ "id": "4242", "shortId": "WEB-7", "status": "resolved" });
+        let (url, requests) = fake_sentry(issue).await;
+        let client = SentryClient::new("acme", "token")
+            .unwrap()
+            .with_base_url(&url);
+
+        assert_eq!(resolve_merged_fix(&client, "WEB-7").await.unwrap(), None);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,238 @@
+//! Impact thresholds gating Sentry fix jobs.
+//!
+//! A fix job is expensive, so a project mapping can require an issue to have
+//! enough events, users and age, to be seen in chosen environments, and to
+//! not come from excluded culprits. Issues below the thresholds are parked
+//! and evaluated again on later webhooks for them and on a timer; issues
+//! from excluded culprits are skipped without parking.
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// Impact an issue must have for a fix job to be queued.
+///
+/// ```json
+/// {
+///   "min_events": 10,
+///   "min_users": 2,
+///   "min_age_minutes": 30,
+///   "environments": ["production"],
+///   "exclude_culprits": ["^vendor/", "HealthCheckController"]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct ImpactThresholds {
+    /// Minimum number of events of the issue.
+    pub min_events: u64,
+    /// Minimum number of users affected.
+    pub min_users: u64,
+    /// Minimum time since the issue was first seen, in minutes.
+    pub min_age_minutes: u64,
+    /// Environments the issue must be seen in (empty allows all).
+    pub environments: Vec<String>,
+    /// Regex patterns of culprits never fixed.
+    #[serde(deserialize_with = "deserialize_patterns")]
+    pub exclude_culprits: Vec<String>,
+}
+
+/// Accept only patterns that compile, so a bad pattern fails the mapping.
+fn deserialize_patterns<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let patterns = Vec::<String>::deserialize(deserializer)?;
+    for pattern in &patterns {
+        Regex::new(pattern).map_err(serde::de::Error::custom)?;
+    }
+    Ok(patterns)
+}
+
+impl ImpactThresholds {
+    /// Whether checking the thresholds needs the issue's stats from the
+    /// Sentry API.
+    pub fn needs_stats(&self) -> bool {
+        self.min_events > 0
+            || self.min_users > 0
+            || self.min_age_minutes > 0
+            || !self.environments.is_empty()
+    }
+
+    /// Why a culprit is excluded, if it matches an excluded pattern.
+    pub fn excluded_culprit(&self, culprit: &str) -> Option<String> {
+        self.exclude_culprits
+            .iter()
+            .find(|pattern| Regex::new(pattern).is_ok_and(|regex| regex.is_match(culprit)))
+            .map(|pattern| format!("culprit `{culprit}` matches excluded pattern `{pattern}`"))
+    }
+
+    /// Why an issue, as returned by the Sentry issue API, falls below the
+    /// thresholds; empty when it meets them all.
+    pub fn shortfalls(&self, issue: &Value, now: DateTime<Utc>) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        let events = count(&issue["count"]);
+        if events < self.min_events {
+            reasons.push(format!(
+                "{events} events, below the minimum of {}",
+                self.min_events
+            ));
+        }
+        let users = count(&issue["userCount"]);
+        if users < self.min_users {
+            reasons.push(format!(
+                "{users} users, below the minimum of {}",
+                self.min_users
+            ));
+        }
+
+        if self.min_age_minutes > 0 {
+            let first_seen = issue["firstSeen"]
+                .as_str()
+                .and_then(|first_seen| DateTime::parse_from_rfc3339(first_seen).ok());
+            let age = first_seen.map(|first_seen| (now - first_seen.to_utc()).num_minutes());
+            match age {
+                Some(age) if age >= self.min_age_minutes as i64 => {}
+                Some(age) => reasons.push(format!(
+                    "first seen {} minutes ago, below the minimum age of {} minutes",
+                    age.max(0),
+                    self.min_age_minutes
+                )),
+                None => reasons.push("first-seen date unknown".into()),
+            }
+        }
+
+        // Issues whose environments are not reported are let through.
+        if !self.environments.is_empty()
+            && let Some(seen) = environments(issue)
+            && !seen
+                .iter()
+                .any(|environment| self.environments.contains(environment))
+        {
+            reasons.push(format!(
+                "seen only in {}, not in {}",
+                join_or_none(&seen),
+                self.environments.join(", ")
+            ));
+        }
+
+        reasons
+    }
+}
+
+/// A count the API returns as a number or as a string (`"count": "42"`).
+fn count(value: &Value) -> u64 {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|count| count.parse().ok()))
+        .unwrap_or(0)
+}
+
+/// Environments of the issue, from the top values of its `environment` tag.
+fn environments(issue: &Value) -> Option<Vec<String>> {
+    let tag = issue["tags"]
+        .as_array()?
+        .iter()
+        .find(|tag| tag["key"] == "environment")?;
+    Some(
+        tag["topValues"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value["value"].as_str().map(String::from))
+            .collect(),
+    )
+}
+
+fn join_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "no environment".into()
+    } else {
+        values.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn thresholds() -> ImpactThresholds {
+        serde_json::from_value(json!({
+            "min_events": 10,
+            "min_users": 2,
+            "min_age_minutes": 30,
+            "environments": ["production"],
+            "exclude_culprits": ["^vendor/", "HealthCheck"]
+        }))
+        .unwrap()
+    }
+
+    fn issue(count: &str, users: u64, first_seen: &str, environment: &str) -> Value {
+        json!({
+            "count": count,
+            "userCount": users,
+            "firstSeen": first_seen,
+            "tags": [
+                { "key": "browser", "topValues": [{ "value": "Chrome" }] },
+                { "key": "environment", "topValues": [{ "value": environment }] }
+            ]
+        })
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2025-03-01T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_shortfalls() {
+        let thresholds = thresholds();
+        assert!(thresholds.needs_stats());
+
+        let busy = issue("250", 40, "2025-03-01T09:00:00Z", "production");
+        assert!(thresholds.shortfalls(&busy, now()).is_empty());
+
+        let quiet = issue("3", 1, "2025-03-01T11:50:00Z", "staging");
+        assert_eq!(
+            thresholds.shortfalls(&quiet, now()),
+            [
+                "3 events, below the minimum of 10",
+                "1 users, below the minimum of 2",
+                "first seen 10 minutes ago, below the minimum age of 30 minutes",
+                "seen only in staging, not in production",
+            ]
+        );
+
+        let mut untagged = busy.clone();
+        untagged["tags"] = json!([]);
+        assert!(thresholds.shortfalls(&untagged, now()).is_empty());
+
+        let none = ImpactThresholds::default();
+        assert!(!none.needs_stats());
+        assert!(none.shortfalls(&quiet, now()).is_empty());
+    }
+
+    #[test]
+    fn test_excluded_culprit() {
+        let thresholds = thresholds();
+        assert_eq!(
+            thresholds
+                .excluded_culprit("vendor/guzzle/Client.php")
+                .as_deref(),
+            Some("culprit `vendor/guzzle/Client.php` matches excluded pattern `^vendor/`")
+        );
+        assert!(
+            thresholds
+                .excluded_culprit("App\\Http\\HealthCheckController@index")
+                .is_some()
+        );
+        assert_eq!(thresholds.excluded_culprit("app/Orders.php"), None);
+
+        let invalid = serde_json::from_value::<ImpactThresholds>(json!({
+            "exclude_culprits": ["("]
+        }));
+        assert!(invalid.is_err());
+    }
+}
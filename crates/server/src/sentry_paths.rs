@@ -0,0 +1,395 @@
+//! Mapping of Sentry frame filenames to repository paths.
+//!
+//! Frames carry the path the code ran from: an absolute deploy path
+//! (`/var/www/app/releases/123/src/Foo.php`), a webpack or app URL, or a
+//! path relative to some build directory. The [`PathRewrite`] rules of a
+//! project mapping turn those into repository paths, and [`FrameResolver`]
+//! checks the result against the checkout, falling back to the longest
+//! existing suffix of the path.
+
+use std::path::{Component, Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// A rule rewriting Sentry frame paths, applied in order before the path is
+/// looked up in the repository.
+///
+/// ```json
+/// [
+///   { "prefix": "/var/www/html/", "replace": "" },
+///   { "regex": "^/srv/app/releases/\\d+/", "replace": "" },
+///   { "prefix": "webpack://shop/./", "replace": "frontend/" }
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PathRewrite {
+    /// Replace a leading `prefix` with `replace`; an empty `replace` strips it.
+    Prefix {
+        prefix: String,
+        #[serde(default)]
+        replace: String,
+    },
+    /// Replace the first match of `regex` with `replace`, which may refer to
+    /// capture groups as `$1`.
+    Regex {
+        #[serde(deserialize_with = "deserialize_regex")]
+        regex: String,
+        #[serde(default)]
+        replace: String,
+    },
+}
+
+/// Accept only patterns that compile, so a bad rule fails the mapping.
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)?;
+    Ok(pattern)
+}
+
+/// Where a frame's file is in the repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameLocation {
+    /// The file exists at `path`; `line` is the text of the frame's line in
+    /// the checkout, when the frame has a line number within the file.
+    Resolved { path: String, line: Option<String> },
+    /// No file matches; `tried` is the path after the rewrite rules.
+    Unresolved { tried: String },
+}
+
+enum Rule {
+    Prefix { prefix: String, replace: String },
+    Regex { regex: Regex, replace: String },
+}
+
+/// Resolves frame filenames against a checkout.
+pub struct FrameResolver {
+    rules: Vec<Rule>,
+    repo_path: PathBuf,
+}
+
+impl FrameResolver {
+    pub fn new(rewrites: &[PathRewrite], repo_path: impl AsRef<Path>) -> Self {
+        let rules = rewrites
+            .iter()
+            .filter_map(|rewrite| match rewrite {
+                PathRewrite::Prefix { prefix, replace } => Some(Rule::Prefix {
+                    prefix: prefix.clone(),
+                    replace: replace.clone(),
+                }),
+                PathRewrite::Regex { regex, replace } => Some(Rule::Regex {
+                    regex: Regex::new(regex).ok()?,
+                    replace: replace.clone(),
+                }),
+            })
+            .collect();
+        Self {
+            rules,
+            repo_path: repo_path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Find the file of a Sentry frame, trying its `absPath` and `filename`.
+    pub fn resolve(&self, frame: &Value) -> FrameLocation {
+        let mut candidates: Vec<&str> = Vec::new();
+        for field in ["absPath", "filename"] {
+            if let Some(path) = frame[field].as_str()
+                && !candidates.contains(&path)
+            {
+                candidates.push(path);
+            }
+        }
+        let mut tried = None;
+        for candidate in candidates {
+            let rewritten = self.rewrite(candidate);
+            if let Some(path) = self.find(&rewritten) {
+                let line = frame["lineNo"]
+                    .as_u64()
+                    .and_then(|number| self.line(&path, number));
+                return FrameLocation::Resolved { path, line };
+            }
+            tried.get_or_insert(rewritten);
+        }
+        FrameLocation::Unresolved {
+            tried: tried.unwrap_or_default(),
+        }
+    }
+
+    /// Apply the rewrite rules in order.
+    fn rewrite(&self, path: &str) -> String {
+        let mut path = path.to_string();
+        for rule in &self.rules {
+            path = match rule {
+                Rule::Prefix { prefix, replace } => match path.strip_prefix(prefix.as_str()) {
+                    Some(rest) => format!("{replace}{rest}"),
+                    None => path,
+                },
+                Rule::Regex { regex, replace } => {
+                    regex.replace(&path, replace.as_str()).into_owned()
+                }
+            };
+        }
+        path
+    }
+
+    /// The repository path of `path`, or of its longest suffix that exists.
+    /// Suffixes keep at least a directory and a file name, so a bare
+    /// `index.php` is not matched against any file of that name.
+    fn find(&self, path: &str) -> Option<String> {
+        let components = normalize(path)?;
+        let min_len = components.len().min(2);
+        (0..=components.len() - min_len)
+            .map(|start| components[start..].join("/"))
+            .find(|relative| self.repo_path.join(relative).is_file())
+    }
+
+    /// Text of line `number` (1-based) of a repository file.
+    fn line(&self, path: &str, number: u64) -> Option<String> {
+        let text = std::fs::read_to_string(self.repo_path.join(path)).ok()?;
+        let index = usize::try_from(number).ok()?.checked_sub(1)?;
+        text.lines().nth(index).map(String::from)
+    }
+}
+
+/// Path components of a frame path without URL scheme and host, `~/`, `./`
+/// and leading slashes; `None` for paths leaving the repository.
+fn normalize(path: &str) -> Option<Vec<&str>> {
+    let path = match path.split_once("://") {
+        // `webpack://shop/src/a.js`, `app:///src/a.js`, `https://cdn/js/a.js`
+        Some((_, rest)) => rest.split_once('/').map_or("", |(_, path)| path),
+        None => path,
+    };
+    let mut components = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => components.push(part.to_str()?),
+            Component::ParentDir => return None,
+            _ => {}
+        }
+    }
+    if components.first() == Some(&"~") {
+        components.remove(0);
+    }
+    (!components.is_empty()).then_some(components)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repo(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sentry-paths-{name}-{}", std::process::id()));
+        let files = [
+            (
+                "src/Repositories/OrderRepository.php",
+                "<?php\nclass OrderRepository\n{\n    return $order->customer->id;\n}\n",
+            ),
+            (
+                "frontend/src/cart/CartTotal.tsx",
+                "export function CartTotal() {}\n",
+            ),
+            (
+                "billing/parsers.py",
+                "def parse_quantity(raw):\n    return int(raw)\n",
+            ),
+            ("public/index.php", "<?php\n"),
+        ];
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn frame(abs_path: Option<&str>, filename: &str, line: u64) -> Value {
+        serde_json::json!({ "absPath": abs_path, "filename": filename, "lineNo": line })
+    }
+
+    #[test]
+    fn test_parse_rewrites() {
+        let rewrites: Vec<PathRewrite> = serde_json::from_str(
+            r#"[{"prefix": "/var/www/"}, {"regex": "^/srv/releases/\\d+/", "replace": "app/"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rewrites,
+            vec![
+                PathRewrite::Prefix {
+                    prefix: "/var/www/".into(),
+                    replace: String::new()
+                },
+                PathRewrite::Regex {
+                    regex: r"^/srv/releases/\d+/".into(),
+                    replace: "app/".into()
+                },
+            ]
+        );
+        let invalid = serde_json::from_str::<Vec<PathRewrite>>(r#"[{"regex": "("}]"#);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_resolve_frames() {
+        let dir = repo("frames");
+        let rewrites = vec![
+            PathRewrite::Regex {
+                regex: r"^/var/www/app/releases/\d+/app/".into(),
+                replace: "src/".into(),
+            },
+            PathRewrite::Prefix {
+                prefix: "webpack://shop/./".into(),
+                replace: "frontend/".into(),
+            },
+        ];
+        let resolver = FrameResolver::new(&rewrites, &dir);
+
+        let php = frame(
+            Some("/var/www/app/releases/123/app/Repositories/OrderRepository.php"),
+            "app/Repositories/OrderRepository.php",
+            4,
+        );
+        assert_eq!(
+            resolver.resolve(&php),
+            FrameLocation::Resolved {
+                path: "src/Repositories/OrderRepository.php".into(),
+                line: Some("    return $order->customer->id;".into()),
+            }
+        );
+
+        let js = frame(
+            Some("webpack://shop/./src/cart/CartTotal.tsx"),
+            "./src/cart/CartTotal.tsx",
+            99,
+        );
+        assert_eq!(
+            resolver.resolve(&js),
+            FrameLocation::Resolved {
+                path: "frontend/src/cart/CartTotal.tsx".into(),
+                line: None,
+            }
+        );
+
+        // Without a rule, the longest existing suffix is found.
+        let python = frame(Some("/app/billing/parsers.py"), "billing/parsers.py", 2);
+        let plain = FrameResolver::new(&[], &dir);
+        assert!(matches!(
+            plain.resolve(&python),
+            FrameLocation::Resolved { path, .. } if path == "billing/parsers.py"
+        ));
+
+        // A bare file name does not match a nested file.
+        assert_eq!(
+            plain.resolve(&frame(None, "/srv/index.php", 1)),
+            FrameLocation::Unresolved {
+                tried: "/srv/index.php".into()
+            }
+        );
+        assert!(matches!(
+            plain.resolve(&frame(None, "../../etc/passwd", 1)),
+            FrameLocation::Unresolved { .. }
+        ));
+        assert!(matches!(
+            plain.resolve(&frame(None, "app:///public/index.php", 1)),
+            FrameLocation::Resolved { path, .. } if path == "public/index.php"
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_rewrite_order() {
+        let dir = std::env::temp_dir().join(format!("sentry-paths-order-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src/Http")).unwrap();
+        std::fs::write(dir.join("src/Http/Kernel.php"), "<?php\n").unwrap();
+
+        let prefix = PathRewrite::Prefix {
+            prefix: "/srv/app/".into(),
+            replace: "legacy/".into(),
+        };
+        let regex = PathRewrite::Regex {
+            regex: r"^/srv/app/releases/(\d+)/".into(),
+            replace: "src/".into(),
+        };
+        let kernel = frame(None, "/srv/app/releases/42/Http/Kernel.php", 1);
+
+        // Rules apply in order: the regex only matches before the prefix
+        // rule has rewritten the path.
+        let regex_first = FrameResolver::new(&[regex.clone(), prefix.clone()], &dir);
+        assert_eq!(
+            regex_first.resolve(&kernel),
+            FrameLocation::Resolved {
+                path: "src/Http/Kernel.php".into(),
+                line: Some("<?php".into()),
+            }
+        );
+        let prefix_first = FrameResolver::new(&[prefix, regex], &dir);
+        assert_eq!(
+            prefix_first.resolve(&kernel),
+            FrameLocation::Unresolved {
+                tried: "legacy/releases/42/Http/Kernel.php".into()
+            }
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_url_paths() {
+        let dir = repo("urls");
+        let resolved =
+            |resolver: &FrameResolver, path: &str| match resolver.resolve(&frame(None, path, 1)) {
+                FrameLocation::Resolved { path, .. } => Some(path),
+                FrameLocation::Unresolved { .. } => None,
+            };
+
+        let plain = FrameResolver::new(&[], &dir);
+        for path in [
+            "app:///billing/parsers.py",
+            "webpack:///./billing/parsers.py",
+            "webpack://billing/./billing/parsers.py",
+            "~/billing/parsers.py",
+            "https://cdn.example.com/billing/parsers.py",
+        ] {
+            assert_eq!(
+                resolved(&plain, path).as_deref(),
+                Some("billing/parsers.py"),
+                "{path}"
+            );
+        }
+        // The bundle root is not in the repository root without a rule.
+        assert_eq!(resolved(&plain, "app:///src/cart/CartTotal.tsx"), None);
+
+        let rewrites = vec![
+            PathRewrite::Prefix {
+                prefix: "app:///".into(),
+                replace: "frontend/".into(),
+            },
+            PathRewrite::Prefix {
+                prefix: "~/".into(),
+                replace: "frontend/".into(),
+            },
+        ];
+        let frontend = FrameResolver::new(&rewrites, &dir);
+        for path in ["app:///src/cart/CartTotal.tsx", "~/src/cart/CartTotal.tsx"] {
+            assert_eq!(
+                resolved(&frontend, path).as_deref(),
+                Some("frontend/src/cart/CartTotal.tsx"),
+                "{path}"
+            );
+        }
+        assert_eq!(
+            frontend.resolve(&frame(None, "app:///src/cart/Missing.tsx", 1)),
+            FrameLocation::Unresolved {
+                tried: "frontend/src/cart/Missing.tsx".into()
+            }
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,605 @@
+//! Commits likely to have introduced a Sentry issue.
+//!
+//! The lines around the issue's in-app frames are traced with `git log -L`,
+//! keeping the last commits made before the issue was first seen. When the
+//! first release names a commit or tag of the checkout, history is read from
+//! that commit, where the line numbers of the event still apply.
+
+use std::path::Path;
+use std::process::Command;
+
+use chrono::{DateTime, Duration, SecondsFormat};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::sentry_api::entries;
+use crate::sentry_paths::{FrameLocation, FrameResolver};
+
+/// How far before the first-seen date the checkout's history must reach.
+pub const LOOKBACK_DAYS: i64 = 30;
+/// In-app frames traced, innermost first.
+const MAX_FRAMES: usize = 5;
+/// Lines traced on each side of a frame's line.
+const CONTEXT_LINES: u64 = 2;
+/// Commits kept from the history of each frame.
+const COMMITS_PER_FRAME: usize = 3;
+const MAX_SUSPECTS: usize = 5;
+
+/// A commit that last changed the lines of one or more frames before the
+/// issue was first seen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspectCommit {
+    pub hash: String,
+    pub author: String,
+    /// Author date, RFC 3339.
+    pub date: String,
+    pub subject: String,
+    /// Number of the pull or merge request the commit came from.
+    pub pull_request: Option<u64>,
+    /// `path:line` of the frames whose lines the commit changed.
+    pub frames: Vec<String>,
+}
+
+/// The date the history of a checkout must reach to trace an issue first
+/// seen at `first_seen`, for `git fetch --shallow-since`.
+pub fn history_since(first_seen: &str) -> Option<String> {
+    let first_seen = DateTime::parse_from_rfc3339(first_seen).ok()?;
+    let since = first_seen - Duration::days(LOOKBACK_DAYS);
+    Some(since.to_utc().to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Fetch the history of a shallow clone of `branch` back to `since`, so the
+/// commits that introduced an issue are in it. Failing only loses the
+/// suspect commits, so it is logged and the job goes on.
+pub fn deepen_history(repo_path: &Path, branch: &str, since: &str) {
+    let before = format!("--before={since}");
+    let covered = git(repo_path, &["rev-list", "-n", "1", &before, "HEAD"])
+        .is_some_and(|output| !output.trim().is_empty());
+    if covered {
+        return;
+    }
+
+    info!(since, "Deepening clone history");
+    let shallow_since = format!("--shallow-since={since}");
+    if git(
+        repo_path,
+        &["fetch", "-q", &shallow_since, "origin", branch],
+    )
+    .is_none()
+    {
+        warn!(since, branch, "git fetch --shallow-since failed");
+    }
+}
+
+/// Trace the in-app frames of `event` through the history of the checkout
+/// at `repo_path`, most frames touched first, then most recent first.
+pub fn find_suspect_commits(
+    repo_path: &Path,
+    event: &Value,
+    resolver: &FrameResolver,
+    first_seen: Option<&str>,
+    release: Option<&str>,
+) -> Vec<SuspectCommit> {
+    let revision = release
+        .and_then(|release| release_commit(repo_path, release))
+        .unwrap_or_else(|| "HEAD".into());
+
+    let mut suspects: Vec<SuspectCommit> = Vec::new();
+    for frame in in_app_frames(event) {
+        let FrameLocation::Resolved { path, .. } = resolver.resolve(frame) else {
+            continue;
+        };
+        let Some(number) = frame["lineNo"].as_u64() else {
+            continue;
+        };
+        let location = format!("{path}:{number}");
+        for commit in line_history(repo_path, &revision, &path, number, first_seen) {
+            match suspects.iter_mut().find(|s| s.hash == commit.hash) {
+                Some(suspect) => {
+                    if !suspect.frames.contains(&location) {
+                        suspect.frames.push(location.clone());
+                    }
+                }
+                None => suspects.push(SuspectCommit {
+                    frames: vec![location.clone()],
+                    ..commit
+                }),
+            }
+        }
+    }
+
+    suspects.sort_by(|a, b| {
+        b.frames
+            .len()
+            .cmp(&a.frames.len())
+            .then_with(|| b.date.cmp(&a.date))
+    });
+    suspects.truncate(MAX_SUSPECTS);
+    suspects
+}
+
+/// Render the "likely introducing commits" section of the fixer prompt;
+/// empty when no commit was found. Pull request links are built from the
+/// repository's clone URL.
+pub fn format_suspect_commits(
+    suspects: &[SuspectCommit],
+    first_seen: Option<&str>,
+    release: Option<&str>,
+    clone_url: &str,
+    vcs_platform: &str,
+) -> String {
+    if suspects.is_empty() {
+        return String::new();
+    }
+
+    let mut output = String::from("## Likely introducing commits\n\n");
+    match (first_seen, release) {
+        (Some(first_seen), Some(release)) => {
+            output.push_str(&format!("First seen {first_seen} in release `{release}`. "))
+        }
+        (Some(first_seen), None) => output.push_str(&format!("First seen {first_seen}. ")),
+        (None, Some(release)) => output.push_str(&format!("First seen in release `{release}`. ")),
+        (None, None) => {}
+    }
+    output.push_str(
+        "The last commits before then to change the lines of the application frames:\n\n",
+    );
+
+    let web_url = repository_web_url(clone_url);
+    for suspect in suspects {
+        let short = &suspect.hash[..suspect.hash.len().min(10)];
+        let date = suspect.date.get(..10).unwrap_or(&suspect.date);
+        output.push_str(&format!(
+            "- `{short}` {date} by {}: {}",
+            suspect.author, suspect.subject
+        ));
+        if let Some(number) = suspect.pull_request {
+            let path = match vcs_platform {
+                "gitlab" => format!("-/merge_requests/{number}"),
+                _ => format!("pull/{number}"),
+            };
+            output.push_str(&format!(" ({web_url}/{path})"));
+        }
+        let frames: Vec<String> = suspect
+            .frames
+            .iter()
+            .map(|frame| format!("`{frame}`"))
+            .collect();
+        output.push_str(&format!(" — {}\n", frames.join(", ")));
+    }
+    output
+}
+
+/// In-app frames with a line number, innermost first, of every exception
+/// of the event.
+fn in_app_frames(event: &Value) -> Vec<&Value> {
+    let mut frames: Vec<&Value> = Vec::new();
+    for entry in entries(event, "exception") {
+        let values = entry["values"].as_array().into_iter().flatten().rev();
+        for value in values {
+            let stack = value["stacktrace"]["frames"].as_array();
+            for frame in stack.into_iter().flatten().rev() {
+                let duplicate = frames.iter().any(|seen| {
+                    seen["filename"] == frame["filename"] && seen["lineNo"] == frame["lineNo"]
+                });
+                if frame["inApp"].as_bool() == Some(true) && frame["lineNo"].is_u64() && !duplicate
+                {
+                    frames.push(frame);
+                }
+            }
+        }
+    }
+    frames.truncate(MAX_FRAMES);
+    frames
+}
+
+/// The commit a release version names, when the checkout has it. Versions
+/// are often a commit hash, or end in one as `app@<hash>` or `1.2.0+<hash>`;
+/// others (`1.2.0`, `app@1.2.0`) may be a tag.
+fn release_commit(repo_path: &Path, release: &str) -> Option<String> {
+    let candidates = [
+        Some(release),
+        release.rsplit_once('@').map(|(_, rest)| rest),
+        release.rsplit_once('+').map(|(_, rest)| rest),
+    ];
+    let hashes = candidates.into_iter().flatten().filter(|candidate| {
+        (7..=40).contains(&candidate.len()) && candidate.chars().all(|c| c.is_ascii_hexdigit())
+    });
+    let tags = [
+        Some(release),
+        release.rsplit_once('@').map(|(_, rest)| rest),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|tag| format!("refs/tags/{tag}"));
+    hashes.map(String::from).chain(tags).find_map(|candidate| {
+        let revision = format!("{candidate}^{{commit}}");
+        git(repo_path, &["rev-parse", "--verify", "--quiet", &revision])
+            .map(|output| output.trim().to_string())
+    })
+}
+
+/// The last commits to change the lines around `number` of `path` at
+/// `revision`, made before `before`.
+fn line_history(
+    repo_path: &Path,
+    revision: &str,
+    path: &str,
+    number: u64,
+    before: Option<&str>,
+) -> Vec<SuspectCommit> {
+    let Some(content) = git(repo_path, &["show", &format!("{revision}:{path}")]) else {
+        return Vec::new();
+    };
+    let length = content.lines().count() as u64;
+    if number == 0 || number > length {
+        return Vec::new();
+    }
+    let start = number.saturating_sub(CONTEXT_LINES).max(1);
+    let end = (number + CONTEXT_LINES).min(length);
+
+    let range = format!("-L{start},{end}:{path}");
+    let limit = format!("-n{COMMITS_PER_FRAME}");
+    let before = before.map(|before| format!("--before={before}"));
+    let mut args = vec![
+        "log",
+        range.as_str(),
+        "-s",
+        limit.as_str(),
+        "--format=%x1e%H%x1f%an%x1f%aI%x1f%s%x1f%b",
+    ];
+    args.extend(before.as_deref());
+    args.push(revision);
+
+    let Some(output) = git(repo_path, &args) else {
+        return Vec::new();
+    };
+    output.split('\x1e').filter_map(parse_commit).collect()
+}
+
+fn parse_commit(record: &str) -> Option<SuspectCommit> {
+    let mut fields = record.split('\x1f');
+    let hash = fields.next()?.trim().to_string();
+    let author = fields.next()?.to_string();
+    let date = fields.next()?.to_string();
+    let subject = fields.next()?.to_string();
+    let body = fields.next().unwrap_or_default();
+    if hash.is_empty() {
+        return None;
+    }
+    Some(SuspectCommit {
+        pull_request: pull_request_number(&subject, body),
+        hash,
+        author,
+        date,
+        subject,
+        frames: Vec::new(),
+    })
+}
+
+/// The pull request of a squash (`Fix totals (#12)`) or merge
+/// (`Merge pull request #12 from …`) commit, or the merge request of a
+/// GitLab merge commit (`See merge request group/project!12`).
+fn pull_request_number(subject: &str, body: &str) -> Option<u64> {
+    let number = |text: &str| {
+        let digits: String = text.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    };
+    if let Some(rest) = subject.strip_prefix("Merge pull request #") {
+        return number(rest);
+    }
+    if let Some(start) = subject.rfind("(#")
+        && subject.ends_with(')')
+    {
+        return number(&subject[start + 2..]);
+    }
+    body.lines()
+        .find_map(|line| line.trim().strip_prefix("See merge request "))
+        .and_then(|reference| reference.rsplit_once('!'))
+        .and_then(|(_, rest)| number(rest))
+}
+
+/// Browser URL of a repository from its clone URL.
+fn repository_web_url(clone_url: &str) -> String {
+    let url = clone_url.trim_end_matches('/').trim_end_matches(".git");
+    match url.strip_prefix("git@") {
+        Some(rest) => format!("https://{}", rest.replacen(':', "/", 1)),
+        None => url.to_string(),
+    }
+}
+
+fn git(repo_path: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    /// A repository whose `src/Orders.php` line 4 was changed before and
+    /// after the issue was first seen on 2024-05-01.
+    fn repo(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sentry-suspects-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let commit = |lines: &str, date: &str, message: &str| {
+            std::fs::write(dir.join("src/Orders.php"), lines).unwrap();
+            let run = |args: &[&str]| {
+                let status = Command::new("git")
+                    .args(args)
+                    .current_dir(&dir)
+                    .env("GIT_AUTHOR_DATE", date)
+                    .env("GIT_COMMITTER_DATE", date)
+                    .status()
+                    .unwrap();
+                assert!(status.success());
+            };
+            run(&["add", "-A"]);
+            run(&[
+                "-c",
+                "user.name=Jane Doe",
+                "-c",
+                "user.email=jane@example.com",
+                "commit",
+                "-q",
+                "-m",
+                message,
+            ]);
+        };
+        Command::new("git")
+            .args(["init", "-q", "-b", "main"])
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        commit(
+            "<?php\nclass Orders\n{\n    return $order->id;\n}\n",
+            "2024-01-10T09:00:00Z",
+            "Add orders",
+        );
+        commit(
+            "<?php\nclass Orders\n{\n    return $order->customer->id;\n}\n",
+            "2024-04-20T09:00:00Z",
+            "Show customer of orders (#12)",
+        );
+        commit(
+            "<?php\nclass Orders\n{\n    return $order->customer?->id;\n}\n",
+            "2024-06-01T09:00:00Z",
+            "Guard missing customer",
+        );
+        dir
+    }
+
+    fn event() -> Value {
+        json!({
+            "entries": [{
+                "type": "exception",
+                "data": {"values": [{
+                    "type": "Error",
+                    "stacktrace": {"frames": [
+                        {"filename": "vendor/laravel/Router.php", "lineNo": 10, "inApp": false},
+                        {"filename": "/var/www/src/Orders.php", "lineNo": 4, "inApp": true}
+                    ]}
+                }]}
+            }]
+        })
+    }
+
+    #[test]
+    fn test_find_suspect_commits() {
+        let repo = repo("find");
+        let resolver = FrameResolver::new(&[], &repo);
+
+        let suspects = find_suspect_commits(
+            &repo,
+            &event(),
+            &resolver,
+            Some("2024-05-01T12:00:00Z"),
+            None,
+        );
+        let subjects: Vec<&str> = suspects.iter().map(|s| s.subject.as_str()).collect();
+        assert_eq!(subjects, ["Show customer of orders (#12)", "Add orders"]);
+        assert_eq!(suspects[0].author, "Jane Doe");
+        assert_eq!(suspects[0].pull_request, Some(12));
+        assert_eq!(suspects[0].frames, ["src/Orders.php:4"]);
+
+        // History is read from the release's commit.
+        let first = suspects[1].hash.clone();
+        let release = format!("shop@{}", &first[..12]);
+        let suspects = find_suspect_commits(&repo, &event(), &resolver, None, Some(&release));
+        assert_eq!(suspects.len(), 1);
+        assert_eq!(suspects[0].hash, first);
+
+        let section = format_suspect_commits(
+            &suspects,
+            Some("2024-05-01T12:00:00Z"),
+            Some(&release),
+            "git@github.com:acme/shop.git",
+            "github",
+        );
+        assert!(section.starts_with("## Likely introducing commits\n"));
+        assert!(section.contains(&format!("in release `{release}`")));
+        assert!(section.contains("2024-01-10 by Jane Doe: Add orders — `src/Orders.php:4`"));
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    /// Subjects of the suspect commits of `event()` in `repo`.
+    fn suspect_subjects(repo: &Path, event: &Value, release: Option<&str>) -> Vec<String> {
+        let resolver = FrameResolver::new(&[], repo);
+        find_suspect_commits(
+            repo,
+            event,
+            &resolver,
+            Some("2024-05-01T12:00:00Z"),
+            release,
+        )
+        .into_iter()
+        .map(|suspect| suspect.subject)
+        .collect()
+    }
+
+    #[test]
+    fn test_release_commit() {
+        let repo = repo("release");
+        let head = git(&repo, &["rev-parse", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string();
+        let first = git(&repo, &["rev-list", "--max-parents=0", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string();
+        git(&repo, &["tag", "1.0.0", &first]).unwrap();
+
+        assert_eq!(
+            release_commit(&repo, &head[..7]).as_deref(),
+            Some(head.as_str())
+        );
+        assert_eq!(
+            release_commit(&repo, &format!("2.0.0+{}", &head[..10])).as_deref(),
+            Some(head.as_str())
+        );
+        assert_eq!(
+            release_commit(&repo, "shop@1.0.0").as_deref(),
+            Some(first.as_str())
+        );
+        assert_eq!(
+            release_commit(&repo, "1.0.0").as_deref(),
+            Some(first.as_str())
+        );
+
+        // Missing tags and commits fall back to the history of HEAD.
+        for missing in ["shop@1.1.0", "1.1.0", "shop@deadbeef1234", "main"] {
+            assert_eq!(release_commit(&repo, missing), None, "{missing}");
+            assert_eq!(
+                suspect_subjects(&repo, &event(), Some(missing)),
+                ["Show customer of orders (#12)", "Add orders"],
+                "{missing}"
+            );
+        }
+        assert_eq!(
+            suspect_subjects(&repo, &event(), Some("shop@1.0.0")),
+            ["Add orders"]
+        );
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn test_line_history_bounds() {
+        let repo = repo("bounds");
+        let history = |number| line_history(&repo, "HEAD", "src/Orders.php", number, None);
+
+        // The last line is traced with the context clamped to the file.
+        let last = history(5);
+        assert_eq!(last.len(), 3);
+        assert!(history(6).is_empty());
+        assert!(history(99).is_empty());
+        assert!(history(0).is_empty());
+        assert!(line_history(&repo, "HEAD", "src/Missing.php", 1, None).is_empty());
+
+        let mut past_end = event();
+        past_end["entries"][0]["data"]["values"][0]["stacktrace"]["frames"][1]["lineNo"] =
+            json!(99);
+        assert!(suspect_subjects(&repo, &past_end, None).is_empty());
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn test_deepen_shallow_clone() {
+        let origin = repo("origin");
+        let clone = origin.with_file_name(format!("sentry-suspects-clone-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&clone);
+        let url = format!("file://{}", origin.display());
+        let status = Command::new("git")
+            .args(["clone", "-q", "--depth", "1", "--branch", "main", &url])
+            .arg(&clone)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let commits = || {
+            git(&clone, &["rev-list", "--count", "HEAD"])
+                .unwrap()
+                .trim()
+                .parse::<u32>()
+                .unwrap()
+        };
+        assert_eq!(commits(), 1);
+        assert!(suspect_subjects(&clone, &event(), None).is_empty());
+
+        deepen_history(&clone, "main", "2024-04-01T00:00:00Z");
+        assert_eq!(commits(), 2);
+        assert_eq!(
+            suspect_subjects(&clone, &event(), None),
+            ["Show customer of orders (#12)"]
+        );
+
+        let since = history_since("2024-02-01T00:00:00Z").unwrap();
+        deepen_history(&clone, "main", &since);
+        assert_eq!(commits(), 3);
+        assert_eq!(
+            suspect_subjects(&clone, &event(), None),
+            ["Show customer of orders (#12)", "Add orders"]
+        );
+
+        // A branch the remote does not have only logs a warning.
+        deepen_history(&clone, "missing", "2020-01-01T00:00:00Z");
+        assert_eq!(commits(), 3);
+
+        std::fs::remove_dir_all(&origin).unwrap();
+        std::fs::remove_dir_all(&clone).unwrap();
+    }
+
+    #[test]
+    fn test_pull_request_links() {
+        assert_eq!(pull_request_number("Fix totals (#12)", ""), Some(12));
+        assert_eq!(
+            pull_request_number("Merge pull request #7 from acme/fix", ""),
+            Some(7)
+        );
+        assert_eq!(
+            pull_request_number(
+                "Merge branch 'fix' into 'main'",
+                "Fix totals\n\nSee merge request acme/shop!31"
+            ),
+            Some(31)
+        );
+        assert_eq!(pull_request_number("Fix #12 totals", ""), None);
+
+        let suspect = SuspectCommit {
+            hash: "0123456789abcdef".into(),
+            author: "Jane Doe".into(),
+            date: "2024-04-20T09:00:00+00:00".into(),
+            subject: "Fix totals".into(),
+            pull_request: Some(31),
+            frames: vec!["src/Orders.php:4".into()],
+        };
+        let section = format_suspect_commits(
+            &[suspect],
+            None,
+            None,
+            "https://gitlab.example.com/acme/shop.git",
+            "gitlab",
+        );
+        assert!(section.contains(
+            "- `0123456789` 2024-04-20 by Jane Doe: Fix totals (https://gitlab.example.com/acme/shop/-/merge_requests/31)"
+        ));
+        assert_eq!(format_suspect_commits(&[], None, None, "", "github"), "");
+        assert_eq!(
+            history_since("2024-05-01T12:00:00.123Z").as_deref(),
+            Some("2024-04-01T12:00:00Z")
+        );
+    }
+}
@@ -13,9 +13,10 @@ use tracing::{info, warn};
 
 use crate::jira;
 use crate::payload::{JiraTicketPayload, SentryFixPayload};
+use crate::sentry_followup::fix_branch;
 
 use super::github::fetch_github_pr_payload;
-use super::{AppError, AppState, branch_exists_on_platform};
+use super::{AppError, AppState, attach_jira_ticket, branch_exists_on_platform};
 
 // -- Queue management --
 
@@ -83,6 +84,28 @@ pub(super) async fn retry_handler(
     }
 }
 
+pub(super) async fn report_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    if !state.verify_api_key(&headers) {
+        warn!("Invalid API key for /api/reports");
+        return Err(AppError::Unauthorized);
+    }
+    let report = state.queue.get_report(&id).await.map_err(AppError::Redis)?;
+    match report {
+        Some(report) => Ok((
+            StatusCode::OK,
+            Json(serde_json::to_value(report).unwrap_or_default()),
+        )),
+        None => Ok((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "status": "not_found", "id": id })),
+        )),
+    }
+}
+
 // -- Review endpoints --
 
 #[derive(Deserialize)]
@@ -115,6 +138,7 @@ pub(super) async fn queue_github_review_handler(
     if let Some(action) = &req.action {
         payload.action = action.clone();
     }
+    attach_jira_ticket(&state, &mut payload).await;
 
     let job_id = state.queue.push(payload).await.map_err(AppError::Redis)?;
     info!(job_id = %job_id, repo = %req.repo, pr = %req.pr, "Queued GitHub review via API");
@@ -161,7 +185,7 @@ pub(super) async fn queue_sentry_fix_handler(
 
     let mapping = find_sentry_mapping(&state, &req.project)?;
     let (issue, short_id) = fetch_sentry_issue_details(&state, &req).await?;
-    let branch_name = format!("sentry-fix/{}", short_id.to_lowercase());
+    let branch_name = fix_branch(&short_id);
 
     if branch_exists_on_platform(
         &state,
@@ -239,6 +263,8 @@ fn build_sentry_api_payload(
         target_branch: mapping.target_branch.clone(),
         vcs_platform: mapping.vcs_platform.clone(),
         vcs_project: mapping.vcs_project.clone(),
+        path_rewrites: mapping.path_rewrites.clone(),
+        sentry_installation: None,
     }
 }
 
@@ -264,7 +290,7 @@ pub(super) struct QueueJiraFixRequest {
 }
 
 fn default_jira_url() -> String {
-    "https://globalcomix.atlassian.net".into()
+    jira::DEFAULT_JIRA_URL.into()
 }
 
 fn find_jira_mapping<'a>(
@@ -337,24 +363,9 @@ async fn fetch_jira_issue(
         }
     };
 
-    let client = reqwest::Client::new();
-    let url = format!(
-        "{}/rest/api/3/issue/{}",
-        req.jira_url.trim_end_matches('/'),
-        req.issue_key
-    );
-    client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", jira_token))
-        .header("Accept", "application/json")
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("Failed to fetch Jira issue: {e}")))?
-        .error_for_status()
-        .map_err(|e| AppError::Internal(format!("Jira API error: {e}")))?
-        .json()
+    jira::fetch_issue(&req.jira_url, &req.issue_key, &jira_token)
         .await
-        .map_err(|e| AppError::Internal(format!("Failed to parse Jira response: {e}")))
+        .map_err(|e| AppError::Internal(format!("Failed to fetch Jira issue: {e}")))
 }
 
 fn build_jira_api_payload(
@@ -10,12 +10,18 @@ use axum::{
 };
 use tracing::{debug, error, info, warn};
 
-use crate::github::{PullRequestEvent, verify_signature};
+use crate::checks::{fetch_failed_check, is_lint_fix_commit};
+use crate::github::{CheckRunEvent, PullRequestEvent, verify_signature};
 use crate::payload::ReviewPayload;
+use crate::sentry::SentryProjectMapping as SentryMapping;
+use crate::sentry_api::SentryClient;
+use crate::sentry_followup::{resolve_merged_fix, short_id_from_branch};
 
-use super::{AppError, AppState, WebhookResponse, ignored, queued};
+use super::{AppError, AppState, WebhookResponse, attach_jira_ticket, ignored, queued, skipped};
 
-/// GitHub webhook handler for pull_request events.
+/// GitHub webhook handler for pull_request and check_run events.
+///
+/// Merged Sentry fix PRs resolve their issue; other PR events queue reviews.
 pub(super) async fn github_webhook_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -23,30 +29,145 @@ pub(super) async fn github_webhook_handler(
 ) -> Result<(StatusCode, Json<WebhookResponse>), AppError> {
     verify_github_signature(&state.webhook_secret, &body, &headers)?;
     let event_type = extract_event_type(&headers);
-    if event_type != "pull_request" {
-        return Ok(ignored(format!("Unsupported event type: {event_type}")));
+    match event_type {
+        "pull_request" => {}
+        "check_run" => return handle_check_run(&state, &body).await,
+        _ => return Ok(ignored(format!("Unsupported event type: {event_type}"))),
     }
-    let event = parse_pull_request_event(&body)?;
+    let event: PullRequestEvent = parse_event(&body)?;
     log_received_event(&event);
+    if event.is_merged() {
+        return handle_merged_pull_request(&state, &event).await;
+    }
     if !event.should_review() {
         return Ok(ignored("Event does not require review"));
     }
-    if state
-        .ignored_repos
-        .iter()
-        .any(|r| r.eq_ignore_ascii_case(&event.repository.full_name))
-    {
+    if is_repo_ignored(&state.ignored_repos, &event.repository.full_name) {
         return Ok(ignored(format!(
             "Repo {} is ignored",
             event.repository.full_name
         )));
     }
-    let payload = ReviewPayload::from(&event);
+    let mut payload = ReviewPayload::from(&event);
+    attach_jira_ticket(&state, &mut payload).await;
     let job_id = state.queue.push(payload).await.map_err(AppError::Redis)?;
     info!(job_id = %job_id, "Queued GitHub review job");
     Ok(queued(job_id))
 }
 
+/// Queue a lint-fix job for a failed run of one of the configured lint
+/// checks, carrying the check's trimmed log.
+async fn handle_check_run(
+    state: &AppState,
+    body: &Bytes,
+) -> Result<(StatusCode, Json<WebhookResponse>), AppError> {
+    let event: CheckRunEvent = parse_event(body)?;
+    let repo = &event.repository.full_name;
+    let Some(pr) = event.lint_fix_pr(&state.lint_fix_checks) else {
+        return Ok(ignored("Check run does not need a lint fix"));
+    };
+    info!(repo = %repo, pr, check = %event.check_run.name, "Received failed lint check");
+    if is_repo_ignored(&state.ignored_repos, repo) {
+        return Ok(ignored(format!("Repo {repo} is ignored")));
+    }
+    let token = state
+        .github_token
+        .as_ref()
+        .ok_or_else(|| AppError::Internal("GitHub token not configured".into()))?;
+
+    let already_fixed = is_lint_fix_commit(repo, &event.check_run.head_sha, token)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to fetch head commit: {e}")))?;
+    if already_fixed {
+        return Ok(skipped("Head commit is already a lint fix"));
+    }
+
+    let mut payload = fetch_github_pr_payload(repo, pr, token)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to fetch PR from GitHub: {e}")))?;
+    if !state.is_author_allowed(&payload.author) {
+        return Ok(ignored(format!("Author {} is not allowed", payload.author)));
+    }
+    let failed_check = fetch_failed_check(&event, token)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to fetch check log: {e}")))?;
+    payload.action = "lint_fix".into();
+    payload.failed_check = Some(failed_check);
+
+    let job_id = state.queue.push(payload).await.map_err(AppError::Redis)?;
+    info!(job_id = %job_id, "Queued lint-fix job");
+    Ok(queued(job_id))
+}
+
+/// Resolve the Sentry issue of a merged fix PR in the next release.
+async fn handle_merged_pull_request(
+    state: &AppState,
+    event: &PullRequestEvent,
+) -> Result<(StatusCode, Json<WebhookResponse>), AppError> {
+    let client = match (&state.sentry_auth_token, &state.sentry_organization) {
+        (Some(token), Some(organization)) => Some(
+            SentryClient::new(organization, token)
+                .map_err(|e| AppError::Internal(format!("Failed to create Sentry client: {e}")))?,
+        ),
+        _ => None,
+    };
+    Ok(resolve_merged_pull_request(
+        event,
+        &state.sentry_project_mappings,
+        &state.ignored_repos,
+        client.as_ref(),
+    )
+    .await)
+}
+
+/// Resolve the issue of a merged fix PR when its repository is mapped to a
+/// Sentry project and not ignored, so a branch name alone cannot resolve
+/// issues of other projects. Sentry API failures are logged and skip the
+/// event, as GitHub does not retry a failed delivery.
+async fn resolve_merged_pull_request(
+    event: &PullRequestEvent,
+    mappings: &[SentryMapping],
+    ignored_repos: &[String],
+    client: Option<&SentryClient>,
+) -> (StatusCode, Json<WebhookResponse>) {
+    let repo = &event.repository.full_name;
+    let Some(short_id) = short_id_from_branch(&event.pull_request.head.ref_name) else {
+        return ignored("Merged PR is not a Sentry fix");
+    };
+    if is_repo_ignored(ignored_repos, repo) {
+        return ignored(format!("Repo {repo} is ignored"));
+    }
+    let mapped = mappings
+        .iter()
+        .any(|m| m.vcs_platform == "github" && m.vcs_project.eq_ignore_ascii_case(repo));
+    if !mapped {
+        return ignored(format!("Repo {repo} has no Sentry project mapping"));
+    }
+    let Some(client) = client else {
+        return ignored("Sentry API not configured");
+    };
+
+    match resolve_merged_fix(client, &short_id).await {
+        Ok(Some(_)) => super::resolved(format!(
+            "Resolved Sentry issue {short_id} in the next release"
+        )),
+        Ok(None) => skipped(format!("Sentry issue {short_id} is already resolved")),
+        Err(e) => {
+            warn!(
+                short_id = %short_id,
+                repo = %repo,
+                error = format!("{e:#}"),
+                "Failed to resolve merged Sentry fix"
+            );
+            skipped(format!("Failed to resolve Sentry issue {short_id}"))
+        }
+    }
+}
+
+fn is_repo_ignored(ignored_repos: &[String], repo: &str) -> bool {
+    ignored_repos.iter().any(|r| r.eq_ignore_ascii_case(repo))
+}
+
 fn verify_github_signature(
     secret: &str,
     body: &Bytes,
@@ -70,7 +191,7 @@ fn extract_event_type(headers: &HeaderMap) -> &str {
         .unwrap_or("")
 }
 
-fn parse_pull_request_event(body: &Bytes) -> Result<PullRequestEvent, AppError> {
+fn parse_event<T: serde::de::DeserializeOwned>(body: &Bytes) -> Result<T, AppError> {
     if let Ok(s) = std::str::from_utf8(body) {
         debug!(body = %s, "Raw GitHub webhook body");
     }
@@ -125,5 +246,164 @@ pub(crate) async fn fetch_github_pr_payload(
         action: "open".to_string(),
         platform: "github".to_string(),
         trigger_comment: None,
+        ticket: None,
+        known_issues: Vec::new(),
+        last_reviewed_sha: None,
+        failed_check: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use axum::Router;
+    use axum::http::{Method, Uri};
+    use serde_json::{Value, json};
+
+    /// Requests received by the fake Sentry API.
+    type Requests = Arc<Mutex<Vec<(Method, String)>>>;
+
+    /// Serve a fake Sentry API on a local port, answering with `status` and
+    /// an unresolved issue, and recording every request.
+    async fn fake_sentry(status: StatusCode) -> (SentryClient, Requests) {
+        let requests: Requests = Arc::default();
+        let recorded = requests.clone();
+        let app = Router::new().fallback(move |method: Method, uri: Uri| async move {
+            recorded
+                .lock()
+                .unwrap()
+                .push((method, uri.path().to_string()));
+            let issue = json!({ "id": "4242", "shortId": "WEB-7", "status": "unresolved" });
+            (status, axum::Json(issue))
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = SentryClient::new("acme", "token")
+            .unwrap()
+            .with_base_url(&url);
+        (client, requests)
+    }
+
+    fn merged_event(repo: &str, branch: &str) -> PullRequestEvent {
+        let user = json!({ "id": 1, "login": "claude-agent" });
+        serde_json::from_value(json!({
+            "action": "closed",
+            "number": 12,
+            "pull_request": {
+                "id": 1,
+                "number": 12,
+                "title": "Fix WEB-7",
+                "body": null,
+                "state": "closed",
+                "user": user,
+                "head": { "ref": branch, "sha": "abc123", "repo": null },
+                "base": { "ref": "main", "sha": "def456", "repo": null },
+                "html_url": format!("https://github.com/{repo}/pull/12"),
+                "merged": true
+            },
+            "repository": {
+                "id": 1,
+                "name": "web",
+                "full_name": repo,
+                "clone_url": format!("https://github.com/{repo}.git"),
+                "html_url": format!("https://github.com/{repo}"),
+                "default_branch": "main"
+            },
+            "sender": user
+        }))
+        .unwrap()
+    }
+
+    fn mappings() -> Vec<SentryMapping> {
+        serde_json::from_value(json!([{
+            "sentry_project": "web",
+            "clone_url": "https://github.com/acme/web.git",
+            "vcs_platform": "github",
+            "vcs_project": "acme/web",
+            "target_branch": "main"
+        }]))
+        .unwrap()
+    }
+
+    async fn respond(
+        event: &PullRequestEvent,
+        ignored_repos: &[String],
+        client: Option<&SentryClient>,
+    ) -> (StatusCode, Value) {
+        let (status, Json(response)) =
+            resolve_merged_pull_request(event, &mappings(), ignored_repos, client).await;
+        (status, serde_json::to_value(response).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_merged_fix_resolves_issue() {
+        let (client, requests) = fake_sentry(StatusCode::OK).await;
+        let event = merged_event("Acme/Web", "sentry-fix/web-7");
+
+        let (status, response) = respond(&event, &[], Some(&client)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response,
+            json!({ "status": "resolved", "message": "Resolved Sentry issue WEB-7 in the next release" })
+        );
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            *requests,
+            [
+                (Method::GET, "/organizations/acme/issues/WEB-7/".to_string()),
+                (Method::PUT, "/organizations/acme/issues/4242/".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merged_fix_needs_mapped_repo() {
+        let (client, requests) = fake_sentry(StatusCode::OK).await;
+
+        let cases = [
+            (
+                merged_event("acme/web", "feature/web-7"),
+                "Merged PR is not a Sentry fix",
+            ),
+            (
+                merged_event("other/web", "sentry-fix/web-7"),
+                "Repo other/web has no Sentry project mapping",
+            ),
+        ];
+        for (event, message) in &cases {
+            let (_, response) = respond(event, &[], Some(&client)).await;
+            assert_eq!(response, json!({ "status": "ignored", "message": message }));
+        }
+
+        let event = merged_event("acme/web", "sentry-fix/web-7");
+        let (_, response) = respond(&event, &["ACME/web".to_string()], Some(&client)).await;
+        assert_eq!(
+            response,
+            json!({ "status": "ignored", "message": "Repo acme/web is ignored" })
+        );
+        let (_, response) = respond(&event, &[], None).await;
+        assert_eq!(
+            response,
+            json!({ "status": "ignored", "message": "Sentry API not configured" })
+        );
+
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merged_fix_sentry_error_skips() {
+        let (client, requests) = fake_sentry(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let event = merged_event("acme/web", "sentry-fix/web-7");
+
+        let (status, response) = respond(&event, &[], Some(&client)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response,
+            json!({ "status": "skipped", "message": "Failed to resolve Sentry issue WEB-7" })
+        );
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::sync::Arc;
 
+use anyhow::Context;
 use axum::{
     Json, Router,
     http::{HeaderMap, StatusCode},
@@ -9,9 +10,11 @@ use axum::{
     routing::{get, post},
 };
 use serde::Serialize;
+use tracing::{debug, info, warn};
 
 use crate::jira::JiraProjectMapping;
 use crate::jira_token::JiraTokenManager;
+use crate::payload::{LinkedTicket, ReviewPayload};
 use crate::queue::Queue;
 use crate::sentry::SentryProjectMapping as SentryMapping;
 
@@ -21,6 +24,8 @@ mod jira;
 mod sentry;
 mod tokens;
 
+pub use sentry::reevaluate_parked_issues;
+
 /// Application state shared across handlers.
 #[derive(Clone)]
 pub struct AppState {
@@ -50,6 +55,8 @@ pub struct AppState {
     pub allowed_authors: Vec<String>,
     /// Repos to ignore for automatic review (e.g. "Globalcomix/gcmobileapps")
     pub ignored_repos: Vec<String>,
+    /// Check run names whose failures trigger lint-fix jobs (empty = none)
+    pub lint_fix_checks: Vec<String>,
 }
 
 impl AppState {
@@ -85,6 +92,7 @@ pub fn router(state: AppState) -> Router {
         .route("/api/stats", get(api::queue_stats_handler))
         .route("/api/failed", get(api::list_failed_handler))
         .route("/api/retry/{id}", post(api::retry_handler))
+        .route("/api/reports/{id}", get(api::report_handler))
         .route("/api/review/github", post(api::queue_github_review_handler))
         .route("/api/sentry-fix", post(api::queue_sentry_fix_handler))
         .route("/api/jira-fix", post(api::queue_jira_fix_handler))
@@ -138,6 +146,10 @@ pub(crate) fn queued_with_message(
     )
 }
 
+pub(crate) fn resolved(message: impl Into<String>) -> (StatusCode, Json<WebhookResponse>) {
+    ok_with_message("resolved", message.into())
+}
+
 pub(crate) fn skipped(message: impl Into<String>) -> (StatusCode, Json<WebhookResponse>) {
     ok_with_message("skipped", message.into())
 }
@@ -153,6 +165,76 @@ fn ok_with_message(status: &'static str, message: String) -> (StatusCode, Json<W
     )
 }
 
+/// Attach the Jira ticket referenced by a PR's title, branch or description.
+///
+/// Only keys of mapped Jira projects are considered. If the ticket cannot be
+/// fetched, the key is still attached so the review can point at it.
+pub(crate) async fn attach_jira_ticket(state: &AppState, payload: &mut ReviewPayload) {
+    let projects: Vec<&str> = state
+        .jira_project_mappings
+        .iter()
+        .map(|m| m.jira_project.as_str())
+        .collect();
+    let text = [
+        payload.title.as_str(),
+        payload.source_branch.as_str(),
+        payload.description.as_deref().unwrap_or(""),
+    ]
+    .join("\n");
+    let Some(key) = crate::jira::find_issue_keys(&text, &projects)
+        .into_iter()
+        .next()
+    else {
+        debug!(project = %payload.project, "No Jira key found in PR");
+        return;
+    };
+
+    let base_url = state
+        .jira_project_mappings
+        .iter()
+        .find(|m| key.starts_with(&format!("{}-", m.jira_project.to_ascii_uppercase())))
+        .map(|m| m.jira_url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| crate::jira::DEFAULT_JIRA_URL.into());
+
+    let mut ticket = LinkedTicket {
+        key: key.clone(),
+        web_url: format!("{base_url}/browse/{key}"),
+        summary: None,
+        description: None,
+        issue_type: None,
+        status: None,
+    };
+
+    match fetch_ticket_fields(state, &base_url, &key).await {
+        Ok(fields) => {
+            ticket.summary = fields["summary"].as_str().map(String::from);
+            ticket.description = fields
+                .get("description")
+                .filter(|d| !d.is_null())
+                .map(crate::jira::extract_text_from_adf);
+            ticket.issue_type = fields["issuetype"]["name"].as_str().map(String::from);
+            ticket.status = fields["status"]["name"].as_str().map(String::from);
+            info!(ticket = %key, "Linked Jira ticket to review");
+        }
+        Err(e) => warn!(ticket = %key, error = %e, "Failed to fetch linked Jira ticket"),
+    }
+    payload.ticket = Some(ticket);
+}
+
+async fn fetch_ticket_fields(
+    state: &AppState,
+    base_url: &str,
+    key: &str,
+) -> anyhow::Result<serde_json::Value> {
+    let manager = state
+        .jira_token_manager
+        .as_ref()
+        .context("Jira integration not configured")?;
+    let token = manager.get_access_token().await?;
+    let issue = crate::jira::fetch_issue(base_url, key, &token).await?;
+    Ok(issue["fields"].clone())
+}
+
 /// Check if a branch exists on the VCS platform.
 pub(crate) async fn branch_exists_on_platform(
     state: &AppState,
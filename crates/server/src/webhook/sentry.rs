@@ -1,6 +1,7 @@
 //! Sentry webhook handler.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{
     Json,
@@ -8,12 +9,21 @@ use axum::{
     extract::State,
     http::{HeaderMap, StatusCode},
 };
+use chrono::{DateTime, Utc};
+use tokio::time::MissedTickBehavior;
 use tracing::{debug, error, info, warn};
 
 use crate::payload::SentryFixPayload;
-use crate::sentry::{self, SentryWebhookEvent};
+use crate::queue::ParkedSentryIssue;
+use crate::sentry::{self, Issue, SentryWebhookEvent};
+use crate::sentry_api::SentryClient;
+use crate::sentry_followup::fix_branch;
+use crate::sentry_impact::ImpactThresholds;
 
-use super::{AppError, AppState, WebhookResponse, branch_exists_on_platform, queued, skipped};
+use super::{
+    AppError, AppState, WebhookResponse, branch_exists_on_platform, queued, queued_with_message,
+    skipped,
+};
 
 /// Sentry webhook handler.
 pub(super) async fn sentry_webhook_handler(
@@ -54,36 +64,86 @@ pub(super) async fn sentry_webhook_handler(
     );
 
     if !event.should_fix() {
-        debug!("Sentry event does not require fixing");
-        return Ok(super::ignored("Event does not require fixing"));
+        let parked = event.should_reevaluate()
+            && state
+                .queue
+                .is_sentry_issue_parked(&event.issue().id)
+                .await
+                .map_err(AppError::Redis)?;
+        if !parked {
+            debug!("Sentry event does not require fixing");
+            return Ok(super::ignored("Event does not require fixing"));
+        }
+        info!(issue = %event.issue().short_id, "Re-evaluating parked Sentry issue");
     }
-    queue_sentry_webhook_fix(&state, &event).await
+    queue_sentry_fix(&state, event.issue(), &event.installation.uuid).await
 }
 
-/// Find mapping, check branch, build payload, and queue.
-async fn queue_sentry_webhook_fix(
+/// Re-evaluate the parked Sentry issues every `period`, so an issue that
+/// gains impact is fixed even when Sentry sends no further webhook for it.
+pub async fn reevaluate_parked_issues(state: Arc<AppState>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let parked = match state.queue.parked_sentry_issues().await {
+            Ok(parked) => parked,
+            Err(e) => {
+                error!(error = %e, "Failed to list parked Sentry issues");
+                continue;
+            }
+        };
+        debug!(count = parked.len(), "Re-evaluating parked Sentry issues");
+        for ParkedSentryIssue {
+            issue,
+            installation,
+            ..
+        } in parked
+        {
+            match queue_sentry_fix(&state, &issue, &installation).await {
+                Ok((_, Json(response))) => info!(
+                    issue = %issue.short_id,
+                    status = %response.status,
+                    message = response.message.as_deref().unwrap_or_default(),
+                    "Re-evaluated parked Sentry issue"
+                ),
+                Err(e) => {
+                    warn!(issue = %issue.short_id, error = ?e, "Failed to re-evaluate parked Sentry issue")
+                }
+            }
+        }
+    }
+}
+
+/// Find mapping, check branch and impact, build payload, and queue.
+async fn queue_sentry_fix(
     state: &AppState,
-    event: &SentryWebhookEvent,
+    issue: &Issue,
+    installation: &str,
 ) -> Result<(StatusCode, Json<WebhookResponse>), AppError> {
-    let issue = event.issue();
-    let mapping = state
+    let Some(mapping) = state
         .sentry_project_mappings
         .iter()
         .find(|m| m.sentry_project == issue.project.slug)
-        .ok_or_else(|| {
-            warn!(project = %issue.project.slug, "No project mapping for Sentry project");
-            AppError::BadRequest(format!(
-                "No project mapping for Sentry project: {}",
-                issue.project.slug
-            ))
-        })?;
+    else {
+        warn!(project = %issue.project.slug, "No project mapping for Sentry project");
+        state
+            .queue
+            .unpark_sentry_issue(&issue.id)
+            .await
+            .map_err(AppError::Redis)?;
+        return Err(AppError::BadRequest(format!(
+            "No project mapping for Sentry project: {}",
+            issue.project.slug
+        )));
+    };
 
     let organization = state
         .sentry_organization
         .as_ref()
         .ok_or_else(|| AppError::Internal("SENTRY_ORGANIZATION not configured".into()))?;
 
-    let branch_name = format!("sentry-fix/{}", issue.short_id.to_lowercase());
+    let branch_name = fix_branch(&issue.short_id);
     if branch_exists_on_platform(
         state,
         &mapping.vcs_platform,
@@ -93,18 +153,130 @@ async fn queue_sentry_webhook_fix(
     .await?
     {
         info!(branch = %branch_name, issue = %issue.short_id, "Fix branch already exists, skipping");
+        state
+            .queue
+            .unpark_sentry_issue(&issue.id)
+            .await
+            .map_err(AppError::Redis)?;
         return Ok(skipped(format!("Branch {} already exists", branch_name)));
     }
 
-    let payload = build_sentry_webhook_payload(issue, organization, mapping);
+    let client = sentry_client(state, organization)?;
+    let impact = check_impact(&mapping.impact, issue, client.as_ref(), Utc::now()).await?;
+    match &impact {
+        Impact::Sufficient => {}
+        // Excluded culprits are never fixed, so they are not parked either.
+        Impact::Excluded(reason) => {
+            info!(issue = %issue.short_id, reason = %reason, "Sentry issue culprit excluded, skipping");
+            state
+                .queue
+                .unpark_sentry_issue(&issue.id)
+                .await
+                .map_err(AppError::Redis)?;
+        }
+        Impact::Below(shortfalls) => {
+            info!(issue = %issue.short_id, reasons = ?shortfalls, "Sentry issue below impact thresholds, parking");
+            state
+                .queue
+                .park_sentry_issue(issue, installation, shortfalls)
+                .await
+                .map_err(AppError::Redis)?;
+        }
+    }
+    if let Some(response) = impact.skipped() {
+        return Ok(response);
+    }
+
+    let payload = build_sentry_webhook_payload(issue, organization, installation, mapping);
     let job_id = state.queue.push(payload).await.map_err(AppError::Redis)?;
-    info!(job_id = %job_id, issue = %issue.short_id, "Queued Sentry fix job");
+    let was_parked = state
+        .queue
+        .unpark_sentry_issue(&issue.id)
+        .await
+        .map_err(AppError::Redis)?;
+    info!(job_id = %job_id, issue = %issue.short_id, was_parked, "Queued Sentry fix job");
+    if was_parked {
+        return Ok(queued_with_message(
+            job_id,
+            format!(
+                "Parked issue {} now meets the impact thresholds",
+                issue.short_id
+            ),
+        ));
+    }
     Ok(queued(job_id))
 }
 
+/// A Sentry API client, when the auth token is configured.
+fn sentry_client(state: &AppState, organization: &str) -> Result<Option<SentryClient>, AppError> {
+    state
+        .sentry_auth_token
+        .as_ref()
+        .map(|token| {
+            SentryClient::new(organization, token)
+                .map_err(|e| AppError::Internal(format!("Failed to create Sentry client: {e}")))
+        })
+        .transpose()
+}
+
+/// How an issue compares to its mapping's impact thresholds.
+#[derive(Debug, PartialEq, Eq)]
+enum Impact {
+    /// Enough impact for a fix job.
+    Sufficient,
+    /// The culprit matches an excluded pattern.
+    Excluded(String),
+    /// Below the thresholds, with the reasons.
+    Below(Vec<String>),
+}
+
+impl Impact {
+    /// The response skipping the issue, when no fix job is queued.
+    fn skipped(&self) -> Option<(StatusCode, Json<WebhookResponse>)> {
+        match self {
+            Impact::Sufficient => None,
+            Impact::Excluded(reason) => Some(skipped(format!("Excluded: {reason}"))),
+            Impact::Below(shortfalls) => Some(skipped(format!(
+                "Below impact thresholds: {}",
+                shortfalls.join("; ")
+            ))),
+        }
+    }
+}
+
+/// Compare an issue to the impact thresholds. Stats are fetched only when a
+/// threshold needs them.
+async fn check_impact(
+    thresholds: &ImpactThresholds,
+    issue: &Issue,
+    client: Option<&SentryClient>,
+    now: DateTime<Utc>,
+) -> Result<Impact, AppError> {
+    if let Some(reason) = thresholds.excluded_culprit(&issue.culprit) {
+        return Ok(Impact::Excluded(reason));
+    }
+    if !thresholds.needs_stats() {
+        return Ok(Impact::Sufficient);
+    }
+
+    let client =
+        client.ok_or_else(|| AppError::Internal("SENTRY_AUTH_TOKEN not configured".into()))?;
+    let stats = client
+        .get_issue(&issue.id)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to fetch Sentry issue: {e}")))?;
+    let shortfalls = thresholds.shortfalls(&stats, now);
+    if shortfalls.is_empty() {
+        Ok(Impact::Sufficient)
+    } else {
+        Ok(Impact::Below(shortfalls))
+    }
+}
+
 fn build_sentry_webhook_payload(
-    issue: &crate::sentry::Issue,
+    issue: &Issue,
     organization: &str,
+    installation: &str,
     mapping: &crate::sentry::SentryProjectMapping,
 ) -> SentryFixPayload {
     SentryFixPayload {
@@ -125,5 +297,166 @@ fn build_sentry_webhook_payload(
         target_branch: mapping.target_branch.clone(),
         vcs_platform: mapping.vcs_platform.clone(),
         vcs_project: mapping.vcs_project.clone(),
+        path_rewrites: mapping.path_rewrites.clone(),
+        sentry_installation: Some(installation.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use axum::Router;
+    use axum::http::Uri;
+    use serde_json::{Value, json};
+
+    /// Serve a fake Sentry API on a local port, answering issue lookups with
+    /// `stats` and recording the requested paths.
+    async fn fake_sentry(stats: Value) -> (SentryClient, Arc<Mutex<Vec<String>>>) {
+        let paths: Arc<Mutex<Vec<String>>> = Arc::default();
+        let recorded = paths.clone();
+        let app = Router::new().fallback(move |uri: Uri| async move {
+            recorded.lock().unwrap().push(uri.path().to_string());
+            Json(stats)
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client = SentryClient::new("acme", "token")
+            .unwrap()
+            .with_base_url(&url);
+        (client, paths)
+    }
+
+    fn issue(culprit: &str) -> Issue {
+        serde_json::from_value(json!({
+            "id": "4242",
+            "shortId": "WEB-7",
+            "title": "TypeError: Cannot read properties of null",
+            "culprit": culprit,
+            "platform": "php",
+            "status": "unresolved",
+            "firstSeen": "2025-03-01T09:00:00Z",
+            "lastSeen": "2025-03-01T11:00:00Z",
+            "project": { "id": "1", "slug": "web", "name": "Web" }
+        }))
+        .unwrap()
+    }
+
+    fn thresholds() -> ImpactThresholds {
+        serde_json::from_value(json!({
+            "min_events": 10,
+            "min_users": 2,
+            "exclude_culprits": ["^vendor/"]
+        }))
+        .unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2025-03-01T12:00:00Z".parse().unwrap()
+    }
+
+    /// The status and message of the response skipping an issue.
+    fn skip_reason(impact: &Impact) -> Option<(String, String)> {
+        let (status, Json(response)) = impact.skipped()?;
+        assert_eq!(status, StatusCode::OK);
+        Some((response.status, response.message.unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_excluded_culprit_skips_without_stats() {
+        let (client, paths) = fake_sentry(json!({})).await;
+
+        let impact = check_impact(
+            &thresholds(),
+            &issue("vendor/guzzle/Client.php"),
+            Some(&client),
+            now(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            skip_reason(&impact),
+            Some((
+                "skipped".into(),
+                "Excluded: culprit `vendor/guzzle/Client.php` matches excluded pattern `^vendor/`"
+                    .into()
+            ))
+        );
+        assert!(matches!(impact, Impact::Excluded(_)));
+        assert!(paths.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_below_thresholds_skips_with_reasons() {
+        let (client, paths) = fake_sentry(json!({ "count": "3", "userCount": 1 })).await;
+
+        let impact = check_impact(
+            &thresholds(),
+            &issue("app/Orders.php"),
+            Some(&client),
+            now(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            skip_reason(&impact),
+            Some((
+                "skipped".into(),
+                "Below impact thresholds: 3 events, below the minimum of 10; \
+                 1 users, below the minimum of 2"
+                    .into()
+            ))
+        );
+        assert_eq!(*paths.lock().unwrap(), ["/organizations/acme/issues/4242/"]);
+    }
+
+    #[tokio::test]
+    async fn test_sufficient_impact_queues() {
+        let (client, _) = fake_sentry(json!({ "count": 250, "userCount": 40 })).await;
+
+        let impact = check_impact(
+            &thresholds(),
+            &issue("app/Orders.php"),
+            Some(&client),
+            now(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(impact, Impact::Sufficient);
+        assert_eq!(skip_reason(&impact), None);
+
+        // Without thresholds needing stats, no client is needed.
+        let impact = check_impact(
+            &ImpactThresholds::default(),
+            &issue("app/Orders.php"),
+            None,
+            now(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(impact, Impact::Sufficient);
+        assert!(
+            check_impact(&thresholds(), &issue("app/Orders.php"), None, now())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parked_issue_roundtrip() {
+        let parked = ParkedSentryIssue {
+            issue: issue("app/Orders.php"),
+            installation: "install-1".into(),
+            reasons: vec!["3 events, below the minimum of 10".into()],
+            parked_at: now(),
+        };
+        let json = serde_json::to_value(&parked).unwrap();
+        assert_eq!(json["issue"]["shortId"], "WEB-7");
+        let parsed: ParkedSentryIssue = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.issue.culprit, "app/Orders.php");
+        assert_eq!(parsed.installation, "install-1");
+        assert_eq!(parsed.parked_at, now());
     }
 }